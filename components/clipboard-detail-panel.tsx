@@ -1,14 +1,20 @@
-import React from "react";
+import React, { useEffect, useState } from "react";
 import {
   Copy, Check, Pin, Trash2, ClipboardPaste, Clock,
-  Link, Mail, Phone, Code, FolderOpen, Hash, Braces, Palette, FileText, Type,
+  Link, Mail, Phone, Code, FolderOpen, Hash, Braces, Palette, Type, Image as ImageIcon, Files,
+  Network, Fingerprint, Calendar, Binary, FileText, Tag, Zap,
 } from "lucide-react";
-import type { ClipboardEntry } from "@/hooks/use-clipboard-history";
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
+import type {
+  ClipboardAction, ClipboardCollection, ClipboardEntry, ClipboardImage, ClipboardMetadata, ClipboardTransform,
+} from "@/hooks/use-clipboard-history";
 
-const CONTENT_TYPE_META: Record<
-  ClipboardEntry["contentType"],
-  { icon: React.ComponentType<{ className?: string }>; label: string }
-> = {
+/** Select value that asks for a new collection's name; ids are UUIDs. */
+const NEW_COLLECTION = "new";
+
+type ContentTypeMeta = { icon: React.ComponentType<{ className?: string }>; label: string };
+
+const CONTENT_TYPE_META: Record<string, ContentTypeMeta> = {
   text:   { icon: Type,       label: "Text" },
   link:   { icon: Link,       label: "Link" },
   email:  { icon: Mail,       label: "Email" },
@@ -18,11 +24,92 @@ const CONTENT_TYPE_META: Record<
   number: { icon: Hash,       label: "Number" },
   json:   { icon: Braces,     label: "JSON" },
   color:  { icon: Palette,    label: "Color" },
-  image:  { icon: FileText,   label: "Image" },
+  image:  { icon: ImageIcon,  label: "Image" },
+  files:  { icon: Files,      label: "Files" },
+  ip:       { icon: Network,     label: "IP Address" },
+  uuid:     { icon: Fingerprint, label: "UUID" },
+  datetime: { icon: Calendar,    label: "Date & Time" },
+  hash:     { icon: Binary,      label: "Hash" },
+  markdown: { icon: FileText,    label: "Markdown" },
+};
+
+/** Built-in types by name; custom types take their label from the metadata. */
+function contentTypeMeta(entry: ClipboardEntry): ContentTypeMeta {
+  if (entry.metadata?.kind === "custom") return { icon: Tag, label: entry.metadata.label };
+  return CONTENT_TYPE_META[entry.contentType] ?? CONTENT_TYPE_META.text;
+}
+
+/** One-line summary of the classifier metadata, e.g. "rust" or "example.com". */
+function describeMetadata(metadata: ClipboardMetadata): string {
+  switch (metadata.kind) {
+    case "code": return metadata.language === "unknown" ? "" : metadata.language;
+    case "link": return metadata.port ? `${metadata.host}:${metadata.port}` : metadata.host;
+    case "color": {
+      const [r, g, b] = metadata.rgb;
+      const [h, s, l] = metadata.hsl;
+      const alpha = metadata.alpha < 1 ? ` · ${Math.round(metadata.alpha * 100)}%` : "";
+      return `rgb(${r}, ${g}, ${b}) · hsl(${h}, ${s}%, ${l}%)${alpha}`;
+    }
+    case "path": return metadata.remote ? "Network path" : metadata.exists ? (metadata.isDir ? "Folder" : "File") : "Not found";
+    case "number": return metadata.value.toLocaleString();
+    case "json": return `${metadata.shape === "object" ? "Object" : "Array"} · ${metadata.len} ${metadata.shape === "object" ? "keys" : "items"}`;
+    case "ip": return `IPv${metadata.version}${metadata.prefix !== undefined ? ` /${metadata.prefix}` : ""}${metadata.local ? " · local" : ""}`;
+    case "uuid": return `Version ${metadata.version}`;
+    case "datetime": {
+      const date = new Date(metadata.timestamp);
+      return metadata.hasTime ? date.toLocaleString() : date.toLocaleDateString();
+    }
+    case "hash": return metadata.algorithm.toUpperCase();
+    case "custom": return "";
+  }
+}
+
+const TRANSFORM_LABELS: Record<ClipboardTransform, string> = {
+  plain: "Plain text",
+  trim: "Trimmed",
+  upper: "UPPERCASE",
+  lower: "lowercase",
+  title: "Title Case",
+  "url-encode": "URL-encoded",
+  "url-decode": "URL-decoded",
+  "base64-encode": "Base64",
+  "base64-decode": "Base64-decoded",
+  "json-pretty": "Pretty JSON",
+  "json-minify": "Minified JSON",
 };
 
+/** Actions the backend offers for an entry, by content type. */
+function useClipboardActions(id: string) {
+  const [actions, setActions] = useState<ClipboardAction[]>([]);
+  useEffect(() => {
+    let cancelled = false;
+    setActions([]);
+    invoke<ClipboardAction[]>("get_clipboard_actions", { id })
+      .then((actions) => { if (!cancelled) setActions(actions); })
+      .catch(console.error);
+    return () => { cancelled = true; };
+  }, [id]);
+  return actions;
+}
+
+/** Encrypted image files can't go through the asset protocol. */
+function useImageSrc(image: ClipboardImage | undefined) {
+  const [src, setSrc] = useState<string | null>(null);
+  useEffect(() => {
+    if (!image) return setSrc(null);
+    if (!image.encrypted) return setSrc(convertFileSrc(image.thumbnailPath));
+    let cancelled = false;
+    setSrc(null);
+    invoke<string>("read_clipboard_image", { path: image.thumbnailPath })
+      .then((png) => { if (!cancelled) setSrc(`data:image/png;base64,${png}`); })
+      .catch(console.error);
+    return () => { cancelled = true; };
+  }, [image]);
+  return src;
+}
+
 export function getClipboardIcon(contentType: ClipboardEntry["contentType"]) {
-  return CONTENT_TYPE_META[contentType]?.icon ?? Type;
+  return CONTENT_TYPE_META[contentType]?.icon ?? Tag;
 }
 
 function ActionButton({
@@ -58,7 +145,7 @@ function ActionButton({
   );
 }
 
-function formatTimestamp(ts: number): string {
+export function formatTimestamp(ts: number): string {
   const date = new Date(ts);
   const now = new Date();
   const diffMs = now.getTime() - date.getTime();
@@ -76,23 +163,73 @@ function formatTimestamp(ts: number): string {
   return date.toLocaleDateString(undefined, { month: "short", day: "numeric" });
 }
 
+function formatBytes(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
 export function ClipboardDetailPanel({
   entry,
   onCopy,
   onPaste,
   onPin,
   onDelete,
+  onRunAction,
+  collections,
+  onCreateCollection,
+  onMove,
+  onSetTags,
+  onPromote,
   copied,
 }: {
   entry: ClipboardEntry;
   onCopy: () => void;
-  onPaste: () => void;
+  onPaste: (transforms?: ClipboardTransform[]) => void;
   onPin: () => void;
   onDelete: () => void;
+  onRunAction: (id: string, action: string) => Promise<string | null>;
+  collections: ClipboardCollection[];
+  /** Resolves to the new collection, or an error message. */
+  onCreateCollection: (name: string) => Promise<ClipboardCollection | string>;
+  onMove: (collection: string | null) => void;
+  onSetTags: (tags: string[]) => void;
+  /** Resolves to an error message, or null once the snippet exists. */
+  onPromote: (id: string, keyword: string) => Promise<string | null>;
   copied: boolean;
 }) {
-  const meta = CONTENT_TYPE_META[entry.contentType] ?? CONTENT_TYPE_META.text;
+  const meta = contentTypeMeta(entry);
+  const actions = useClipboardActions(entry.id);
+  const [actionMessage, setActionMessage] = useState<string | null>(null);
+  useEffect(() => setActionMessage(null), [entry.id]);
+  // Name typed for a new collection; null while the select is shown
+  const [newCollection, setNewCollection] = useState<string | null>(null);
+  const [collectionError, setCollectionError] = useState<string | null>(null);
+  const [snippetMessage, setSnippetMessage] = useState<string | null>(null);
+  useEffect(() => {
+    setNewCollection(null);
+    setCollectionError(null);
+    setSnippetMessage(null);
+  }, [entry.id]);
+
+  const promote = async (input: HTMLInputElement) => {
+    const keyword = input.value.trim();
+    if (!keyword) return;
+    const error = await onPromote(entry.id, keyword);
+    setSnippetMessage(error ?? `Saved as snippet ${keyword}`);
+    if (!error) input.value = "";
+  };
+
+  const createAndMove = async () => {
+    if (!newCollection?.trim()) return setNewCollection(null);
+    const created = await onCreateCollection(newCollection);
+    if (typeof created === "string") return setCollectionError(created);
+    onMove(created.id);
+    setNewCollection(null);
+    setCollectionError(null);
+  };
   const Icon = meta.icon;
+  const imageSrc = useImageSrc(entry.image);
 
   return (
     <div className="flex h-full flex-col items-center justify-center gap-4 px-8 text-center animate-slide-right">
@@ -107,20 +244,46 @@ export function ClipboardDetailPanel({
           {entry.pinned && <Pin className="h-3 w-3 text-[var(--accent-coral)]" />}
           <p className="text-[13px] font-semibold text-white/90 tracking-tight">{meta.label}</p>
         </div>
+        {entry.metadata && describeMetadata(entry.metadata) && (
+          <p className="text-[10px] text-white/40">{describeMetadata(entry.metadata)}</p>
+        )}
+        {entry.sourceDetails?.title && (
+          <p className="mx-auto max-w-[280px] truncate text-[10px] text-white/30" title={entry.sourceDetails.title}>
+            {entry.sourceDetails.title}
+          </p>
+        )}
         <div className="flex items-center justify-center gap-2 text-[10px] text-white/30">
-          <span className="inline-flex items-center gap-1 rounded-full bg-white/[0.06] px-2 py-0.5">
+          <span
+            className="inline-flex items-center gap-1 rounded-full bg-white/[0.06] px-2 py-0.5"
+            title={entry.sourceDetails?.exePath}
+          >
+            {entry.sourceDetails?.icon && (
+              <img src={convertFileSrc(entry.sourceDetails.icon)} alt="" className="h-3 w-3" />
+            )}
             {entry.sourceApp}
           </span>
           <span className="inline-flex items-center gap-1">
             <Clock className="h-2.5 w-2.5" />
             {formatTimestamp(entry.timestamp)}
           </span>
+          {(entry.copyCount ?? 1) > 1 && (
+            <span className="inline-flex items-center gap-1">
+              <Copy className="h-2.5 w-2.5" />
+              {entry.copyCount}×
+            </span>
+          )}
         </div>
       </div>
 
       {/* Content Preview */}
       <div className="w-full max-w-[280px] rounded-xl bg-white/[0.03] border border-white/[0.05] px-4 py-3 text-left">
-        {entry.contentType === "color" ? (
+        {entry.image ? (
+          imageSrc && <img
+            src={imageSrc}
+            alt={entry.preview}
+            className="mx-auto max-h-[120px] rounded-lg object-contain"
+          />
+        ) : entry.contentType === "color" ? (
           <div className="flex items-center gap-3">
             <div
               className="h-8 w-8 rounded-lg border border-white/10 flex-shrink-0"
@@ -143,11 +306,102 @@ export function ClipboardDetailPanel({
         )}
       </div>
 
+      {/* Formats */}
+      {entry.formats && (
+        <div className="flex items-center justify-center gap-1.5">
+          {[
+            entry.formats.html && "HTML",
+            entry.formats.rtf && "RTF",
+            entry.formats.files?.length && `${entry.formats.files.length} file${entry.formats.files.length !== 1 ? "s" : ""}`,
+          ]
+            .filter(Boolean)
+            .map((label) => (
+              <span
+                key={label as string}
+                className="inline-flex items-center rounded-full bg-white/[0.06] px-2 py-0.5 text-[9px] font-medium text-white/40"
+              >
+                {label}
+              </span>
+            ))}
+        </div>
+      )}
+
+      {/* Collection & Tags */}
+      <div className="flex w-full max-w-[280px] items-center gap-1.5">
+        {newCollection === null ? (
+          <select
+            value={entry.collection ?? ""}
+            onChange={(e) => {
+              if (e.target.value === NEW_COLLECTION) setNewCollection("");
+              else onMove(e.target.value || null);
+            }}
+            className="min-w-0 flex-1 rounded-lg border bg-white/[0.03] border-white/[0.06] px-2 py-1.5 text-[10px] font-medium text-white/40 hover:text-white/70 cursor-pointer outline-none"
+          >
+            <option value="">No collection</option>
+            {collections.map((collection) => (
+              <option key={collection.id} value={collection.id}>{collection.name}</option>
+            ))}
+            <option value={NEW_COLLECTION}>New collection…</option>
+          </select>
+        ) : (
+          <input
+            autoFocus
+            placeholder="Collection name"
+            value={newCollection}
+            onChange={(e) => setNewCollection(e.target.value)}
+            onKeyDown={(e) => {
+              e.stopPropagation();
+              if (e.key === "Enter") createAndMove();
+              if (e.key === "Escape") setNewCollection(null);
+            }}
+            onBlur={() => setNewCollection(null)}
+            className="min-w-0 flex-1 rounded-lg border bg-white/[0.03] border-[var(--accent-coral-border)] px-2 py-1.5 text-[10px] text-white/60 outline-none"
+          />
+        )}
+        <input
+          key={entry.id}
+          placeholder="Tags"
+          defaultValue={(entry.tags ?? []).join(", ")}
+          onKeyDown={(e) => {
+            e.stopPropagation();
+            if (e.key === "Enter") e.currentTarget.blur();
+          }}
+          onBlur={(e) => {
+            const tags = e.target.value.split(/[,\s]+/).filter(Boolean);
+            if (tags.join(",") !== (entry.tags ?? []).join(",")) onSetTags(tags);
+          }}
+          className="min-w-0 flex-1 rounded-lg border bg-white/[0.03] border-white/[0.06] px-2 py-1.5 text-[10px] text-white/60 outline-none focus:border-[var(--accent-coral-border)]"
+        />
+      </div>
+      {collectionError && <p className="text-[10px] text-red-400/70">{collectionError}</p>}
+      {!entry.image && (
+        <input
+          key={`snippet-${entry.id}`}
+          placeholder="Save as snippet: keyword + Enter"
+          onKeyDown={(e) => {
+            e.stopPropagation();
+            if (e.key === "Enter") promote(e.currentTarget);
+          }}
+          className="w-full max-w-[280px] rounded-lg border bg-white/[0.03] border-white/[0.06] px-2 py-1.5 text-[10px] text-white/60 outline-none focus:border-[var(--accent-coral-border)]"
+        />
+      )}
+      {snippetMessage && <p className="text-[10px] text-white/40">{snippetMessage}</p>}
+
       {/* Stats */}
       <div className="flex items-center gap-3 text-[10px] text-white/25">
-        <span>{entry.wordCount} word{entry.wordCount !== 1 ? "s" : ""}</span>
-        <span className="text-white/10">|</span>
-        <span>{entry.charCount} char{entry.charCount !== 1 ? "s" : ""}</span>
+        {entry.image ? (
+          <>
+            <span>{entry.image.width}×{entry.image.height}</span>
+            <span className="text-white/10">|</span>
+            <span>{formatBytes(entry.image.byteSize)}</span>
+          </>
+        ) : (
+          <>
+            <span>{entry.wordCount} word{entry.wordCount !== 1 ? "s" : ""}</span>
+            <span className="text-white/10">|</span>
+            <span>{entry.charCount} char{entry.charCount !== 1 ? "s" : ""}</span>
+          </>
+        )}
       </div>
 
       {/* Actions */}
@@ -160,10 +414,22 @@ export function ClipboardDetailPanel({
           accent={copied}
         />
         <ActionButton
-          onClick={onPaste}
+          onClick={() => onPaste()}
           icon={<ClipboardPaste className="h-3 w-3" />}
           label="Paste"
         />
+        {!entry.image && (
+          <select
+            value=""
+            onChange={(e) => onPaste([e.target.value as ClipboardTransform])}
+            className="rounded-lg border bg-white/[0.03] border-white/[0.06] px-2 py-1.5 text-[10px] font-medium text-white/40 hover:text-white/70 cursor-pointer outline-none"
+          >
+            <option value="" disabled>Paste as…</option>
+            {Object.entries(TRANSFORM_LABELS).map(([name, label]) => (
+              <option key={name} value={name}>{label}</option>
+            ))}
+          </select>
+        )}
         <ActionButton
           onClick={onPin}
           active={entry.pinned}
@@ -179,11 +445,34 @@ export function ClipboardDetailPanel({
         />
       </div>
 
+      {/* Actions for the content type */}
+      {actions.length > 0 && (
+        <div className="flex flex-wrap items-center justify-center gap-1.5">
+          {actions.map((action) => (
+            <ActionButton
+              key={action.id}
+              onClick={() => onRunAction(entry.id, action.id).then(setActionMessage)}
+              icon={<Zap className="h-3 w-3" />}
+              label={action.label}
+            />
+          ))}
+        </div>
+      )}
+      {actionMessage && <p className="text-[10px] text-white/40">{actionMessage}</p>}
+
       {/* Hint */}
       <p className="text-[10px] text-white/20 tracking-wide">
         <span className="text-white/30">Enter</span> copy
         <span className="mx-2 text-white/10">|</span>
         <span className="text-white/30">Ctrl+Enter</span> paste
+        <span className="mx-2 text-white/10">|</span>
+        <span className="text-white/30">Ctrl+S</span> paste stack
+        {!entry.image && (
+          <>
+            <span className="mx-2 text-white/10">|</span>
+            <span className="text-white/30">Ctrl+Shift+Enter</span> paste plain
+          </>
+        )}
       </p>
     </div>
   );
@@ -11,11 +11,11 @@ import { SettingsPanel } from "@/components/settings-menu";
 import { SnippetForm } from "@/components/snippet-form";
 import { QuicklinkForm } from "@/components/quicklink-form";
 import { ICON_MAP } from "@/components/icon-picker";
-import { useSnippets, type Snippet } from "@/hooks/use-snippets";
+import { useSnippets, type Snippet, type SnippetStats } from "@/hooks/use-snippets";
 import { useQuicklinks, type Quicklink } from "@/hooks/use-quicklinks";
-import { useClipboardHistory, type ClipboardEntry } from "@/hooks/use-clipboard-history";
+import { useClipboardHistory, type ClipboardEntry, type ClipboardTransform } from "@/hooks/use-clipboard-history";
 import { useInstalledApps, type InstalledApp } from "@/hooks/use-installed-apps";
-import { ClipboardDetailPanel, getClipboardIcon } from "@/components/clipboard-detail-panel";
+import { ClipboardDetailPanel, formatTimestamp, getClipboardIcon } from "@/components/clipboard-detail-panel";
 import { resolvePlaceholders, previewPlaceholders } from "@/lib/resolve-placeholders";
 import { getCurrentWindow, Effect } from "@tauri-apps/api/window";
 import { LogicalSize } from "@tauri-apps/api/dpi";
@@ -64,6 +64,7 @@ function TagBadge({ label }: { label: string }) {
 
 function DetailPanel({
   item,
+  stats,
   onEdit,
   onCopy,
   onPaste,
@@ -71,6 +72,7 @@ function DetailPanel({
   copied,
 }: {
   item: CommandItem;
+  stats?: SnippetStats;
   onEdit: () => void;
   onCopy: () => void;
   onPaste: () => void;
@@ -118,6 +120,14 @@ function DetailPanel({
         </div>
       )}
 
+      {/* Usage */}
+      {stats && stats.expansions > 0 && (
+        <p className="text-[10px] text-white/25">
+          Expanded {stats.expansions} time{stats.expansions !== 1 ? "s" : ""}
+          {stats.lastExpandedAt ? ` · ${formatTimestamp(stats.lastExpandedAt)}` : ""}
+        </p>
+      )}
+
       {/* Actions */}
       <div className="flex items-center gap-1.5 mt-1">
         <ActionButton
@@ -241,14 +251,19 @@ function clipboardEntryToItem(entry: ClipboardEntry): CommandItem {
     subtitle: `${entry.sourceApp} · ${entry.contentType}`,
     icon: Icon,
     category: entry.pinned ? "Pinned" : "Clipboard",
-    keywords: [entry.contentType, entry.sourceApp, "clipboard"],
+    keywords: [entry.contentType, entry.sourceApp, "clipboard", ...(entry.tags ?? [])],
     body: entry.content,
+    tags: entry.tags,
     pinned: entry.pinned,
     itemType: "clipboard",
     clipboardEntry: entry,
   };
 }
 
+function hasRichContent(entry: ClipboardEntry): boolean {
+  return !!entry.image || !!entry.formats;
+}
+
 function installedAppToItem(app: InstalledApp): CommandItem {
   const location = app.location?.trim();
   const sourceLabel = (() => {
@@ -295,9 +310,9 @@ export function CommandBar() {
   const [query, setQuery] = useState("");
   const [selectedIndex, setSelectedIndex] = useState(0);
 
-  const { snippets, addSnippet, updateSnippet, deleteSnippet, togglePin: toggleSnippetPin, duplicateSnippet } = useSnippets();
+  const { snippets, conflicts: triggerConflicts, stats: snippetStats, addSnippet, updateSnippet, deleteSnippet, togglePin: toggleSnippetPin, duplicateSnippet } = useSnippets();
   const { quicklinks, addQuicklink, updateQuicklink, deleteQuicklink, togglePin: toggleQuicklinkPin, duplicateQuicklink } = useQuicklinks();
-  const { entries: clipboardEntries, deleteEntry: deleteClipboardEntry, togglePin: toggleClipboardPin, pasteEntry: pasteClipboardEntry } = useClipboardHistory();
+  const { entries: clipboardEntries, deleteEntry: deleteClipboardEntry, togglePin: toggleClipboardPin, pasteEntry: pasteClipboardEntry, copyEntry: copyClipboardEntry, searchEntries: searchClipboardEntries, pasteStack, togglePasteStack, runAction: runClipboardAction, collections: clipboardCollections, createCollection: createClipboardCollection, moveEntries: moveClipboardEntries, setEntryTags: setClipboardEntryTags, promoteEntry: promoteClipboardEntry } = useClipboardHistory();
   const { apps: installedApps, launchApp } = useInstalledApps();
 
   const [isCreating, setIsCreating] = useState(false);
@@ -383,13 +398,31 @@ export function CommandBar() {
     });
   }, [quicklinks]);
 
+  // While searching inside the clipboard submenu, results come from the backend.
+  const [clipboardSearchResults, setClipboardSearchResults] = useState<ClipboardEntry[] | null>(null);
+  const clipboardSearchText = activeSubmenu === "clipboard" ? query.trim() : "";
+
+  useEffect(() => {
+    if (!clipboardSearchText) {
+      setClipboardSearchResults(null);
+      return;
+    }
+    let cancelled = false;
+    const timer = setTimeout(async () => {
+      const results = await searchClipboardEntries({ text: clipboardSearchText, limit: 100 });
+      if (!cancelled && results) setClipboardSearchResults(results.entries);
+    }, 120);
+    return () => { cancelled = true; clearTimeout(timer); };
+  }, [clipboardSearchText, clipboardEntries, searchClipboardEntries]);
+
   const clipboardItems = useMemo(() => {
+    if (clipboardSearchResults) return clipboardSearchResults;
     return [...clipboardEntries].sort((a, b) => {
       if (a.pinned && !b.pinned) return -1;
       if (!a.pinned && b.pinned) return 1;
       return 0;
     });
-  }, [clipboardEntries]);
+  }, [clipboardEntries, clipboardSearchResults]);
 
   const sortedInstalledApps = useMemo(() => {
     return [...installedApps].sort((a, b) => a.name.localeCompare(b.name));
@@ -441,6 +474,8 @@ export function CommandBar() {
 
   const filtered = useMemo(() => {
     if (!query.trim()) return items;
+    // Already filtered and ranked by search_clipboard_history
+    if (activeSubmenu === "clipboard" && clipboardSearchResults) return items;
     const q = query.toLowerCase();
     return items.filter(
       (item) =>
@@ -449,7 +484,7 @@ export function CommandBar() {
         item.category.toLowerCase().includes(q) ||
         item.keywords?.some((kw) => kw.toLowerCase().includes(q))
     );
-  }, [query, items]);
+  }, [query, items, activeSubmenu, clipboardSearchResults]);
 
   const openCreateForm = useCallback(() => {
     setIsCreating(true);
@@ -524,10 +559,18 @@ export function CommandBar() {
     } catch {}
   }, []);
 
-  const pasteSnippet = useCallback(async (body: string) => {
+  // Image and rich entries are put back on the clipboard by the backend so every
+  // representation (PNG, HTML, RTF, file list) is restored.
+  const copyClipboardRich = useCallback(async (id: string) => {
+    await copyClipboardEntry(id);
+    setCopied(true);
+    setTimeout(() => setCopied(false), 1500);
+  }, [copyClipboardEntry]);
+
+  const pasteSnippet = useCallback(async (body: string, transforms?: ClipboardTransform[]) => {
     if (!isTauri()) return;
     try {
-      await invoke("paste_snippet", { body });
+      await invoke("paste_snippet", { body, transforms });
     } catch {}
   }, []);
 
@@ -572,6 +615,15 @@ export function CommandBar() {
     } else if (e.key === "Enter" && !showForm && selectedItem?.action) {
       e.preventDefault();
       selectedItem.action();
+    } else if (e.key === "Enter" && e.ctrlKey && e.shiftKey && !showForm && selectedItem?.clipboardEntry && !selectedItem.clipboardEntry.image) {
+      e.preventDefault();
+      pasteClipboardEntry(selectedItem.clipboardEntry.id, []);
+    } else if (e.key === "Enter" && e.ctrlKey && e.shiftKey && !showForm && selectedItem?.itemType === "snippet" && selectedItem.body) {
+      e.preventDefault();
+      pasteSnippet(selectedItem.body, []);
+    } else if (e.key === "Enter" && e.ctrlKey && !showForm && selectedItem?.clipboardEntry) {
+      e.preventDefault();
+      pasteClipboardEntry(selectedItem.clipboardEntry.id);
     } else if (e.key === "Enter" && e.ctrlKey && !showForm && selectedItem?.body) {
       e.preventDefault();
       pasteSnippet(selectedItem.body);
@@ -581,6 +633,9 @@ export function CommandBar() {
     } else if (e.key === "Enter" && !e.ctrlKey && !showForm && selectedItem?.link) {
       e.preventDefault();
       openLink(selectedItem.link);
+    } else if (e.key === "Enter" && !e.ctrlKey && !showForm && selectedItem?.clipboardEntry && hasRichContent(selectedItem.clipboardEntry)) {
+      e.preventDefault();
+      copyClipboardRich(selectedItem.clipboardEntry.id);
     } else if (e.key === "Enter" && !e.ctrlKey && !showForm && selectedItem?.body) {
       e.preventDefault();
       copyToClipboard(selectedItem.body);
@@ -607,6 +662,9 @@ export function CommandBar() {
         toggleClipboardPin(selectedItem.clipboardEntry.id);
       } else if (selectedItem.itemType === "quicklink") toggleQuicklinkPin(selectedItem.id);
       else if (selectedItem.itemType === "snippet") toggleSnippetPin(selectedItem.id);
+    } else if (e.key === "s" && e.ctrlKey && !showForm && selectedItem?.clipboardEntry) {
+      e.preventDefault();
+      togglePasteStack(selectedItem.clipboardEntry.id);
     } else if (e.key === "n" && e.ctrlKey && !showForm) {
       e.preventDefault();
       openCreateForm();
@@ -806,6 +864,11 @@ export function CommandBar() {
                         <div className="flex-1 min-w-0">
                           <div className="flex items-center gap-1.5">
                             {item.pinned && <Pin className="h-2.5 w-2.5 text-[var(--accent-coral)] flex-shrink-0" />}
+                            {item.clipboardEntry && pasteStack.includes(item.clipboardEntry.id) && (
+                              <span className="flex h-3.5 min-w-3.5 items-center justify-center rounded-full bg-[var(--accent-coral-dim)] px-1 text-[8px] font-semibold text-[var(--accent-coral)] flex-shrink-0">
+                                {pasteStack.indexOf(item.clipboardEntry.id) + 1}
+                              </span>
+                            )}
                             <p
                               className={`text-[12px] truncate transition-colors duration-150 ${
                                 isSelected
@@ -848,6 +911,7 @@ export function CommandBar() {
               ) : editingSnippet ? (
                 <SnippetForm
                   initial={editingSnippet}
+                  conflicts={triggerConflicts}
                   onSave={handleSaveEdit}
                   onCancel={closeForm}
                   onDelete={handleDelete}
@@ -923,15 +987,26 @@ export function CommandBar() {
             ) : selectedItem?.itemType === "clipboard" && selectedItem.clipboardEntry ? (
               <ClipboardDetailPanel
                 entry={selectedItem.clipboardEntry}
-                onCopy={() => selectedItem.body && copyToClipboard(selectedItem.body)}
-                onPaste={() => selectedItem.body && pasteClipboardEntry(selectedItem.body)}
+                onCopy={() => {
+                  const entry = selectedItem.clipboardEntry;
+                  if (entry && hasRichContent(entry)) copyClipboardRich(entry.id);
+                  else if (selectedItem.body) copyToClipboard(selectedItem.body);
+                }}
+                onPaste={(transforms) => selectedItem.clipboardEntry && pasteClipboardEntry(selectedItem.clipboardEntry.id, transforms)}
                 onPin={() => selectedItem.clipboardEntry && toggleClipboardPin(selectedItem.clipboardEntry.id)}
                 onDelete={() => selectedItem.clipboardEntry && deleteClipboardEntry(selectedItem.clipboardEntry.id)}
+                onRunAction={runClipboardAction}
+                collections={clipboardCollections}
+                onCreateCollection={createClipboardCollection}
+                onMove={(collection) => selectedItem.clipboardEntry && moveClipboardEntries([selectedItem.clipboardEntry.id], collection)}
+                onSetTags={(tags) => selectedItem.clipboardEntry && setClipboardEntryTags(selectedItem.clipboardEntry.id, tags)}
+                onPromote={promoteClipboardEntry}
                 copied={copied}
               />
             ) : selectedItem ? (
               <DetailPanel
                 item={selectedItem}
+                stats={selectedItem.itemType === "snippet" ? snippetStats[selectedItem.id] : undefined}
                 onEdit={startEdit}
                 onCopy={() => selectedItem.body && copyToClipboard(selectedItem.body)}
                 onPaste={() => selectedItem.body && pasteSnippet(selectedItem.body)}
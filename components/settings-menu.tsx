@@ -3,7 +3,7 @@
 import { useState, useRef, useEffect, useCallback } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { getCurrentWindow, Effect } from '@tauri-apps/api/window'
-import { X, Keyboard, Pin } from 'lucide-react'
+import { X, Keyboard, Pin, History, ShieldAlert, KeyRound, Copy, Download, Upload, Folder, Trash2 } from 'lucide-react'
 
 // Check if running in Tauri environment (v2 uses __TAURI_INTERNALS__)
 const isTauri = () => typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
@@ -13,6 +13,69 @@ interface ShortcutConfig {
   key: string
 }
 
+interface RetentionPolicy {
+  maxEntries: number | null
+  maxBytes: number | null
+  maxAgeDays: number | null
+  [key: string]: unknown
+}
+
+interface SensitiveSettings {
+  deniedApps: string[]
+  detectSecrets: boolean
+  secretAction: 'skip' | 'expire'
+  [key: string]: unknown
+}
+
+interface ClipboardSettings {
+  retention: RetentionPolicy
+  sensitive: SensitiveSettings
+  mergeDuplicates: boolean
+  [key: string]: unknown
+}
+
+interface ExpansionSettings {
+  deniedApps: string[]
+  [key: string]: unknown
+}
+
+type KeySource = 'none' | 'keyring' | 'passphrase'
+
+interface EncryptionStatus {
+  source: KeySource
+  locked: boolean
+}
+
+interface ClipboardCollection {
+  id: string
+  name: string
+  createdAt: number
+}
+
+type ExportFormat = 'json' | 'csv' | 'markdown'
+
+interface ImportSummary {
+  added: number
+  merged: number
+}
+
+const MB = 1024 * 1024
+const DAY = 24 * 60 * 60 * 1000
+
+const EXPORT_TYPES = ['text', 'link', 'code', 'json', 'path', 'email', 'color'] as const
+
+// Start of a yyyy-mm-dd date input value in local time, in ms
+function parseDate(value: string): number | null {
+  if (!value) return null
+  const [year, month, day] = value.split('-').map(Number)
+  return new Date(year, month - 1, day).getTime()
+}
+
+function parseLimit(value: string, scale = 1): number | null {
+  const n = Number(value)
+  return value.trim() === '' || !Number.isFinite(n) || n <= 0 ? null : Math.round(n * scale)
+}
+
 function formatShortcut(config: ShortcutConfig): string {
   const labels = config.modifiers.map((m) =>
     m === 'Control' ? 'Ctrl' : m
@@ -46,6 +109,28 @@ export function SettingsPanel({ onClose }: { onClose: () => void }) {
     return (localStorage.getItem('backdrop-effect') as 'none' | 'acrylic' | 'mica') ?? 'none'
   })
 
+  const [clipboardSettings, setClipboardSettings] = useState<ClipboardSettings | null>(null)
+  const [expansionSettings, setExpansionSettings] = useState<ExpansionSettings | null>(null)
+  const [canScopeToApps, setCanScopeToApps] = useState(true)
+
+  const [encryption, setEncryption] = useState<EncryptionStatus | null>(null)
+  const [pendingSource, setPendingSource] = useState<KeySource | null>(null)
+  const [passphrase, setPassphrase] = useState('')
+  const [encryptionError, setEncryptionError] = useState<string | null>(null)
+  const [encryptionBusy, setEncryptionBusy] = useState(false)
+
+  const [collections, setCollections] = useState<ClipboardCollection[]>([])
+  const [collectionError, setCollectionError] = useState<string | null>(null)
+
+  const [exportFormat, setExportFormat] = useState<ExportFormat>('json')
+  const [exportFrom, setExportFrom] = useState('')
+  const [exportTo, setExportTo] = useState('')
+  const [exportType, setExportType] = useState<string>('all')
+  const [pinnedOnly, setPinnedOnly] = useState(false)
+  const [transferMessage, setTransferMessage] = useState<string | null>(null)
+  const [transferBusy, setTransferBusy] = useState(false)
+  const importRef = useRef<HTMLInputElement>(null)
+
   const [capturing, setCapturing] = useState(false)
   const captureRef = useRef<HTMLButtonElement>(null)
 
@@ -85,6 +170,140 @@ export function SettingsPanel({ onClose }: { onClose: () => void }) {
     localStorage.setItem('backdrop-effect', backdropEffect)
   }, [backdropEffect])
 
+  useEffect(() => {
+    if (!isTauri()) return
+    invoke<ClipboardSettings>('get_clipboard_settings')
+      .then(setClipboardSettings)
+      .catch(console.error)
+  }, [])
+
+  useEffect(() => {
+    if (!isTauri()) return
+    invoke<ExpansionSettings>('get_expansion_settings')
+      .then(setExpansionSettings)
+      .catch(console.error)
+    invoke<boolean>('can_scope_snippets_to_apps')
+      .then(setCanScopeToApps)
+      .catch(console.error)
+  }, [])
+
+  useEffect(() => {
+    if (!isTauri()) return
+    invoke<ClipboardCollection[]>('get_clipboard_collections')
+      .then(setCollections)
+      .catch(console.error)
+  }, [])
+
+  useEffect(() => {
+    if (!isTauri()) return
+    invoke<EncryptionStatus>('get_clipboard_encryption')
+      .then(setEncryption)
+      .catch(console.error)
+  }, [])
+
+  // Runs an encryption command, then refreshes the status
+  const runEncryption = useCallback(async (command: string, args: Record<string, unknown>) => {
+    setEncryptionBusy(true)
+    setEncryptionError(null)
+    try {
+      await invoke(command, args)
+      setEncryption(await invoke<EncryptionStatus>('get_clipboard_encryption'))
+      setPendingSource(null)
+      setPassphrase('')
+    } catch (e) {
+      setEncryptionError(String(e))
+    } finally {
+      setEncryptionBusy(false)
+    }
+  }, [])
+
+  const chooseKeySource = useCallback(
+    (source: KeySource) => {
+      if (!encryption || source === encryption.source) return setPendingSource(null)
+      // A passphrase must be entered before encrypting with it
+      if (source === 'passphrase') return setPendingSource(source)
+      runEncryption('set_clipboard_encryption', { source, passphrase: null })
+    },
+    [encryption, runEncryption]
+  )
+
+  // Runs a collection command, then refreshes the list
+  const runCollection = useCallback(async (command: string, args: Record<string, unknown>) => {
+    setCollectionError(null)
+    try {
+      await invoke(command, args)
+    } catch (e) {
+      setCollectionError(String(e))
+    }
+    setCollections(await invoke<ClipboardCollection[]>('get_clipboard_collections'))
+  }, [])
+
+  const exportHistory = useCallback(async () => {
+    setTransferBusy(true)
+    try {
+      const to = parseDate(exportTo)
+      const filter = {
+        from: parseDate(exportFrom),
+        // The end date is inclusive
+        to: to === null ? null : to + DAY - 1,
+        contentTypes: exportType === 'all' ? [] : [exportType],
+        pinnedOnly,
+      }
+      const path = await invoke<string>('export_clipboard_history', { format: exportFormat, filter, path: null })
+      setTransferMessage(`Exported to ${path}`)
+    } catch (e) {
+      setTransferMessage(String(e))
+    } finally {
+      setTransferBusy(false)
+    }
+  }, [exportFormat, exportFrom, exportTo, exportType, pinnedOnly])
+
+  const importHistory = useCallback(async (file: File) => {
+    setTransferBusy(true)
+    try {
+      const format: ExportFormat = file.name.toLowerCase().endsWith('.csv') ? 'csv' : 'json'
+      const contents = await file.text()
+      const summary = await invoke<ImportSummary>('import_clipboard_history', { format, contents })
+      setTransferMessage(`Imported ${summary.added} new, merged ${summary.merged} existing`)
+    } catch (e) {
+      setTransferMessage(String(e))
+    } finally {
+      setTransferBusy(false)
+    }
+  }, [])
+
+  const saveClipboardSettings = useCallback((settings: ClipboardSettings) => {
+    setClipboardSettings(settings)
+    invoke('set_clipboard_settings', { settings }).catch(console.error)
+  }, [])
+
+  const saveExpansionSettings = useCallback((settings: ExpansionSettings) => {
+    setExpansionSettings(settings)
+    invoke('set_expansion_settings', { settings }).catch(console.error)
+  }, [])
+
+  const updateRetention = useCallback(
+    (changes: Partial<RetentionPolicy>) => {
+      if (!clipboardSettings) return
+      saveClipboardSettings({
+        ...clipboardSettings,
+        retention: { ...clipboardSettings.retention, ...changes },
+      })
+    },
+    [clipboardSettings, saveClipboardSettings]
+  )
+
+  const updateSensitive = useCallback(
+    (changes: Partial<SensitiveSettings>) => {
+      if (!clipboardSettings) return
+      saveClipboardSettings({
+        ...clipboardSettings,
+        sensitive: { ...clipboardSettings.sensitive, ...changes },
+      })
+    },
+    [clipboardSettings, saveClipboardSettings]
+  )
+
   useEffect(() => {
     if (capturing && captureRef.current) captureRef.current.focus()
   }, [capturing])
@@ -239,6 +458,393 @@ export function SettingsPanel({ onClose }: { onClose: () => void }) {
         </button>
       </div>
 
+      {/* Clipboard history */}
+      {clipboardSettings && (
+        <div className="mb-8">
+          <p className="text-[9px] font-semibold uppercase tracking-[0.15em] text-white/25 mb-4">
+            Clipboard History
+          </p>
+          <div className="space-y-2">
+            {([
+              { label: 'Max entries', key: 'maxEntries', scale: 1 },
+              { label: 'Max age (days)', key: 'maxAgeDays', scale: 1 },
+              { label: 'Max size (MB)', key: 'maxBytes', scale: MB },
+            ] as const).map(({ label, key, scale }) => {
+              const value = clipboardSettings.retention[key]
+              return (
+                <div key={key} className="flex items-center justify-between gap-3">
+                  <div className="flex items-center gap-2.5">
+                    <History className="h-3.5 w-3.5 text-white/40" />
+                    <span className="text-[11px] text-white/50">{label}</span>
+                  </div>
+                  <input
+                    type="number"
+                    min={1}
+                    placeholder="Unlimited"
+                    defaultValue={value == null ? '' : String(Math.round((value / scale) * 100) / 100)}
+                    onBlur={(e) => updateRetention({ [key]: parseLimit(e.target.value, scale) })}
+                    onKeyDown={(e) => e.stopPropagation()}
+                    className="w-24 rounded-lg border border-white/[0.06] bg-white/[0.03] px-2 py-1.5 text-right text-[11px] text-white/60 tabular-nums outline-none focus:border-[var(--accent-coral-border)]"
+                    style={{ fontFamily: "'JetBrains Mono', monospace" }}
+                  />
+                </div>
+              )
+            })}
+          </div>
+          <p className="mt-2 text-[10px] text-white/20">
+            Pinned entries are always kept. Leave empty for no limit
+          </p>
+
+          <button
+            type="button"
+            onClick={() =>
+              saveClipboardSettings({ ...clipboardSettings, mergeDuplicates: !clipboardSettings.mergeDuplicates })
+            }
+            className="mt-5 w-full flex items-center justify-between rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2.5 cursor-pointer hover:bg-white/[0.06] transition-colors"
+          >
+            <div className="flex items-center gap-2.5">
+              <Copy className="h-3.5 w-3.5 text-white/40" />
+              <span className="text-[11px] text-white/50">Merge duplicate copies</span>
+            </div>
+            <div
+              className={`relative w-8 h-[18px] rounded-full transition-colors duration-200 ${
+                clipboardSettings.mergeDuplicates ? 'bg-[var(--accent-coral)]' : 'bg-white/[0.1]'
+              }`}
+            >
+              <div
+                className={`absolute top-[3px] h-3 w-3 rounded-full bg-white transition-transform duration-200 ${
+                  clipboardSettings.mergeDuplicates ? 'translate-x-[17px]' : 'translate-x-[3px]'
+                }`}
+              />
+            </div>
+          </button>
+
+          {/* Sensitive content */}
+          <div className="mt-5 space-y-2">
+            <button
+              type="button"
+              onClick={() => updateSensitive({ detectSecrets: !clipboardSettings.sensitive.detectSecrets })}
+              className="w-full flex items-center justify-between rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2.5 cursor-pointer hover:bg-white/[0.06] transition-colors"
+            >
+              <div className="flex items-center gap-2.5">
+                <ShieldAlert className="h-3.5 w-3.5 text-white/40" />
+                <span className="text-[11px] text-white/50">Detect passwords and keys</span>
+              </div>
+              <div
+                className={`relative w-8 h-[18px] rounded-full transition-colors duration-200 ${
+                  clipboardSettings.sensitive.detectSecrets ? 'bg-[var(--accent-coral)]' : 'bg-white/[0.1]'
+                }`}
+              >
+                <div
+                  className={`absolute top-[3px] h-3 w-3 rounded-full bg-white transition-transform duration-200 ${
+                    clipboardSettings.sensitive.detectSecrets ? 'translate-x-[17px]' : 'translate-x-[3px]'
+                  }`}
+                />
+              </div>
+            </button>
+            {clipboardSettings.sensitive.detectSecrets && (
+              <div className="flex gap-1.5">
+                {([
+                  { action: 'skip', label: 'Never record' },
+                  { action: 'expire', label: 'Forget after a minute' },
+                ] as const).map(({ action, label }) => (
+                  <button
+                    key={action}
+                    type="button"
+                    onClick={() => updateSensitive({ secretAction: action })}
+                    className={`flex-1 rounded-lg border px-3 py-2 text-[11px] font-medium transition-all duration-200 cursor-pointer ${
+                      clipboardSettings.sensitive.secretAction === action
+                        ? 'bg-[var(--accent-coral-dim)] border-[var(--accent-coral-border)] text-[var(--accent-coral)]'
+                        : 'border-white/[0.06] bg-white/[0.03] text-white/40 hover:text-white/70 hover:bg-white/[0.06]'
+                    }`}
+                  >
+                    {label}
+                  </button>
+                ))}
+              </div>
+            )}
+            <span className="block pt-1 text-[11px] text-white/50">Ignored apps</span>
+            <input
+              type="text"
+              placeholder="e.g. KeePassXC, 1Password"
+              defaultValue={clipboardSettings.sensitive.deniedApps.join(', ')}
+              onBlur={(e) =>
+                updateSensitive({
+                  deniedApps: e.target.value.split(',').map((app) => app.trim()).filter(Boolean),
+                })
+              }
+              onKeyDown={(e) => e.stopPropagation()}
+              className="w-full rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2 text-[11px] text-white/60 outline-none focus:border-[var(--accent-coral-border)]"
+            />
+          </div>
+        </div>
+      )}
+
+      {/* Text expansion */}
+      {expansionSettings && (
+        <div className="mb-8">
+          <p className="text-[9px] font-semibold uppercase tracking-[0.15em] text-white/25 mb-4">
+            Text Expansion
+          </p>
+          <span className="block text-[11px] text-white/50 mb-2">Never expand snippets in</span>
+          <input
+            type="text"
+            placeholder="e.g. WindowsTerminal, alacritty"
+            defaultValue={expansionSettings.deniedApps.join(', ')}
+            onBlur={(e) =>
+              saveExpansionSettings({
+                ...expansionSettings,
+                deniedApps: e.target.value.split(',').map((app) => app.trim()).filter(Boolean),
+              })
+            }
+            onKeyDown={(e) => e.stopPropagation()}
+            className="w-full rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2 text-[11px] text-white/60 outline-none focus:border-[var(--accent-coral-border)]"
+          />
+          {!canScopeToApps && (
+            <p className="mt-2 text-[10px] text-white/40">
+              The focused app can&apos;t be detected on Wayland, so this list doesn&apos;t apply and snippets limited to
+              apps won&apos;t expand.
+            </p>
+          )}
+          <p className="mt-2 text-[10px] text-white/20">
+            Snippets also expand in password fields. Add your password manager here to prevent that.
+          </p>
+        </div>
+      )}
+
+      {/* Encryption */}
+      {encryption && (
+        <div className="mb-8">
+          <p className="text-[9px] font-semibold uppercase tracking-[0.15em] text-white/25 mb-4">
+            History Encryption
+          </p>
+          <div className="space-y-2">
+            {encryption.locked ? (
+              <div className="flex items-center gap-2.5">
+                <KeyRound className="h-3.5 w-3.5 text-white/40" />
+                <span className="text-[11px] text-white/50">History is locked</span>
+              </div>
+            ) : (
+              <div className="flex gap-1.5">
+                {([
+                  { source: 'none', label: 'Off' },
+                  { source: 'keyring', label: 'OS keyring' },
+                  { source: 'passphrase', label: 'Passphrase' },
+                ] as const).map(({ source, label }) => (
+                  <button
+                    key={source}
+                    type="button"
+                    disabled={encryptionBusy}
+                    onClick={() => chooseKeySource(source)}
+                    className={`flex-1 rounded-lg border px-3 py-2 text-[11px] font-medium transition-all duration-200 cursor-pointer ${
+                      (pendingSource ?? encryption.source) === source
+                        ? 'bg-[var(--accent-coral-dim)] border-[var(--accent-coral-border)] text-[var(--accent-coral)]'
+                        : 'border-white/[0.06] bg-white/[0.03] text-white/40 hover:text-white/70 hover:bg-white/[0.06]'
+                    }`}
+                  >
+                    {label}
+                  </button>
+                ))}
+              </div>
+            )}
+            {(encryption.locked || pendingSource === 'passphrase' || encryption.source === 'passphrase') && (
+              <input
+                type="password"
+                placeholder={encryption.locked ? 'Passphrase' : pendingSource ? 'New passphrase' : 'New passphrase (to rotate)'}
+                value={passphrase}
+                onChange={(e) => setPassphrase(e.target.value)}
+                onKeyDown={(e) => e.stopPropagation()}
+                className="w-full rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2 text-[11px] text-white/60 outline-none focus:border-[var(--accent-coral-border)]"
+              />
+            )}
+            {(encryption.locked || pendingSource || encryption.source !== 'none') && (
+              <button
+                type="button"
+                disabled={encryptionBusy}
+                onClick={() => {
+                  if (encryption.locked) runEncryption('unlock_clipboard_history', { passphrase })
+                  else if (pendingSource) runEncryption('set_clipboard_encryption', { source: pendingSource, passphrase })
+                  else runEncryption('rotate_clipboard_key', { passphrase: passphrase || null })
+                }}
+                className="w-full rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2 text-[11px] text-white/60 cursor-pointer hover:bg-white/[0.06] transition-colors"
+              >
+                {encryptionBusy
+                  ? 'Working...'
+                  : encryption.locked
+                    ? 'Unlock'
+                    : pendingSource
+                      ? 'Encrypt history'
+                      : 'Rotate key'}
+              </button>
+            )}
+            {encryptionError && <p className="text-[10px] text-red-400/70">{encryptionError}</p>}
+          </div>
+        </div>
+      )}
+
+      {/* Collections */}
+      {isTauri() && (
+        <div className="mb-8">
+          <p className="text-[9px] font-semibold uppercase tracking-[0.15em] text-white/25 mb-4">
+            Clipboard Collections
+          </p>
+          <div className="space-y-2">
+            {collections.map((collection) => (
+              <div key={collection.id} className="flex items-center gap-1.5">
+                <div className="flex-1 flex items-center gap-2.5 rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2">
+                  <Folder className="h-3.5 w-3.5 text-white/40" />
+                  <input
+                    type="text"
+                    defaultValue={collection.name}
+                    onBlur={(e) => {
+                      if (e.target.value !== collection.name) {
+                        runCollection('rename_clipboard_collection', { id: collection.id, name: e.target.value })
+                      }
+                    }}
+                    onKeyDown={(e) => {
+                      e.stopPropagation()
+                      if (e.key === 'Enter') e.currentTarget.blur()
+                    }}
+                    className="min-w-0 flex-1 bg-transparent text-[11px] text-white/60 outline-none"
+                  />
+                </div>
+                <button
+                  type="button"
+                  title="Delete collection (entries stay in the history)"
+                  onClick={() => runCollection('delete_clipboard_collection', { id: collection.id })}
+                  className="rounded-lg border border-white/[0.06] bg-white/[0.03] p-2 text-white/40 cursor-pointer hover:text-red-400/70 hover:bg-white/[0.06] transition-colors"
+                >
+                  <Trash2 className="h-3.5 w-3.5" />
+                </button>
+              </div>
+            ))}
+            <input
+              type="text"
+              placeholder="New collection"
+              onKeyDown={(e) => {
+                e.stopPropagation()
+                if (e.key === 'Enter' && e.currentTarget.value.trim()) {
+                  runCollection('create_clipboard_collection', { name: e.currentTarget.value })
+                  e.currentTarget.value = ''
+                }
+              }}
+              className="w-full rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2 text-[11px] text-white/60 outline-none focus:border-[var(--accent-coral-border)]"
+            />
+            <p className="text-[10px] text-white/20">
+              {collectionError ?? 'Entries in a collection are kept like pinned ones.'}
+            </p>
+          </div>
+        </div>
+      )}
+
+      {/* Export & Import */}
+      {isTauri() && (
+        <div className="mb-8">
+          <p className="text-[9px] font-semibold uppercase tracking-[0.15em] text-white/25 mb-4">
+            Export &amp; Import
+          </p>
+          <div className="space-y-2">
+            <div className="flex gap-1.5">
+              {([
+                { format: 'json', label: 'JSON' },
+                { format: 'csv', label: 'CSV' },
+                { format: 'markdown', label: 'Markdown' },
+              ] as const).map(({ format, label }) => (
+                <button
+                  key={format}
+                  type="button"
+                  onClick={() => setExportFormat(format)}
+                  className={`flex-1 rounded-lg border px-3 py-2 text-[11px] font-medium transition-all duration-200 cursor-pointer ${
+                    exportFormat === format
+                      ? 'bg-[var(--accent-coral-dim)] border-[var(--accent-coral-border)] text-[var(--accent-coral)]'
+                      : 'border-white/[0.06] bg-white/[0.03] text-white/40 hover:text-white/70 hover:bg-white/[0.06]'
+                  }`}
+                >
+                  {label}
+                </button>
+              ))}
+            </div>
+            <div className="flex gap-1.5">
+              {([
+                { label: 'From', value: exportFrom, set: setExportFrom },
+                { label: 'To', value: exportTo, set: setExportTo },
+              ] as const).map(({ label, value, set }) => (
+                <label key={label} className="flex-1 flex items-center gap-2 rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2">
+                  <span className="text-[11px] text-white/50">{label}</span>
+                  <input
+                    type="date"
+                    value={value}
+                    onChange={(e) => set(e.target.value)}
+                    onKeyDown={(e) => e.stopPropagation()}
+                    className="min-w-0 flex-1 bg-transparent text-[11px] text-white/60 outline-none"
+                  />
+                </label>
+              ))}
+            </div>
+            <div className="flex gap-1.5">
+              <select
+                value={exportType}
+                onChange={(e) => setExportType(e.target.value)}
+                className="flex-1 rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2 text-[11px] text-white/60 outline-none"
+              >
+                <option value="all">All types</option>
+                {EXPORT_TYPES.map((type) => (
+                  <option key={type} value={type}>
+                    {type}
+                  </option>
+                ))}
+              </select>
+              <button
+                type="button"
+                onClick={() => setPinnedOnly(!pinnedOnly)}
+                className={`flex-1 flex items-center justify-center gap-2 rounded-lg border px-3 py-2 text-[11px] font-medium transition-all duration-200 cursor-pointer ${
+                  pinnedOnly
+                    ? 'bg-[var(--accent-coral-dim)] border-[var(--accent-coral-border)] text-[var(--accent-coral)]'
+                    : 'border-white/[0.06] bg-white/[0.03] text-white/40 hover:text-white/70 hover:bg-white/[0.06]'
+                }`}
+              >
+                <Pin className="h-3 w-3" />
+                Pinned only
+              </button>
+            </div>
+            <div className="flex gap-1.5">
+              <button
+                type="button"
+                disabled={transferBusy}
+                onClick={exportHistory}
+                className="flex-1 flex items-center justify-center gap-2 rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2 text-[11px] text-white/60 cursor-pointer hover:bg-white/[0.06] transition-colors"
+              >
+                <Download className="h-3.5 w-3.5" />
+                Export
+              </button>
+              <button
+                type="button"
+                disabled={transferBusy}
+                onClick={() => importRef.current?.click()}
+                className="flex-1 flex items-center justify-center gap-2 rounded-lg border border-white/[0.06] bg-white/[0.03] px-3 py-2 text-[11px] text-white/60 cursor-pointer hover:bg-white/[0.06] transition-colors"
+              >
+                <Upload className="h-3.5 w-3.5" />
+                Import JSON / CSV
+              </button>
+              <input
+                ref={importRef}
+                type="file"
+                accept=".json,.csv"
+                className="hidden"
+                onChange={(e) => {
+                  const file = e.target.files?.[0]
+                  if (file) importHistory(file)
+                  e.target.value = ''
+                }}
+              />
+            </div>
+            <p className="text-[10px] text-white/20">
+              {transferMessage ?? 'Images are not exported. Imports merge entries already in the history.'}
+            </p>
+          </div>
+        </div>
+      )}
+
       {/* Shortcut */}
       <div>
         <p className="text-[9px] font-semibold uppercase tracking-[0.15em] text-white/25 mb-4">
@@ -1,13 +1,15 @@
 "use client";
 
 import React, { useState, useRef, useEffect, useCallback } from "react";
-import { X, Trash2 } from "lucide-react";
+import { X, Trash2, AlertTriangle } from "lucide-react";
 import { IconPicker } from "@/components/icon-picker";
-import type { Snippet } from "@/hooks/use-snippets";
+import { DEFAULT_TRIGGER_OPTIONS, type Snippet, type TriggerConflict, type TriggerOptions } from "@/hooks/use-snippets";
 
 interface SnippetFormProps {
   initial?: Snippet;
   defaultName?: string;
+  /** Conflicts between the saved triggers, to warn about those of `initial`. */
+  conflicts?: TriggerConflict[];
   onSave: (data: Omit<Snippet, "id">) => void;
   onCancel: () => void;
   onDelete?: () => void;
@@ -22,12 +24,36 @@ const PLACEHOLDERS = [
   { label: "UUID", value: "{uuid}" },
 ];
 
-export function SnippetForm({ initial, defaultName, onSave, onCancel, onDelete }: SnippetFormProps) {
+function parseApps(value: string): string[] {
+  return value.split(",").map((app) => app.trim()).filter(Boolean);
+}
+
+/** Warnings about the saved trigger `keyword`. */
+function conflictWarnings(keyword: string, conflicts: TriggerConflict[]): string[] {
+  const warnings: string[] = [];
+  if (conflicts.some((c) => c.kind === "duplicate" && c.keyword === keyword)) {
+    warnings.push(`Other snippets use ${keyword} too – the one with the highest priority expands`);
+  }
+  for (const c of conflicts) {
+    if (c.kind === "shadowed" && c.keyword === keyword) {
+      warnings.push(`Never expands: ${c.other} expands first while typing it`);
+    } else if (c.kind === "shadowed" && c.other === keyword) {
+      warnings.push(`Keeps ${c.keyword} from ever expanding`);
+    } else if (c.kind === "overlap" && c.other === keyword) {
+      warnings.push(`Typing ${c.keyword} expands that snippet instead`);
+    }
+  }
+  return warnings;
+}
+
+export function SnippetForm({ initial, defaultName, conflicts = [], onSave, onCancel, onDelete }: SnippetFormProps) {
   const [name, setName] = useState(initial?.name ?? defaultName ?? "");
   const [icon, setIcon] = useState(initial?.icon ?? "Code");
   const [keyword, setKeyword] = useState(initial?.keyword ?? "");
   const [body, setBody] = useState(initial?.body ?? "");
   const [tags, setTags] = useState<string[]>(initial?.tags ?? []);
+  const [priority, setPriority] = useState(initial?.priority ?? 0);
+  const [options, setOptions] = useState<TriggerOptions>({ ...DEFAULT_TRIGGER_OPTIONS, ...initial?.options });
   const [tagInput, setTagInput] = useState("");
   const [confirmDelete, setConfirmDelete] = useState(false);
   const textareaRef = useRef<HTMLTextAreaElement>(null);
@@ -41,6 +67,8 @@ export function SnippetForm({ initial, defaultName, onSave, onCancel, onDelete }
       keyword: keyword.trim().startsWith("/") ? keyword.trim() : `/${keyword.trim()}`,
       body,
       tags,
+      priority,
+      options,
     });
   };
 
@@ -88,6 +116,8 @@ export function SnippetForm({ initial, defaultName, onSave, onCancel, onDelete }
           keyword: keyword.trim().startsWith("/") ? keyword.trim() : `/${keyword.trim()}`,
           body,
           tags,
+          priority,
+          options,
         });
       }
     } else if (e.key === "Backspace" && e.ctrlKey && initial && onDelete) {
@@ -95,7 +125,12 @@ export function SnippetForm({ initial, defaultName, onSave, onCancel, onDelete }
       e.stopPropagation();
       onDelete();
     }
-  }, [onCancel, onSave, onDelete, name, icon, keyword, body, tags, initial]);
+  }, [onCancel, onSave, onDelete, name, icon, keyword, body, tags, priority, options, initial]);
+
+  const setOption = <K extends keyof TriggerOptions>(key: K, value: TriggerOptions[K]) =>
+    setOptions((prev) => ({ ...prev, [key]: value }));
+
+  const warnings = initial && keyword === initial.keyword ? conflictWarnings(initial.keyword, conflicts) : [];
 
   useEffect(() => {
     window.addEventListener("keydown", handleFormKeyDown);
@@ -137,6 +172,80 @@ export function SnippetForm({ initial, defaultName, onSave, onCancel, onDelete }
           style={{ fontFamily: "'JetBrains Mono', monospace" }}
         />
       </div>
+      {warnings.length > 0 && (
+        <div className="flex flex-col gap-1 -mt-2 mb-4">
+          {warnings.map((warning) => (
+            <span key={warning} className="flex items-center gap-1.5 text-[10px] text-amber-400/70">
+              <AlertTriangle className="h-3 w-3 shrink-0" />
+              {warning}
+            </span>
+          ))}
+        </div>
+      )}
+
+      {/* Expansion */}
+      <FormLabel>Expand</FormLabel>
+      <div className="flex gap-2 mb-2">
+        <select
+          value={options.expand}
+          onChange={(e) => setOption("expand", e.target.value as TriggerOptions["expand"])}
+          className="form-input"
+        >
+          <option value="immediate">Immediately</option>
+          <option value="delimiter">After Space, Return or Tab</option>
+        </select>
+        <select
+          value={options.case}
+          onChange={(e) => setOption("case", e.target.value as TriggerOptions["case"])}
+          className="form-input"
+        >
+          <option value="sensitive">Match case</option>
+          <option value="insensitive">Ignore case</option>
+          <option value="propagate">Follow typed case</option>
+        </select>
+      </div>
+      <div className="flex flex-col gap-1.5 mb-4">
+        <OptionCheckbox
+          checked={options.wordBoundary}
+          onChange={(checked) => setOption("wordBoundary", checked)}
+          label="Only at the start of a word"
+        />
+        {options.expand === "delimiter" && (
+          <OptionCheckbox
+            checked={options.consumeDelimiter}
+            onChange={(checked) => setOption("consumeDelimiter", checked)}
+            label="Remove the delimiter"
+          />
+        )}
+      </div>
+
+      {/* Apps */}
+      <FormLabel>Only in apps</FormLabel>
+      <input
+        type="text"
+        defaultValue={options.allowedApps.join(", ")}
+        onChange={(e) => setOption("allowedApps", parseApps(e.target.value))}
+        placeholder="Everywhere, or e.g. Code, firefox"
+        className="form-input mb-4"
+      />
+      <FormLabel>Never in apps</FormLabel>
+      <input
+        type="text"
+        defaultValue={options.deniedApps.join(", ")}
+        onChange={(e) => setOption("deniedApps", parseApps(e.target.value))}
+        placeholder="e.g. WindowsTerminal"
+        className="form-input mb-4"
+      />
+
+      {/* Priority */}
+      <FormLabel>Priority</FormLabel>
+      <input
+        type="number"
+        value={priority}
+        onChange={(e) => setPriority(Number.parseInt(e.target.value, 10) || 0)}
+        title="Decides between snippets with the same keyword; higher wins"
+        className="form-input mb-4"
+      />
 
       {/* Body */}
       <FormLabel>Body</FormLabel>
@@ -274,6 +383,20 @@ export function SnippetForm({ initial, defaultName, onSave, onCancel, onDelete }
   );
 }
 
+function OptionCheckbox({ checked, onChange, label }: { checked: boolean; onChange: (checked: boolean) => void; label: string }) {
+  return (
+    <label className="flex items-center gap-2 text-[11px] text-white/50 cursor-pointer">
+      <input
+        type="checkbox"
+        checked={checked}
+        onChange={(e) => onChange(e.target.checked)}
+        className="accent-[var(--accent-coral)]"
+      />
+      {label}
+    </label>
+  );
+}
+
 function FormLabel({ children }: { children: React.ReactNode }) {
   return (
     <label className="text-[9px] font-semibold uppercase tracking-[0.15em] text-white/25 mb-1.5 block">
@@ -2,20 +2,120 @@
 
 import { useState, useEffect, useCallback } from "react";
 
+export interface ClipboardImage {
+  path: string;
+  thumbnailPath: string;
+  width: number;
+  height: number;
+  byteSize: number;
+  /** Files are encrypted; read them with `read_clipboard_image`. */
+  encrypted?: boolean;
+}
+
+export interface ClipboardFormats {
+  html?: string;
+  rtf?: string;
+  files?: string[];
+}
+
+/** A quick action of a custom content type, expanded for the entry. */
+export interface QuickAction {
+  label: string;
+  kind: "open" | "copy" | "paste";
+  value: string;
+}
+
+/** Details the classifier extracted, tagged by `kind`. */
+export type ClipboardMetadata =
+  | { kind: "code"; language: string }
+  | { kind: "link"; scheme: string; host: string; port?: number }
+  | { kind: "color"; rgb: [number, number, number]; alpha: number; hsl: [number, number, number] }
+  | { kind: "path"; exists: boolean; isDir: boolean; remote?: boolean }
+  | { kind: "number"; value: number }
+  | { kind: "json"; shape: "object" | "array"; len: number; keys?: string[] }
+  | { kind: "ip"; version: 4 | 6; prefix?: number; local: boolean }
+  | { kind: "uuid"; version: number }
+  | { kind: "datetime"; timestamp: number; hasTime: boolean }
+  | { kind: "hash"; algorithm: string }
+  | { kind: "custom"; label: string; actions?: QuickAction[] };
+
+/** Where an entry was copied from; `icon` is a cached PNG in the app data dir. */
+export interface SourceDetails {
+  exePath?: string;
+  title?: string;
+  icon?: string;
+}
+
 export interface ClipboardEntry {
   id: string;
   content: string;
-  contentType: "text" | "link" | "email" | "phone" | "code" | "path" | "number" | "json" | "color" | "image";
+  contentType:
+    | "text" | "link" | "email" | "phone" | "code" | "path" | "number" | "json" | "color" | "image" | "files"
+    | "ip" | "uuid" | "datetime" | "hash" | "markdown"
+    // Names of custom classifiers from the clipboard settings
+    | (string & {});
   sourceApp: string;
+  sourceDetails?: SourceDetails;
   wordCount: number;
   charCount: number;
   timestamp: number;
   pinned: boolean;
+  /** Id of the collection the entry is filed in; such entries are never dropped. */
+  collection?: string;
+  tags?: string[];
   preview: string;
+  image?: ClipboardImage;
+  formats?: ClipboardFormats;
+  metadata?: ClipboardMetadata;
+  /** Times this content was copied; duplicates are merged into one entry. */
+  copyCount?: number;
+  /** Set for entries that looked like a secret: kept in memory only until then (ms). */
+  expiresAt?: number;
+}
+
+/** Text transforms applied when pasting; run in order. */
+export type ClipboardTransform =
+  | "plain" | "trim" | "upper" | "lower" | "title"
+  | "url-encode" | "url-decode" | "base64-encode" | "base64-decode"
+  | "json-pretty" | "json-minify";
+
+/** An action from `get_clipboard_actions`, run with `run_clipboard_action`. */
+export interface ClipboardAction {
+  id: string;
+  label: string;
+}
+
+/** A named folder of clipboard entries. */
+export interface ClipboardCollection {
+  id: string;
+  name: string;
+  createdAt: number;
+}
+
+export interface ClipboardSearchQuery {
+  text?: string;
+  contentTypes?: ClipboardEntry["contentType"][];
+  sourceApps?: string[];
+  from?: number;
+  to?: number;
+  pinned?: boolean;
+  collection?: string;
+  /** Entries must carry every tag. */
+  tags?: string[];
+  offset?: number;
+  limit?: number;
+}
+
+export interface ClipboardSearchResults {
+  entries: ClipboardEntry[];
+  total: number;
 }
 
 export function useClipboardHistory() {
   const [entries, setEntries] = useState<ClipboardEntry[]>([]);
+  // Entry ids queued for the paste stack hotkey, next first
+  const [pasteStack, setPasteStackState] = useState<string[]>([]);
+  const [collections, setCollections] = useState<ClipboardCollection[]>([]);
 
   // Load initial history from Rust backend
   useEffect(() => {
@@ -24,7 +124,13 @@ export function useClipboardHistory() {
       try {
         const { invoke } = await import("@tauri-apps/api/core");
         const history = await invoke<ClipboardEntry[]>("get_clipboard_history");
-        if (!cancelled) setEntries(history);
+        const stack = await invoke<string[]>("get_paste_stack");
+        const collections = await invoke<ClipboardCollection[]>("get_clipboard_collections");
+        if (!cancelled) {
+          setEntries(history);
+          setPasteStackState(stack);
+          setCollections(collections);
+        }
       } catch {
         // Not running in Tauri context
       }
@@ -32,21 +138,48 @@ export function useClipboardHistory() {
     return () => { cancelled = true; };
   }, []);
 
-  // Listen for new clipboard entries from the Rust monitor
+  // Listen for new clipboard entries from the Rust monitor, and for entries
+  // dropped by the retention policy
   useEffect(() => {
     let unlisten: (() => void) | null = null;
+    let unlistenRemoved: (() => void) | null = null;
+    let unlistenReloaded: (() => void) | null = null;
+    let unlistenStack: (() => void) | null = null;
+    let unlistenCollections: (() => void) | null = null;
     (async () => {
       try {
         const { listen } = await import("@tauri-apps/api/event");
         const fn_ = await listen<ClipboardEntry>("clipboard-changed", (event) => {
-          setEntries((prev) => [event.payload, ...prev]);
+          // A merged duplicate moves to the top
+          setEntries((prev) => [event.payload, ...prev.filter((e) => e.id !== event.payload.id)]);
         });
         unlisten = fn_;
+        unlistenRemoved = await listen<string[]>("clipboard-entries-removed", (event) => {
+          const removed = new Set(event.payload);
+          setEntries((prev) => prev.filter((e) => !removed.has(e.id)));
+        });
+        // Sent after unlocking or re-encrypting the history
+        unlistenReloaded = await listen<ClipboardEntry[]>("clipboard-history-reloaded", (event) => {
+          setEntries(event.payload);
+        });
+        // The paste stack hotkey consumes entries from the front
+        unlistenStack = await listen<string[]>("paste-stack-changed", (event) => {
+          setPasteStackState(event.payload);
+        });
+        unlistenCollections = await listen<ClipboardCollection[]>("clipboard-collections-changed", (event) => {
+          setCollections(event.payload);
+        });
       } catch {
         // Not running in Tauri context
       }
     })();
-    return () => { unlisten?.(); };
+    return () => {
+      unlisten?.();
+      unlistenRemoved?.();
+      unlistenReloaded?.();
+      unlistenStack?.();
+      unlistenCollections?.();
+    };
   }, []);
 
   const deleteEntry = useCallback(async (id: string) => {
@@ -75,12 +208,96 @@ export function useClipboardHistory() {
     } catch {}
   }, []);
 
-  const pasteEntry = useCallback(async (content: string) => {
+  // Resolves to the new collection, or an error message
+  const createCollection = useCallback(async (name: string): Promise<ClipboardCollection | string> => {
     try {
       const { invoke } = await import("@tauri-apps/api/core");
-      await invoke("paste_snippet", { body: content });
+      return await invoke<ClipboardCollection>("create_clipboard_collection", { name });
+    } catch (e) {
+      return String(e);
+    }
+  }, []);
+
+  // Files the entries in a collection; `null` takes them out of theirs
+  const moveEntries = useCallback(async (ids: string[], collection: string | null) => {
+    setEntries((prev) =>
+      prev.map((e) => (ids.includes(e.id) ? { ...e, collection: collection ?? undefined } : e))
+    );
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      await invoke("move_clipboard_entries", { ids, collection });
     } catch {}
   }, []);
 
-  return { entries, deleteEntry, clearHistory, togglePin, pasteEntry };
+  const setEntryTags = useCallback(async (id: string, tags: string[]) => {
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const stored = await invoke<string[]>("set_clipboard_entry_tags", { id, tags });
+      setEntries((prev) => prev.map((e) => (e.id === id ? { ...e, tags: stored } : e)));
+    } catch {}
+  }, []);
+
+  // Pastes into the app that was focused before the bar opened. With
+  // `transforms` it pastes plain text; `[]` uses the defaults for the type.
+  const pasteEntry = useCallback(async (id: string, transforms?: ClipboardTransform[]) => {
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      await invoke("paste_clipboard_entry", { id, transforms });
+    } catch {}
+  }, []);
+
+  const copyEntry = useCallback(async (id: string) => {
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      await invoke("copy_clipboard_entry", { id });
+    } catch {}
+  }, []);
+
+  const setPasteStack = useCallback(async (ids: string[]) => {
+    setPasteStackState(ids);
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      await invoke("set_paste_stack", { ids });
+    } catch {}
+  }, []);
+
+  // Adds the entry to the end of the paste stack, or takes it out
+  const togglePasteStack = useCallback((id: string) => {
+    setPasteStack(pasteStack.includes(id) ? pasteStack.filter((e) => e !== id) : [...pasteStack, id]);
+  }, [pasteStack, setPasteStack]);
+
+  // Resolves to a message to show, e.g. a validation result
+  const runAction = useCallback(async (id: string, action: string): Promise<string | null> => {
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      return await invoke<string | null>("run_clipboard_action", { id, action });
+    } catch (e) {
+      return String(e);
+    }
+  }, []);
+
+  // Resolves to an error message, or null once the snippet exists
+  const promoteEntry = useCallback(async (id: string, keyword: string, name?: string): Promise<string | null> => {
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      await invoke("promote_clipboard_entry", { id, keyword, name: name ?? null });
+      return null;
+    } catch (e) {
+      return String(e);
+    }
+  }, []);
+
+  const searchEntries = useCallback(async (query: ClipboardSearchQuery): Promise<ClipboardSearchResults | null> => {
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      return await invoke<ClipboardSearchResults>("search_clipboard_history", { query });
+    } catch {
+      return null;
+    }
+  }, []);
+
+  return {
+    entries, deleteEntry, clearHistory, togglePin, pasteEntry, copyEntry, searchEntries, pasteStack, setPasteStack, togglePasteStack, runAction,
+    collections, createCollection, moveEntries, setEntryTags, promoteEntry,
+  };
 }
@@ -10,6 +10,50 @@ export interface Snippet {
   body: string;
   tags: string[];
   pinned?: boolean;
+  /** Decides between snippets with the same keyword; higher wins. */
+  priority?: number;
+  options?: TriggerOptions;
+}
+
+/** How a snippet's keyword is matched and expanded while typing. */
+export interface TriggerOptions {
+  /** Expand on the keyword's last key, or only once Space, Return or Tab follows it. */
+  expand: "immediate" | "delimiter";
+  /** Erase the delimiter with the keyword instead of typing it again after the expansion. */
+  consumeDelimiter: boolean;
+  /** Only expand when the keyword starts a word. */
+  wordBoundary: boolean;
+  /** "propagate" matches any case and capitalizes the expansion like the typed keyword. */
+  case: "sensitive" | "insensitive" | "propagate";
+  /** App names the snippet expands in; empty means everywhere. */
+  allowedApps: string[];
+  /** App names the snippet never expands in. */
+  deniedApps: string[];
+}
+
+/** How often a snippet was expanded by typing its keyword. */
+export interface SnippetStats {
+  expansions: number;
+  lastExpandedAt?: number | null;
+}
+
+/** Version of the `sync_triggers` payload the backend reads. */
+const TRIGGER_SCHEMA_VERSION = 1;
+
+export const DEFAULT_TRIGGER_OPTIONS: TriggerOptions = {
+  expand: "immediate",
+  consumeDelimiter: false,
+  wordBoundary: false,
+  case: "sensitive",
+  allowedApps: [],
+  deniedApps: [],
+};
+
+/** Two triggers getting in each other's way, as reported by `sync_triggers`. */
+export interface TriggerConflict {
+  kind: "duplicate" | "shadowed" | "overlap";
+  keyword: string;
+  other: string;
 }
 
 const STORAGE_KEY = "ohmycommandbar-snippets";
@@ -28,35 +72,106 @@ function saveSnippets(snippets: Snippet[]) {
   localStorage.setItem(STORAGE_KEY, JSON.stringify(snippets));
 }
 
-/** Send the keyword->body map to the Rust backend for text expansion. */
-async function syncTriggersToBackend(snippets: Snippet[]) {
+/** Store the snippets in the Rust backend, which owns them. */
+async function saveSnippetsToBackend(snippets: Snippet[]) {
   try {
     const { invoke } = await import("@tauri-apps/api/core");
-    const triggers: Record<string, string> = {};
-    for (const s of snippets) {
-      if (s.keyword && s.body) {
-        triggers[s.keyword] = s.body;
-      }
-    }
-    await invoke("sync_triggers", { triggers });
+    await invoke("save_snippets", { snippets });
   } catch {
     // Not running in Tauri (e.g. browser dev) – silently ignore
   }
 }
 
+/** Send the triggers to the Rust backend for text expansion. Returns the conflicts between them. */
+async function syncTriggersToBackend(snippets: Snippet[]): Promise<TriggerConflict[]> {
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    const triggers = snippets
+      .filter((s) => s.keyword && s.body)
+      .map((s) => ({
+        id: s.id,
+        name: s.name,
+        keyword: s.keyword,
+        body: s.body,
+        priority: s.priority ?? 0,
+        options: s.options ?? DEFAULT_TRIGGER_OPTIONS,
+      }));
+    return await invoke<TriggerConflict[]>("sync_triggers", {
+      triggers: { version: TRIGGER_SCHEMA_VERSION, triggers },
+    });
+  } catch {
+    // Not running in Tauri (e.g. browser dev) – silently ignore
+    return [];
+  }
+}
+
 export function useSnippets() {
   const [snippets, setSnippets] = useState<Snippet[]>([]);
+  const [conflicts, setConflicts] = useState<TriggerConflict[]>([]);
+  const [stats, setStats] = useState<Record<string, SnippetStats>>({});
 
   useEffect(() => {
-    const loaded = loadSnippets();
-    setSnippets(loaded);
-    syncTriggersToBackend(loaded);
+    let cancelled = false;
+    (async () => {
+      let loaded = loadSnippets();
+      try {
+        const { invoke } = await import("@tauri-apps/api/core");
+        const stored = await invoke<Snippet[]>("get_snippets");
+        // Snippets saved before the backend owned them are moved over once
+        if (stored.length > 0) loaded = stored;
+        else if (loaded.length > 0) await saveSnippetsToBackend(loaded);
+      } catch {
+        // Not running in Tauri context
+      }
+      if (cancelled) return;
+      setSnippets(loaded);
+      saveSnippets(loaded);
+      setConflicts(await syncTriggersToBackend(loaded));
+    })();
+    return () => { cancelled = true; };
+  }, []);
+
+  // Snippets created by the backend, e.g. from a clipboard entry
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    (async () => {
+      try {
+        const { listen } = await import("@tauri-apps/api/event");
+        unlisten = await listen<Snippet[]>("snippets-changed", async (event) => {
+          setSnippets(event.payload);
+          saveSnippets(event.payload);
+          setConflicts(await syncTriggersToBackend(event.payload));
+        });
+      } catch {
+        // Not running in Tauri context
+      }
+    })();
+    return () => { unlisten?.(); };
+  }, []);
+
+  // Expansion counts, updated as snippets are expanded while typing
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    (async () => {
+      try {
+        const { invoke } = await import("@tauri-apps/api/core");
+        const { listen } = await import("@tauri-apps/api/event");
+        setStats(await invoke<Record<string, SnippetStats>>("get_snippet_stats"));
+        unlisten = await listen<{ id: string; stats: SnippetStats }>("snippet-expanded", (event) => {
+          setStats((prev) => ({ ...prev, [event.payload.id]: event.payload.stats }));
+        });
+      } catch {
+        // Not running in Tauri context
+      }
+    })();
+    return () => { unlisten?.(); };
   }, []);
 
   const persist = useCallback((next: Snippet[]) => {
     setSnippets(next);
     saveSnippets(next);
-    syncTriggersToBackend(next);
+    saveSnippetsToBackend(next);
+    syncTriggersToBackend(next).then(setConflicts);
   }, []);
 
   const addSnippet = useCallback(
@@ -110,5 +225,5 @@ export function useSnippets() {
     [snippets, persist]
   );
 
-  return { snippets, addSnippet, updateSnippet, deleteSnippet, togglePin, duplicateSnippet };
+  return { snippets, conflicts, stats, addSnippet, updateSnippet, deleteSnippet, togglePin, duplicateSnippet };
 }
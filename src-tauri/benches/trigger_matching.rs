@@ -0,0 +1,119 @@
+use app_lib::bench_support::{ExpandMode, KeyBuffer, TriggerDefinition, TriggerMatcher};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::collections::HashMap;
+
+const PREFIXES: [&str; 4] = [";", "/", "x", "::"];
+const WORDS: [&str; 8] = ["sig", "addr", "date", "email", "reply", "todo", "pr", "meet"];
+
+/// Text typed in the per-key benchmark. It ends with words the keywords end
+/// with, so lookups walk several levels into the trie before missing.
+const TYPED: &str = "Looking forward to the meeting; see the addr below /sig and xsig, reply todo";
+
+/// Letters standing for `n`: "", "a", ..., "z", "aa", "ab"...
+fn tag(mut n: usize) -> String {
+    let mut letters = Vec::new();
+    while n > 0 {
+        n -= 1;
+        letters.push((b'a' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+/// Keywords shaped like real ones: a prefix, a few letters and a word, such as
+/// `;sig`, `/addr`, `xsig` or `;bemail`. They share suffixes with each other
+/// and with the typed text.
+fn triggers(count: usize) -> Vec<TriggerDefinition> {
+    (0..count)
+        .map(|i| {
+            let prefix = PREFIXES[i % PREFIXES.len()];
+            let word = WORDS[i / PREFIXES.len() % WORDS.len()];
+            let keyword = format!("{}{}{}", prefix, tag(i / (PREFIXES.len() * WORDS.len())), word);
+            TriggerDefinition {
+                id: i.to_string(),
+                name: String::new(),
+                keyword,
+                body: format!("Snippet body {}", i),
+                priority: 0,
+                options: Default::default(),
+            }
+        })
+        .collect()
+}
+
+/// Cost of looking up the buffer after one key, while typing plain text
+/// that ends in near misses and hits of the keywords.
+fn per_key(c: &mut Criterion) {
+    let mut group = c.benchmark_group("per_key");
+    let typed: Vec<char> = TYPED.chars().collect();
+    for count in [10, 100, 1_000, 10_000] {
+        let matcher = TriggerMatcher::new(triggers(count));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &matcher, |b, matcher| {
+            let mut keys = KeyBuffer::new(64);
+            let mut next = typed.iter().cycle();
+            b.iter(|| {
+                keys.push(*next.next().unwrap());
+                black_box(matcher.find(&keys, ExpandMode::Immediate, |_| true).is_some())
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Cost of a lookup when the typed keys end with a keyword.
+fn hit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hit");
+    for count in [10, 100, 1_000, 10_000] {
+        let mut triggers = triggers(count);
+        triggers.push(TriggerDefinition {
+            id: "hit".to_string(),
+            name: String::new(),
+            keyword: ";hit".to_string(),
+            body: "Hit".to_string(),
+            priority: 0,
+            options: Default::default(),
+        });
+        let matcher = TriggerMatcher::new(triggers);
+        let mut keys = KeyBuffer::new(64);
+        "text before ;hit".chars().for_each(|ch| keys.push(ch));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &keys, |b, keys| {
+            b.iter(|| black_box(matcher.find(keys, ExpandMode::Immediate, |_| true).map(|trigger| trigger.len)))
+        });
+    }
+    group.finish();
+}
+
+/// The per-key benchmark against the matching done before the trie: a map
+/// from keyword to body, scanned for a keyword the buffer ends with.
+fn per_key_linear(c: &mut Criterion) {
+    let mut group = c.benchmark_group("per_key_linear");
+    let typed: Vec<char> = TYPED.chars().collect();
+    for count in [10, 100, 1_000, 10_000] {
+        let map: HashMap<String, String> = triggers(count).into_iter().map(|t| (t.keyword, t.body)).collect();
+        group.bench_with_input(BenchmarkId::from_parameter(count), &map, |b, map| {
+            let mut keys = KeyBuffer::new(64);
+            let mut next = typed.iter().cycle();
+            b.iter(|| {
+                keys.push(*next.next().unwrap());
+                black_box(map.keys().any(|keyword| keys.ends_with(keyword)))
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Cost of `sync_triggers` compiling the matcher.
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    group.sample_size(20);
+    for count in [100, 1_000, 10_000] {
+        let triggers = triggers(count);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &triggers, |b, triggers| {
+            b.iter(|| TriggerMatcher::new(triggers.iter().cloned()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, per_key, per_key_linear, hit, build);
+criterion_main!(benches);
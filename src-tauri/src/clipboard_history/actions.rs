@@ -0,0 +1,278 @@
+use super::classifier;
+use super::entry::{ClipboardEntry, ContentMetadata, ContentType, QuickActionKind};
+use super::transform::Transform;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// An action offered for a clipboard entry, run with `run_clipboard_action`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardAction {
+    pub id: String,
+    pub label: String,
+}
+
+/// What running an action does. Resolving an action has no side effects;
+/// the caller performs the effect.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionEffect {
+    /// Open a URL or file with its default handler.
+    Open(String),
+    /// Show a file or folder in the file manager.
+    Reveal(PathBuf),
+    /// Put text on the clipboard.
+    Copy(String),
+    /// Paste text into the previously focused application.
+    Paste(String),
+    /// Only report something back, e.g. a validation result.
+    Message(String),
+}
+
+/// Built-in actions by content type: id and label.
+fn built_in_actions(content_type: &ContentType) -> &'static [(&'static str, &'static str)] {
+    match content_type {
+        ContentType::Link => &[("open", "Open link")],
+        ContentType::Email => &[("compose", "Compose mail")],
+        ContentType::Phone => &[("call", "Call")],
+        ContentType::Path => &[("open", "Open"), ("reveal", "Show in file manager")],
+        ContentType::Color => &[("copy-hex", "Copy as hex"), ("copy-rgb", "Copy as RGB"), ("copy-hsl", "Copy as HSL")],
+        ContentType::Json => &[("validate", "Validate"), ("format", "Copy formatted"), ("minify", "Copy minified")],
+        _ => &[],
+    }
+}
+
+/// The actions available for `entry`.
+pub fn actions_for(entry: &ClipboardEntry) -> Vec<ClipboardAction> {
+    let mut actions: Vec<ClipboardAction> = built_in_actions(&entry.content_type)
+        .iter()
+        .map(|(id, label)| ClipboardAction { id: id.to_string(), label: label.to_string() })
+        .collect();
+    if let Some(ContentMetadata::Custom { actions: custom, .. }) = &entry.metadata {
+        actions.extend(custom.iter().enumerate().map(|(i, action)| ClipboardAction {
+            id: format!("custom-{}", i),
+            label: action.label.clone(),
+        }));
+    }
+    actions
+}
+
+/// Work out what running `action` on `entry` does.
+pub fn resolve(entry: &ClipboardEntry, action: &str) -> Result<ActionEffect, String> {
+    if let Some(index) = action.strip_prefix("custom-") {
+        let quick_action = match &entry.metadata {
+            Some(ContentMetadata::Custom { actions, .. }) => index.parse::<usize>().ok().and_then(|i| actions.get(i)),
+            _ => None,
+        }
+        .ok_or_else(|| format!("Unknown action: {}", action))?;
+        let value = quick_action.value.clone();
+        return Ok(match quick_action.kind {
+            QuickActionKind::Open => ActionEffect::Open(value),
+            QuickActionKind::Copy => ActionEffect::Copy(value),
+            QuickActionKind::Paste => ActionEffect::Paste(value),
+        });
+    }
+    if !built_in_actions(&entry.content_type).iter().any(|(id, _)| *id == action) {
+        return Err(format!("Unknown action: {}", action));
+    }
+
+    let text = entry.content.trim();
+    Ok(match action {
+        "open" if entry.content_type == ContentType::Path => ActionEffect::Open(existing_path(text)?.to_string_lossy().into_owned()),
+        "open" => ActionEffect::Open(text.to_string()),
+        "compose" => ActionEffect::Open(format!("mailto:{}", text)),
+        "call" => {
+            let number: String = text.chars().filter(|c| c.is_ascii_digit() || *c == '+').collect();
+            ActionEffect::Open(format!("tel:{}", number))
+        }
+        "reveal" => ActionEffect::Reveal(existing_path(text)?),
+        "copy-hex" | "copy-rgb" | "copy-hsl" => ActionEffect::Copy(convert_color(entry, action)?),
+        "validate" => ActionEffect::Message(match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(serde_json::Value::Object(map)) => format!("Valid JSON object with {} keys", map.len()),
+            Ok(serde_json::Value::Array(items)) => format!("Valid JSON array with {} items", items.len()),
+            Ok(_) => "Valid JSON".to_string(),
+            Err(e) => format!("Invalid JSON: {}", e),
+        }),
+        "format" => ActionEffect::Copy(Transform::JsonPretty.apply(text)?),
+        "minify" => ActionEffect::Copy(Transform::JsonMinify.apply(text)?),
+        _ => return Err(format!("Unknown action: {}", action)),
+    })
+}
+
+fn existing_path(text: &str) -> Result<PathBuf, String> {
+    classifier::expand_path(text)
+        .filter(|path| path.exists())
+        .ok_or_else(|| format!("{} does not exist", text))
+}
+
+/// The entry's color in the notation of a `copy-*` action.
+fn convert_color(entry: &ClipboardEntry, action: &str) -> Result<String, String> {
+    // Entries from before metadata was stored are classified again
+    let metadata = entry
+        .metadata
+        .clone()
+        .or_else(|| classifier::classify(&entry.content).metadata);
+    let Some(ContentMetadata::Color { rgb: [r, g, b], alpha, hsl: [h, s, l] }) = metadata else {
+        return Err("Not a color".into());
+    };
+    let opaque = alpha >= 1.0;
+    Ok(match action {
+        "copy-hex" if opaque => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        "copy-hex" => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, (alpha * 255.0).round() as u8),
+        "copy-rgb" if opaque => format!("rgb({}, {}, {})", r, g, b),
+        "copy-rgb" => format!("rgba({}, {}, {}, {})", r, g, b, format_alpha(alpha)),
+        "copy-hsl" if opaque => format!("hsl({}, {}%, {}%)", h, s, l),
+        "copy-hsl" => format!("hsla({}, {}%, {}%, {})", h, s, l, format_alpha(alpha)),
+        _ => return Err(format!("Unknown action: {}", action)),
+    })
+}
+
+/// Alpha with at most three decimals, as written in CSS: `0.502`, `0.5`.
+fn format_alpha(alpha: f32) -> String {
+    let text = format!("{:.3}", alpha);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Show `path` selected in the system file manager, or open its folder where
+/// that isn't supported.
+pub fn reveal(path: &Path) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        let mut arg = std::ffi::OsString::from("/select,");
+        arg.push(path);
+        std::process::Command::new("explorer.exe")
+            .arg(arg)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg("-R")
+            .arg(path)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        // The freedesktop file manager interface selects the item
+        let encoded = Transform::UrlEncode.apply(&path.to_string_lossy())?;
+        let uri = format!("file://{}", encoded.replace("%2F", "/"));
+        let shown = std::process::Command::new("dbus-send")
+            .args([
+                "--session",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", uri))
+            .arg("string:")
+            .status()
+            .is_ok_and(|status| status.success());
+        if shown {
+            return Ok(());
+        }
+        let folder = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+        open::that(folder).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard_history::entry::QuickAction;
+
+    fn entry(text: &str) -> ClipboardEntry {
+        let classification = classifier::classify(text);
+        ClipboardEntry::new(text.into(), classification.content_type, "Test".into())
+            .with_metadata(classification.metadata)
+    }
+
+    fn copied(text: &str, action: &str) -> String {
+        match resolve(&entry(text), action) {
+            Ok(ActionEffect::Copy(value)) => value,
+            other => panic!("{} on {:?}: {:?}", action, text, other),
+        }
+    }
+
+    #[test]
+    fn links_mail_and_phone_numbers() {
+        let opened = |text: &str, action: &str| match resolve(&entry(text), action) {
+            Ok(ActionEffect::Open(target)) => target,
+            other => panic!("{} on {:?}: {:?}", action, text, other),
+        };
+        assert_eq!(opened("https://example.com/a", "open"), "https://example.com/a");
+        assert_eq!(opened(" alice@example.com ", "compose"), "mailto:alice@example.com");
+        assert_eq!(opened("+1 (555) 123-4567", "call"), "tel:+15551234567");
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(copied("rgb(255, 136, 0)", "copy-hex"), "#ff8800");
+        assert_eq!(copied("#ff8800", "copy-rgb"), "rgb(255, 136, 0)");
+        assert_eq!(copied("#ff8800", "copy-hsl"), "hsl(32, 100%, 50%)");
+
+        // 0x80 is an alpha of 0.5019608
+        assert_eq!(copied("#ff880080", "copy-rgb"), "rgba(255, 136, 0, 0.502)");
+        assert_eq!(copied("#ff880080", "copy-hsl"), "hsla(32, 100%, 50%, 0.502)");
+        assert_eq!(copied("rgba(255, 136, 0, 0.5)", "copy-rgb"), "rgba(255, 136, 0, 0.5)");
+        assert_eq!(copied("rgba(255, 136, 0, 0.5)", "copy-hex"), "#ff880080");
+        assert_eq!(copied("rgba(0, 0, 0, 0)", "copy-rgb"), "rgba(0, 0, 0, 0)");
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(copied(r#"{"b": 1, "a": [1, 2]}"#, "minify"), r#"{"b":1,"a":[1,2]}"#);
+        assert_eq!(copied(r#"{"a":1}"#, "format"), "{\n  \"a\": 1\n}");
+        assert_eq!(
+            resolve(&entry(r#"{"a": 1, "b": 2}"#), "validate"),
+            Ok(ActionEffect::Message("Valid JSON object with 2 keys".into()))
+        );
+    }
+
+    #[test]
+    fn paths_must_exist() {
+        let dir = std::env::temp_dir();
+        let text = dir.to_string_lossy().into_owned();
+        let mut existing = entry(&text);
+        existing.content_type = ContentType::Path;
+        assert_eq!(resolve(&existing, "reveal"), Ok(ActionEffect::Reveal(dir)));
+
+        let mut missing = entry("/no/such/path/anywhere");
+        missing.content_type = ContentType::Path;
+        assert!(resolve(&missing, "open").is_err());
+    }
+
+    #[test]
+    fn custom_actions() {
+        let action = |kind, value: &str| QuickAction { label: "Action".into(), kind, value: value.into() };
+        let ticket = entry("ABC-1234").with_metadata(Some(ContentMetadata::Custom {
+            label: "Ticket".into(),
+            actions: vec![
+                action(QuickActionKind::Open, "https://tracker.example.com/ABC-1234"),
+                action(QuickActionKind::Copy, "1234"),
+                action(QuickActionKind::Paste, "ABC"),
+            ],
+        }));
+        assert_eq!(actions_for(&ticket).len(), 3);
+        assert_eq!(resolve(&ticket, "custom-0"), Ok(ActionEffect::Open("https://tracker.example.com/ABC-1234".into())));
+        assert_eq!(resolve(&ticket, "custom-1"), Ok(ActionEffect::Copy("1234".into())));
+        assert_eq!(resolve(&ticket, "custom-2"), Ok(ActionEffect::Paste("ABC".into())));
+        assert!(resolve(&ticket, "custom-3").is_err());
+        assert!(resolve(&ticket, "custom-x").is_err());
+    }
+
+    #[test]
+    fn unknown_actions() {
+        // Only the actions offered for the entry's type run
+        assert!(resolve(&entry("https://example.com"), "copy-hex").is_err());
+        assert!(resolve(&entry("plain text"), "open").is_err());
+        assert!(resolve(&entry("#ff8800"), "nope").is_err());
+        // Built-in colour conversions need colour metadata
+        let mut not_a_color = entry("plain text");
+        not_a_color.content_type = ContentType::Color;
+        assert!(resolve(&not_a_color, "copy-hex").is_err());
+    }
+}
@@ -1,83 +1,689 @@
-use super::entry::ContentType;
+use super::entry::{ContentMetadata, ContentType, QuickAction, QuickActionKind};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
-static RE_URL: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"(?i)^https?://\S+$").unwrap());
+static RE_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^([a-z][a-z0-9+.\-]*)://(?:[^@/?#\s]*@)?(\[[0-9a-f:.]+\]|[^/?#:\s]+)(?::(\d{1,5}))?(?:[/?#]\S*)?$")
+        .unwrap()
+});
 
 static RE_EMAIL: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"(?i)^[a-z0-9._%+\-]+@[a-z0-9.\-]+\.[a-z]{2,}$").unwrap());
 
+/// Digits with the usual phone separators; see `is_phone` for the rest.
 static RE_PHONE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^\+?[\d\s\-().]{7,20}$").unwrap());
 
 static RE_PATH: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"(?i)^([a-z]:\\|/|\\\\|~/)[^\x00]*$").unwrap());
 
-static RE_COLOR: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"(?i)^#([0-9a-f]{3}|[0-9a-f]{6}|[0-9a-f]{8})$").unwrap());
+static RE_HEX_COLOR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^#([0-9a-f]{3,4}|[0-9a-f]{6}|[0-9a-f]{8})$").unwrap());
+
+/// `rgb()`/`rgba()`/`hsl()`/`hsla()` with comma or space separated arguments.
+static RE_FN_COLOR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(rgba?|hsla?)\(\s*([\d.]+)(?:deg)?%?\s*[,\s]\s*([\d.]+)%?\s*[,\s]\s*([\d.]+)%?\s*(?:[,/]\s*([\d.]+)(%?)\s*)?\)$")
+        .unwrap()
+});
 
 static RE_NUMBER: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"^-?\d[\d,]*\.?\d*$").unwrap());
+    LazyLock::new(|| Regex::new(r"^-?\d[\d,]*\.?\d*(?:[eE][+-]?\d+)?$").unwrap());
+
+static RE_UUID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^\{?[0-9a-f]{8}-[0-9a-f]{4}-([0-9a-f])[0-9a-f]{3}-[0-9a-f]{4}-[0-9a-f]{12}\}?$").unwrap()
+});
+
+static RE_HASH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^[0-9a-f]{32,128}$").unwrap());
+
+/// Markdown constructs, each counted at most once by `looks_like_markdown`.
+static MARKDOWN_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        r"(?m)^#{1,6} \S",
+        r"(?m)^\s*[-*+] \S",
+        r"(?m)^\s*\d+\. \S",
+        r"(?m)^```",
+        r"(?m)^> ",
+        r"\[[^\]\n]+\]\([^)\s]+\)",
+        r"\*\*[^*\n]+\*\*|__[^_\n]+__",
+        r"(?m)^\|.*\|\s*$",
+    ]
+    .into_iter()
+    .map(|pattern| Regex::new(pattern).unwrap())
+    .collect()
+});
+
+/// Languages and the markers that give them away. Each marker found counts
+/// one point; the first language with the highest score wins.
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["fn ", "let mut ", "impl ", "pub fn ", "::", "println!", "-> ", "&self", "match ", "use std", "#[derive"]),
+    ("python", &["def ", "import ", "self.", "elif ", "print(", "None", "__init__", "from ", " is not ", "lambda "]),
+    ("javascript", &["function ", "const ", "=> ", "console.log", "let ", "require(", "export ", "document.", "===", "undefined"]),
+    ("typescript", &["interface ", ": string", ": number", ": boolean", "export type ", "import type ", "as const", "=> ", "const "]),
+    ("go", &["func ", "package ", ":= ", "fmt.", "err != nil", "go func", "chan "]),
+    ("java", &["public class ", "System.out", "private ", "public static void", "import java", "@Override", "new "]),
+    ("csharp", &["using System", "namespace ", "Console.WriteLine", "public class ", "var ", "async Task", "get; set;"]),
+    ("cpp", &["#include", "#define", "std::", "int main(", "printf(", "using namespace", "nullptr", "->"]),
+    ("sql", &["SELECT ", "FROM ", "WHERE ", "INSERT INTO", "CREATE TABLE", "UPDATE ", "JOIN ", "GROUP BY", "ORDER BY"]),
+    ("shell", &["#!/bin/", "echo ", "sudo ", "\nfi", "$(", "export ", "| grep", "&& ", "apt ", "cd "]),
+    ("html", &["<div", "</", "<html", "<!DOCTYPE", "<span", "class=\"", "<p>", "href="]),
+    ("css", &["px;", "color:", "margin:", "padding:", "display:", "font-", "@media", "!important"]),
+];
+
+/// How many JSON object keys to keep in the metadata.
+const MAX_JSON_KEYS: usize = 20;
+
+/// A user-defined content type, e.g. ticket ids like `ABC-1234`. Custom
+/// classifiers are checked in order before the built-in rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomClassifier {
+    /// Content type name stored with the entry: lowercase letters, digits,
+    /// `-` and `_`, and not the name of a built-in type.
+    pub name: String,
+    /// Shown instead of the name.
+    pub label: String,
+    /// Regular expression that must match the whole trimmed text.
+    pub pattern: String,
+    #[serde(default)]
+    pub actions: Vec<CustomAction>,
+    /// `pattern`, compiled by `compile`. Until then the classifier matches
+    /// nothing.
+    #[serde(skip)]
+    regex: Option<Regex>,
+}
+
+/// A quick action offered for entries of a custom type. `template` may refer
+/// to the match as `$0` and to capture groups as `${1}` or `${name}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomAction {
+    pub label: String,
+    #[serde(default)]
+    pub kind: QuickActionKind,
+    pub template: String,
+}
+
+impl CustomClassifier {
+    /// Check the name and compile the pattern.
+    pub fn compile(&mut self) -> Result<(), String> {
+        let valid_name = !self.name.is_empty()
+            && self
+                .name
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_');
+        if !valid_name {
+            return Err(format!("Invalid content type name {:?}", self.name));
+        }
+        let content_type: ContentType =
+            serde_json::from_value(self.name.clone().into()).map_err(|e| e.to_string())?;
+        if !matches!(content_type, ContentType::Custom(_)) {
+            return Err(format!("{:?} is a built-in content type", self.name));
+        }
+        let regex = Regex::new(&format!("^(?:{})$", self.pattern))
+            .map_err(|e| format!("Invalid pattern for {:?}: {}", self.name, e))?;
+        self.regex = Some(regex);
+        Ok(())
+    }
+
+    /// Classify `text` (already trimmed) if the pattern matches it.
+    fn classify(&self, text: &str) -> Option<Classification> {
+        let caps = self.regex.as_ref()?.captures(text)?;
+        let actions = self
+            .actions
+            .iter()
+            .map(|action| {
+                let mut value = String::new();
+                caps.expand(&action.template, &mut value);
+                QuickAction { label: action.label.clone(), kind: action.kind, value }
+            })
+            .collect();
+        let metadata = ContentMetadata::Custom { label: self.label.clone(), actions };
+        Some(Classification::new(ContentType::Custom(self.name.clone()), Some(metadata)))
+    }
+}
+
+/// The result of classifying a clipboard text.
+pub struct Classification {
+    pub content_type: ContentType,
+    pub metadata: Option<ContentMetadata>,
+}
+
+impl Classification {
+    fn new(content_type: ContentType, metadata: Option<ContentMetadata>) -> Self {
+        Self { content_type, metadata }
+    }
+}
+
+impl From<ContentType> for Classification {
+    fn from(content_type: ContentType) -> Self {
+        Self::new(content_type, None)
+    }
+}
+
+/// Classify a clipboard text string with the built-in rules only.
+pub fn classify(text: &str) -> Classification {
+    classify_with(text, &[])
+}
 
-/// Classify the content type of a clipboard text string.
-pub fn classify(text: &str) -> ContentType {
+/// Classify a clipboard text string and extract metadata for its type. The
+/// first matching `custom` classifier wins over the built-in rules.
+pub fn classify_with(text: &str, custom: &[CustomClassifier]) -> Classification {
     let trimmed = text.trim();
 
-    // Single-line checks (no newlines)
+    if let Some(classification) = custom.iter().find_map(|c| c.classify(trimmed)) {
+        return classification;
+    }
+
+    // Single-line checks (no newlines). The order matters: IPs and dates
+    // would otherwise pass as phone numbers, and hashes as numbers.
     if !trimmed.contains('\n') {
-        if RE_URL.is_match(trimmed) {
-            return ContentType::Link;
+        if let Some(metadata) = url_metadata(trimmed) {
+            return Classification::new(ContentType::Link, Some(metadata));
         }
         if RE_EMAIL.is_match(trimmed) {
-            return ContentType::Email;
+            return ContentType::Email.into();
+        }
+        if let Some(metadata) = ip_metadata(trimmed) {
+            return Classification::new(ContentType::Ip, Some(metadata));
+        }
+        if let Some(caps) = RE_UUID.captures(trimmed) {
+            let version = u8::from_str_radix(&caps[1], 16).unwrap_or(0);
+            return Classification::new(ContentType::Uuid, Some(ContentMetadata::Uuid { version }));
         }
-        if RE_PHONE.is_match(trimmed) {
-            return ContentType::Phone;
+        if let Some(metadata) = datetime_metadata(trimmed) {
+            return Classification::new(ContentType::DateTime, Some(metadata));
         }
-        if RE_COLOR.is_match(trimmed) {
-            return ContentType::Color;
+        if let Some(metadata) = color_metadata(trimmed) {
+            return Classification::new(ContentType::Color, Some(metadata));
+        }
+        if let Some(metadata) = hash_metadata(trimmed) {
+            return Classification::new(ContentType::Hash, Some(metadata));
         }
         if RE_NUMBER.is_match(trimmed) {
-            return ContentType::Number;
+            let metadata = trimmed
+                .replace(',', "")
+                .parse()
+                .ok()
+                .map(|value| ContentMetadata::Number { value });
+            return Classification::new(ContentType::Number, metadata);
+        }
+        if is_phone(trimmed) {
+            return ContentType::Phone.into();
         }
         if RE_PATH.is_match(trimmed) {
-            return ContentType::Path;
+            return Classification::new(ContentType::Path, Some(path_metadata(trimmed)));
         }
     }
 
     // JSON check (multi-line okay)
-    if (trimmed.starts_with('{') && trimmed.ends_with('}'))
-        || (trimmed.starts_with('[') && trimmed.ends_with(']'))
-    {
-        if serde_json::from_str::<serde_json::Value>(trimmed).is_ok() {
-            return ContentType::Json;
+    if let Some(metadata) = json_metadata(trimmed) {
+        return Classification::new(ContentType::Json, Some(metadata));
+    }
+
+    if looks_like_markdown(trimmed) {
+        return ContentType::Markdown.into();
+    }
+
+    if let Some(language) = detect_language(trimmed) {
+        let metadata = ContentMetadata::Code { language: language.to_string() };
+        return Classification::new(ContentType::Code, Some(metadata));
+    }
+
+    ContentType::Text.into()
+}
+
+fn url_metadata(text: &str) -> Option<ContentMetadata> {
+    let caps = RE_URL.captures(text)?;
+    let scheme = caps[1].to_lowercase();
+    if !matches!(scheme.as_str(), "http" | "https" | "ftp" | "ftps" | "ws" | "wss" | "ssh" | "sftp") {
+        return None;
+    }
+    Some(ContentMetadata::Link {
+        scheme,
+        host: caps[2].trim_matches(['[', ']']).to_lowercase(),
+        port: caps.get(3).and_then(|port| port.as_str().parse().ok()),
+    })
+}
+
+fn ip_metadata(text: &str) -> Option<ContentMetadata> {
+    let (addr, prefix) = match text.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+        None => (text, None),
+    };
+    let addr: IpAddr = addr.parse().ok()?;
+    let (version, max_prefix, local) = match addr {
+        IpAddr::V4(v4) => (4, 32, v4.is_loopback() || v4.is_private() || v4.is_link_local()),
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            // fc00::/7 unique local, fe80::/10 link-local
+            let local = v6.is_loopback() || first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80;
+            (6, 128, local)
         }
+    };
+    if prefix.is_some_and(|prefix| prefix > max_prefix) {
+        return None;
+    }
+    Some(ContentMetadata::Ip { version, prefix, local })
+}
+
+fn datetime_metadata(text: &str) -> Option<ContentMetadata> {
+    let local = |naive: NaiveDateTime| Local.from_local_datetime(&naive).earliest();
+
+    let (timestamp, has_time) = if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        (dt.timestamp_millis(), true)
+    } else if let Ok(dt) = DateTime::parse_from_rfc2822(text) {
+        (dt.timestamp_millis(), true)
+    } else if let Some(dt) = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    {
+        (local(dt)?.timestamp_millis(), true)
+    } else if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        (local(date.and_hms_opt(0, 0, 0)?)?.timestamp_millis(), false)
+    } else {
+        return None;
+    };
+    Some(ContentMetadata::DateTime { timestamp, has_time })
+}
+
+fn color_metadata(text: &str) -> Option<ContentMetadata> {
+    let (rgb, alpha) = if RE_HEX_COLOR.is_match(text) {
+        let hex = &text[1..];
+        // Expand the short #rgb / #rgba forms
+        let hex: String = if hex.len() <= 4 {
+            hex.chars().flat_map(|c| [c, c]).collect()
+        } else {
+            hex.to_string()
+        };
+        let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+        let alpha = if hex.len() == 8 { channel(3)? as f32 / 255.0 } else { 1.0 };
+        ([channel(0)?, channel(1)?, channel(2)?], alpha)
+    } else {
+        let caps = RE_FN_COLOR.captures(text)?;
+        let args: Vec<f32> = (2..=4).map(|i| caps[i].parse().ok()).collect::<Option<_>>()?;
+        let alpha = match caps.get(5) {
+            Some(alpha) if caps.get(6).is_some_and(|pct| !pct.as_str().is_empty()) => {
+                alpha.as_str().parse::<f32>().ok()? / 100.0
+            }
+            Some(alpha) => alpha.as_str().parse().ok()?,
+            None => 1.0,
+        };
+        let rgb = if caps[1].to_lowercase().starts_with("rgb") {
+            if args.iter().any(|&c| c > 255.0) {
+                return None;
+            }
+            [args[0] as u8, args[1] as u8, args[2] as u8]
+        } else {
+            if args[1] > 100.0 || args[2] > 100.0 {
+                return None;
+            }
+            hsl_to_rgb(args[0], args[1] / 100.0, args[2] / 100.0)
+        };
+        (rgb, alpha.clamp(0.0, 1.0))
+    };
+    Some(ContentMetadata::Color { rgb, alpha, hsl: rgb_to_hsl(rgb) })
+}
+
+fn rgb_to_hsl([r, g, b]: [u8; 3]) -> [u16; 3] {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let d = max - min;
+    if d == 0.0 {
+        return [0, 0, (l * 100.0).round() as u16];
     }
+    let s = d / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        60.0 * ((g - b) / d).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / d + 2.0)
+    } else {
+        60.0 * ((r - g) / d + 4.0)
+    };
+    [h.round() as u16 % 360, (s * 100.0).round() as u16, (l * 100.0).round() as u16]
+}
+
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> [u8; 3] {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c / 2.0;
+    let channel = |v: f32| ((v + m) * 255.0).round() as u8;
+    [channel(r), channel(g), channel(b)]
+}
+
+/// Hex digests of a known length that contain at least one letter, so long
+/// decimal numbers aren't taken for hashes.
+fn hash_metadata(text: &str) -> Option<ContentMetadata> {
+    if !RE_HASH.is_match(text) || text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let algorithm = match text.len() {
+        32 => "md5",
+        40 => "sha1",
+        56 => "sha224",
+        64 => "sha256",
+        96 => "sha384",
+        128 => "sha512",
+        _ => return None,
+    };
+    Some(ContentMetadata::Hash { algorithm: algorithm.to_string() })
+}
+
+/// Phone numbers have 7 to 15 digits and are either international (`+`) or
+/// written with separators; a bare run of digits is a number.
+fn is_phone(text: &str) -> bool {
+    let digits = text.chars().filter(char::is_ascii_digit).count();
+    RE_PHONE.is_match(text)
+        && (7..=15).contains(&digits)
+        && (text.starts_with('+') || text.contains([' ', '-', '(', ')']))
+}
 
-    // Code heuristics: look for common programming patterns
-    if looks_like_code(trimmed) {
-        return ContentType::Code;
+/// `text` as a path, with a leading `~/` resolved to the home directory.
+pub(super) fn expand_path(text: &str) -> Option<PathBuf> {
+    match text.strip_prefix("~/") {
+        Some(rest) => std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+            .map(|home| Path::new(&home).join(rest)),
+        None => Some(PathBuf::from(text)),
     }
+}
+
+/// Whether `text` (a path) names a file on another machine: a UNC path
+/// (`\\server\share`, `//server/share`) or one on a network drive or mount.
+fn is_remote_path(text: &str) -> bool {
+    text.starts_with(r"\\") || text.starts_with("//") || is_network_mount(text)
+}
+
+/// Whether the drive of `text` (`Z:\...`) is mapped to a network share.
+#[cfg(target_os = "windows")]
+fn is_network_mount(text: &str) -> bool {
+    const DRIVE_REMOTE: u32 = 4;
+    let Some(root) = text.get(..3).filter(|root| root.ends_with(":\\")) else {
+        return false;
+    };
+    let root: Vec<u16> = root.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe { GetDriveTypeW(root.as_ptr()) == DRIVE_REMOTE }
+}
+
+/// Whether `text` lies under a network file system mount, going by the
+/// longest matching mount point in /proc/self/mounts.
+#[cfg(target_os = "linux")]
+fn is_network_mount(text: &str) -> bool {
+    const NETWORK_FS: &[&str] = &["nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "afs", "ceph", "glusterfs"];
+    let Some(path) = expand_path(text) else {
+        return false;
+    };
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return false;
+    };
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ').skip(1);
+            // Spaces in mount points are escaped as \040
+            let mount_point = fields.next()?.replace("\\040", " ");
+            Some((mount_point, fields.next()?))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.len())
+        .is_some_and(|(_, fs_type)| NETWORK_FS.contains(&fs_type) || fs_type.starts_with("fuse.sshfs"))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn is_network_mount(_text: &str) -> bool {
+    false
+}
+
+#[cfg(target_os = "windows")]
+extern "system" {
+    fn GetDriveTypeW(root_path_name: *const u16) -> u32;
+}
+
+fn path_metadata(text: &str) -> ContentMetadata {
+    if is_remote_path(text) {
+        return ContentMetadata::Path { exists: false, is_dir: false, remote: true };
+    }
+    let metadata = expand_path(text).and_then(|path| std::fs::metadata(path).ok());
+    ContentMetadata::Path {
+        exists: metadata.is_some(),
+        is_dir: metadata.is_some_and(|m| m.is_dir()),
+        remote: false,
+    }
+}
+
+fn json_metadata(text: &str) -> Option<ContentMetadata> {
+    let bracketed = (text.starts_with('{') && text.ends_with('}'))
+        || (text.starts_with('[') && text.ends_with(']'));
+    if !bracketed {
+        return None;
+    }
+    let (shape, len, keys) = match serde_json::from_str::<serde_json::Value>(text).ok()? {
+        serde_json::Value::Object(map) => {
+            let keys = map.keys().take(MAX_JSON_KEYS).cloned().collect();
+            ("object", map.len(), keys)
+        }
+        serde_json::Value::Array(items) => ("array", items.len(), Vec::new()),
+        _ => return None,
+    };
+    Some(ContentMetadata::Json { shape: shape.to_string(), len, keys })
+}
 
-    ContentType::Text
+/// Multi-line text using at least two different Markdown constructs.
+fn looks_like_markdown(text: &str) -> bool {
+    text.contains('\n') && MARKDOWN_PATTERNS.iter().filter(|re| re.is_match(text)).count() >= 2
 }
 
-fn looks_like_code(text: &str) -> bool {
-    let indicators = [
-        "fn ", "pub ", "let ", "const ", "var ", "function ", "class ",
-        "import ", "export ", "return ", "if (", "for (", "while (",
-        "def ", "async ", "await ", "=>", "->", "::", "&&", "||",
-        "println!", "console.log", "System.out", "fmt.",
-        "struct ", "enum ", "interface ", "type ", "impl ",
-        "#include", "#define", "using namespace",
-    ];
-    let count = indicators.iter().filter(|&&ind| text.contains(ind)).count();
-    // If 2+ indicators or has semicolons at line ends and braces
-    if count >= 2 {
-        return true;
+/// The language `text` is most likely written in, if it looks like code at
+/// all: two markers of one language, or three for a single line (prose can
+/// easily contain one or two).
+fn detect_language(text: &str) -> Option<&'static str> {
+    let min_score = if text.contains('\n') { 2 } else { 3 };
+    let (language, score) = LANGUAGES
+        .iter()
+        .map(|(language, markers)| (*language, markers.iter().filter(|m| text.contains(*m)).count()))
+        .fold(("", 0), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+    if score >= min_score {
+        return Some(language);
     }
+    // Brace-and-semicolon languages without a recognised keyword
     let has_braces = text.contains('{') && text.contains('}');
     let has_semicolons = text.lines().filter(|l| l.trim_end().ends_with(';')).count() >= 2;
-    has_braces && has_semicolons
+    (has_braces && has_semicolons).then_some("unknown")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORPUS: &str = include_str!("testdata/classifier_corpus.txt");
+
+    /// Every built-in type, with the ones `classify` never produces marked.
+    /// The match makes adding a variant without updating the corpus a
+    /// compile error.
+    fn produced_by_classify(content_type: &ContentType) -> bool {
+        match content_type {
+            ContentType::Text
+            | ContentType::Link
+            | ContentType::Email
+            | ContentType::Phone
+            | ContentType::Code
+            | ContentType::Path
+            | ContentType::Number
+            | ContentType::Json
+            | ContentType::Color
+            | ContentType::Ip
+            | ContentType::Uuid
+            | ContentType::DateTime
+            | ContentType::Hash
+            | ContentType::Markdown => true,
+            // Set by the monitor from the clipboard formats.
+            ContentType::Image | ContentType::Files => false,
+            ContentType::Custom(_) => false,
+        }
+    }
+
+    fn corpus() -> Vec<(ContentType, String)> {
+        CORPUS
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (name, text) = line.split_once(" | ").expect("corpus line without ` | `");
+                let content_type = serde_json::from_value(name.into()).expect("unknown content type");
+                (content_type, text.replace("\\n", "\n"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn golden_corpus() {
+        let failures: Vec<String> = corpus()
+            .into_iter()
+            .filter_map(|(expected, text)| {
+                let actual = classify(&text).content_type;
+                (actual != expected).then(|| format!("{:?}: expected {:?}, got {:?}", text, expected, actual))
+            })
+            .collect();
+        assert!(failures.is_empty(), "misclassified:\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn corpus_covers_every_built_in_type() {
+        let covered: Vec<ContentType> = corpus().into_iter().map(|(t, _)| t).collect();
+        assert!(covered.iter().all(produced_by_classify));
+        for name in [
+            "text", "link", "email", "phone", "code", "path", "number", "json", "color", "ip", "uuid",
+            "datetime", "hash", "markdown",
+        ] {
+            let content_type: ContentType = serde_json::from_value(name.into()).unwrap();
+            assert!(produced_by_classify(&content_type), "{} is not a built-in type", name);
+            assert!(covered.contains(&content_type), "no corpus sample for {}", name);
+        }
+    }
+
+    #[test]
+    fn metadata() {
+        assert_eq!(
+            classify("https://Example.com:8443/a").metadata,
+            Some(ContentMetadata::Link { scheme: "https".into(), host: "example.com".into(), port: Some(8443) })
+        );
+        assert_eq!(
+            classify("hsl(32 100% 50%)").metadata,
+            Some(ContentMetadata::Color { rgb: [255, 136, 0], alpha: 1.0, hsl: [32, 100, 50] })
+        );
+        assert_eq!(classify("1,234.5").metadata, Some(ContentMetadata::Number { value: 1234.5 }));
+        // Network paths aren't looked up
+        let remote = Some(ContentMetadata::Path { exists: false, is_dir: false, remote: true });
+        assert_eq!(classify(r"\\server\share\folder").metadata, remote);
+        assert_eq!(classify("//server/share").metadata, remote);
+        assert_eq!(
+            classify(r#"{"b": 1, "a": 2}"#).metadata,
+            Some(ContentMetadata::Json { shape: "object".into(), len: 2, keys: vec!["b".into(), "a".into()] })
+        );
+        assert_eq!(
+            classify("10.0.0.0/8").metadata,
+            Some(ContentMetadata::Ip { version: 4, prefix: Some(8), local: true })
+        );
+        assert_eq!(
+            classify("550e8400-e29b-41d4-a716-446655440000").metadata,
+            Some(ContentMetadata::Uuid { version: 4 })
+        );
+        assert_eq!(
+            classify("da39a3ee5e6b4b0d3255bfef95601890afd80709").metadata,
+            Some(ContentMetadata::Hash { algorithm: "sha1".into() })
+        );
+        assert_eq!(
+            classify("def f(x):\n    print(x)\n    return None").metadata,
+            Some(ContentMetadata::Code { language: "python".into() })
+        );
+        assert_eq!(
+            classify("2024-01-15T10:30:00Z").metadata,
+            Some(ContentMetadata::DateTime { timestamp: 1_705_314_600_000, has_time: true })
+        );
+    }
+
+    fn compiled(mut classifier: CustomClassifier) -> CustomClassifier {
+        classifier.compile().unwrap();
+        classifier
+    }
+
+    fn ticket_classifier() -> CustomClassifier {
+        serde_json::from_str(
+            r#"{
+                "name": "ticket",
+                "label": "Ticket",
+                "pattern": "(?<project>[A-Z]{2,10})-(?<number>\\d+)",
+                "actions": [
+                    { "label": "Open in tracker", "template": "https://tracker.example.com/browse/$0" },
+                    { "label": "Copy number", "kind": "copy", "template": "${number}" }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn custom_classifiers_run_first() {
+        let custom = [compiled(ticket_classifier())];
+        let classification = classify_with("  ABC-1234 ", &custom);
+        assert_eq!(classification.content_type, ContentType::Custom("ticket".into()));
+        assert_eq!(
+            classification.metadata,
+            Some(ContentMetadata::Custom {
+                label: "Ticket".into(),
+                actions: vec![
+                    QuickAction {
+                        label: "Open in tracker".into(),
+                        kind: QuickActionKind::Open,
+                        value: "https://tracker.example.com/browse/ABC-1234".into(),
+                    },
+                    QuickAction { label: "Copy number".into(), kind: QuickActionKind::Copy, value: "1234".into() },
+                ],
+            })
+        );
+
+        // The pattern has to match the whole text
+        assert_eq!(classify_with("see ABC-1234", &custom).content_type, ContentType::Text);
+        // Before the built-in rules: this would otherwise be a phone number
+        let digits = CustomClassifier { pattern: r"\d{3}-\d{4}".into(), ..ticket_classifier() };
+        assert_eq!(classify_with("555-1234", &[compiled(digits)]).content_type, ContentType::Custom("ticket".into()));
+    }
+
+    #[test]
+    fn custom_types_round_trip_as_their_name() {
+        let content_type = ContentType::Custom("ticket".into());
+        let json = serde_json::to_string(&content_type).unwrap();
+        assert_eq!(json, r#""ticket""#);
+        assert_eq!(serde_json::from_str::<ContentType>(&json).unwrap(), content_type);
+        assert_eq!(serde_json::from_str::<ContentType>(r#""link""#).unwrap(), ContentType::Link);
+    }
+
+    #[test]
+    fn custom_classifier_validation() {
+        assert!(ticket_classifier().compile().is_ok());
+        let invalid = |name: &str, pattern: &str| CustomClassifier {
+            name: name.into(),
+            pattern: pattern.into(),
+            ..ticket_classifier()
+        };
+        assert!(invalid("link", "x").compile().is_err());
+        assert!(invalid("Ticket", "x").compile().is_err());
+        assert!(invalid("", "x").compile().is_err());
+        assert!(invalid("ticket", "(").compile().is_err());
+
+        // Not compiled: matches nothing
+        assert_eq!(classify_with("ABC-1234", &[ticket_classifier()]).content_type, ContentType::Text);
+    }
 }
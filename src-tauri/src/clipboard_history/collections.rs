@@ -0,0 +1,97 @@
+use super::entry::ClipboardEntry;
+use serde::{Deserialize, Serialize};
+
+const MAX_NAME_LEN: usize = 64;
+const MAX_TAG_LEN: usize = 32;
+
+/// A named folder of clipboard entries. Entries in a collection are kept by
+/// the retention policy like pinned ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64,
+}
+
+impl Collection {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            created_at: chrono::Local::now().timestamp_millis(),
+        }
+    }
+}
+
+/// Trim `name` and check it is usable and not taken by another collection
+/// (ignoring case).
+pub fn validate_name(name: &str, collections: &[Collection], except_id: Option<&str>) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Collection name is empty".into());
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(format!("Collection names are limited to {} characters", MAX_NAME_LEN));
+    }
+    let taken = collections
+        .iter()
+        .any(|c| Some(c.id.as_str()) != except_id && c.name.to_lowercase() == name.to_lowercase());
+    if taken {
+        return Err(format!("A collection named {} already exists", name));
+    }
+    Ok(name.to_string())
+}
+
+/// Tags as stored: trimmed, without a leading `#`, lowercase, unique and
+/// sorted. Empty tags are dropped. Tags can't contain whitespace: exports
+/// separate them with spaces and Markdown writes them as `#tag`.
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let normalized = tags.iter().map(|tag| normalize_tag(tag)).collect::<Result<Vec<_>, _>>()?;
+    Ok(sorted_tags(normalized))
+}
+
+/// Like `normalize_tags`, but an invalid tag is dropped (and logged) instead
+/// of failing the whole list. For imported data.
+pub fn normalize_tags_lossy(tags: &[String]) -> Vec<String> {
+    let normalized = tags
+        .iter()
+        .filter_map(|tag| normalize_tag(tag).map_err(|e| log::warn!("Dropping tag: {}", e)).ok())
+        .collect();
+    sorted_tags(normalized)
+}
+
+fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().trim_start_matches('#').trim().to_lowercase();
+    if tag.chars().count() > MAX_TAG_LEN {
+        return Err(format!("Tag {} is longer than {} characters", tag, MAX_TAG_LEN));
+    }
+    if tag.contains(char::is_whitespace) {
+        return Err(format!("Tag {:?} can't contain spaces", tag));
+    }
+    Ok(tag)
+}
+
+fn sorted_tags(mut tags: Vec<String>) -> Vec<String> {
+    tags.retain(|tag| !tag.is_empty());
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Put `entries` into `collection` (`None` takes them out of any). Secrets
+/// only kept in memory are kept for good, as when pinning them. Returns the
+/// changed entries.
+pub fn move_entries(entries: &mut [ClipboardEntry], ids: &[String], collection: Option<&str>) -> Vec<ClipboardEntry> {
+    entries
+        .iter_mut()
+        .filter(|e| ids.contains(&e.id))
+        .map(|entry| {
+            entry.collection = collection.map(str::to_string);
+            if entry.collection.is_some() {
+                entry.expires_at = None;
+            }
+            entry.clone()
+        })
+        .collect()
+}
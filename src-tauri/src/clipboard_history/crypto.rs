@@ -0,0 +1,544 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+const CONFIG_FILE_NAME: &str = "clipboard_encryption.json";
+/// The configuration of a key change in progress; it replaces the current
+/// one once the database has been rewritten.
+const PENDING_CONFIG_FILE_NAME: &str = "clipboard_encryption.pending.json";
+const KEYRING_SERVICE: &str = "OhMyCommandBar";
+const KEYRING_USER: &str = "clipboard-history";
+/// Prefix of encrypted text columns; the rest is base64(nonce || ciphertext).
+const TEXT_PREFIX: &str = "enc1:";
+/// Header of encrypted files, followed by nonce || ciphertext.
+const FILE_MAGIC: &[u8] = b"OMCBENC1";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+/// Encrypted with a passphrase-derived key so `unlock` can tell a wrong
+/// passphrase from a right one.
+const CHECK_PLAINTEXT: &str = "clipboard-history";
+
+/// Where the history encryption key comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeySource {
+    /// History is stored unencrypted.
+    #[default]
+    None,
+    /// A random key kept in the OS keyring (Credential Manager, Keychain,
+    /// Secret Service).
+    Keyring,
+    /// A key derived from a passphrase the user enters on every start.
+    Passphrase,
+}
+
+/// Persisted next to the database. Holds no secrets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct KeyConfig {
+    source: KeySource,
+    /// Argon2 salt for `KeySource::Passphrase` (base64).
+    salt: Option<String>,
+    /// `CHECK_PLAINTEXT` encrypted with the passphrase-derived key.
+    check: Option<String>,
+    /// Identifies the key: it names the keyring entry and is recorded in the
+    /// database along with the rows it encrypted. Missing in configurations
+    /// written before key changes were recoverable.
+    key_id: Option<String>,
+}
+
+/// Encryption state reported to the frontend.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    pub source: KeySource,
+    /// The history is encrypted but the key isn't available yet (passphrase
+    /// not entered, or the keyring couldn't be read).
+    pub locked: bool,
+}
+
+/// An AES-256-GCM key.
+#[derive(Clone)]
+pub struct Cipher(Aes256Gcm);
+
+impl Cipher {
+    fn new(key: &[u8]) -> Self {
+        Self(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)))
+    }
+
+    /// nonce || ciphertext
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "Encryption failed".to_string())?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        if sealed.len() < NONCE_LEN {
+            return Err("Encrypted data is truncated".into());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.0
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Decryption failed (wrong key or corrupted data)".to_string())
+    }
+}
+
+struct State {
+    source: KeySource,
+    /// `None` while locked, and always when `source` is `KeySource::None`.
+    cipher: Option<Cipher>,
+}
+
+static STATE: RwLock<State> = RwLock::new(State {
+    source: KeySource::None,
+    cipher: None,
+});
+
+/// Serializes tests that depend on the key state above.
+#[cfg(test)]
+pub(super) fn test_lock() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn config_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(CONFIG_FILE_NAME)
+}
+
+fn pending_config_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(PENDING_CONFIG_FILE_NAME)
+}
+
+fn read_config(app_data_dir: &Path) -> KeyConfig {
+    read_config_file(&config_path(app_data_dir)).unwrap_or_default()
+}
+
+fn read_config_file(path: &Path) -> Option<KeyConfig> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+fn write_config_file(path: &Path, config: &KeyConfig) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
+
+/// The keyring entry holding the key `key_id`.
+fn keyring_entry(key_id: Option<&str>) -> Result<keyring::Entry, String> {
+    let user = match key_id {
+        Some(key_id) => format!("{}-{}", KEYRING_USER, key_id),
+        None => KEYRING_USER.to_string(),
+    };
+    keyring::Entry::new(KEYRING_SERVICE, &user).map_err(|e| e.to_string())
+}
+
+fn delete_keyring_key(key_id: Option<&str>) {
+    if let Err(e) = keyring_entry(key_id).and_then(|entry| entry.delete_credential().map_err(|e| e.to_string())) {
+        log::warn!("Failed to remove an old key from the keyring: {}", e);
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Cipher, String> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(Cipher::new(&key))
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Read the key configuration and, for keyring keys, the key itself. Call
+/// once at startup before the history is loaded.
+pub fn init(app_data_dir: &PathBuf) {
+    recover_key_change(app_data_dir);
+    let config = read_config(app_data_dir);
+    let cipher = match config.source {
+        KeySource::Keyring => match keyring_entry(config.key_id.as_deref())
+            .and_then(|e| e.get_secret().map_err(|e| e.to_string()))
+        {
+            Ok(key) if key.len() == 32 => Some(Cipher::new(&key)),
+            Ok(_) => {
+                log::error!("Clipboard history key in the keyring is invalid");
+                None
+            }
+            Err(e) => {
+                log::error!("Failed to read clipboard history key from the keyring: {}", e);
+                None
+            }
+        },
+        KeySource::None | KeySource::Passphrase => None,
+    };
+    if let Ok(mut state) = STATE.write() {
+        *state = State {
+            source: config.source,
+            cipher,
+        };
+    }
+}
+
+pub fn status() -> EncryptionStatus {
+    match STATE.read() {
+        Ok(state) => EncryptionStatus {
+            source: state.source,
+            locked: state.source != KeySource::None && state.cipher.is_none(),
+        },
+        Err(_) => EncryptionStatus {
+            source: KeySource::None,
+            locked: true,
+        },
+    }
+}
+
+/// Whether the history is encrypted and the key isn't available.
+pub fn is_locked() -> bool {
+    status().locked
+}
+
+/// Whether new data is written encrypted.
+pub fn is_enabled() -> bool {
+    STATE.read().is_ok_and(|state| state.cipher.is_some())
+}
+
+fn current_cipher() -> Result<Option<Cipher>, String> {
+    let state = STATE.read().map_err(|e| e.to_string())?;
+    match (&state.cipher, state.source) {
+        (Some(cipher), _) => Ok(Some(cipher.clone())),
+        (None, KeySource::None) => Ok(None),
+        (None, _) => Err("Clipboard history is locked".into()),
+    }
+}
+
+/// Unlock a passphrase-protected history.
+pub fn unlock(app_data_dir: &Path, passphrase: &str) -> Result<(), String> {
+    let config = read_config(app_data_dir);
+    let (KeySource::Passphrase, Some(salt), Some(check)) = (config.source, &config.salt, &config.check)
+    else {
+        return Err("Clipboard history is not protected by a passphrase".into());
+    };
+    let salt = BASE64.decode(salt).map_err(|e| e.to_string())?;
+    let cipher = derive_key(passphrase, &salt)?;
+    if decrypt_text_with(Some(&cipher), check.clone()).as_deref() != Ok(CHECK_PLAINTEXT) {
+        return Err("Wrong passphrase".into());
+    }
+    STATE.write().map_err(|e| e.to_string())?.cipher = Some(cipher);
+    Ok(())
+}
+
+/// Encrypt a text column with the current key, or return it unchanged when
+/// encryption is off.
+pub fn encrypt_text(text: &str) -> Result<String, String> {
+    match current_cipher()? {
+        Some(cipher) => Ok(format!("{}{}", TEXT_PREFIX, BASE64.encode(cipher.seal(text.as_bytes())?))),
+        None => Ok(text.to_string()),
+    }
+}
+
+/// Decrypt a text column written by `encrypt_text`. Unencrypted values are
+/// returned as they are.
+pub fn decrypt_text(value: String) -> Result<String, String> {
+    if !value.starts_with(TEXT_PREFIX) {
+        return Ok(value);
+    }
+    decrypt_text_with(current_cipher()?.as_ref(), value)
+}
+
+fn decrypt_text_with(cipher: Option<&Cipher>, value: String) -> Result<String, String> {
+    let Some(sealed) = value.strip_prefix(TEXT_PREFIX) else {
+        return Ok(value);
+    };
+    let cipher = cipher.ok_or("Clipboard history is locked")?;
+    let sealed = BASE64.decode(sealed).map_err(|e| e.to_string())?;
+    String::from_utf8(cipher.open(&sealed)?).map_err(|e| e.to_string())
+}
+
+/// Encrypt file contents with the current key, or return them unchanged
+/// when encryption is off.
+pub fn encrypt_bytes(data: &[u8]) -> Result<Vec<u8>, String> {
+    encrypt_bytes_with(current_cipher()?.as_ref(), data)
+}
+
+/// Decrypt file contents written by `encrypt_bytes`. Unencrypted data is
+/// returned as it is.
+pub fn decrypt_bytes(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    if !data.starts_with(FILE_MAGIC) {
+        return Ok(data);
+    }
+    decrypt_bytes_with(current_cipher()?.as_ref(), data)
+}
+
+pub(super) fn encrypt_bytes_with(cipher: Option<&Cipher>, data: &[u8]) -> Result<Vec<u8>, String> {
+    match cipher {
+        Some(cipher) => Ok([FILE_MAGIC, &cipher.seal(data)?].concat()),
+        None => Ok(data.to_vec()),
+    }
+}
+
+pub(super) fn decrypt_bytes_with(cipher: Option<&Cipher>, data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let Some(sealed) = data.strip_prefix(FILE_MAGIC) else {
+        return Ok(data);
+    };
+    cipher.ok_or("Clipboard history is locked")?.open(sealed)
+}
+
+/// Switch to a new key (or to no encryption) and re-encrypt everything
+/// stored with it: the database rows and the image files. Called with the
+/// current source this rotates the key.
+///
+/// The new configuration is written aside and the new keyring key saved
+/// under its own name, so the old key stays usable until the rows are
+/// rewritten (in one transaction that also records the new key id). Only
+/// then is the configuration swapped and the old key removed; a crash in
+/// between is sorted out by `recover_key_change` on the next start.
+pub fn change_key(
+    app_data_dir: &PathBuf,
+    source: KeySource,
+    passphrase: Option<&str>,
+) -> Result<(), String> {
+    let old_cipher = current_cipher()?;
+    let old_config = read_config(app_data_dir);
+    let entries = super::storage::load_all(app_data_dir)?;
+
+    let key_id = uuid::Uuid::new_v4().to_string();
+    let (config, cipher, keyring_key) = match source {
+        KeySource::None => (KeyConfig::default(), None, None),
+        KeySource::Keyring => {
+            let key = random_bytes::<32>();
+            let config = KeyConfig {
+                source,
+                ..Default::default()
+            };
+            (config, Some(Cipher::new(&key)), Some(key))
+        }
+        KeySource::Passphrase => {
+            let passphrase = passphrase
+                .filter(|p| !p.is_empty())
+                .ok_or("A passphrase is required")?;
+            let salt = random_bytes::<SALT_LEN>();
+            let cipher = derive_key(passphrase, &salt)?;
+            let check = BASE64.encode(cipher.seal(CHECK_PLAINTEXT.as_bytes())?);
+            let config = KeyConfig {
+                source,
+                salt: Some(BASE64.encode(salt)),
+                check: Some(format!("{}{}", TEXT_PREFIX, check)),
+                ..Default::default()
+            };
+            (config, Some(cipher), None)
+        }
+    };
+    let config = KeyConfig {
+        key_id: Some(key_id.clone()),
+        ..config
+    };
+
+    // Re-encrypt images into temporary files first; the old files are kept
+    // until the database has been rewritten too.
+    let staged = super::images::stage_reencrypt(&entries, old_cipher.as_ref(), cipher.as_ref())?;
+    let pending_path = pending_config_path(app_data_dir);
+    let prepared = keyring_key
+        .as_ref()
+        .map_or(Ok(()), |key| {
+            keyring_entry(Some(&key_id))?
+                .set_secret(key)
+                .map_err(|e| format!("Failed to store key in the keyring: {}", e))
+        })
+        .and_then(|_| write_config_file(&pending_path, &config));
+    if let Err(e) = prepared {
+        super::images::discard_staged(&staged);
+        if keyring_key.is_some() {
+            delete_keyring_key(Some(&key_id));
+        }
+        return Err(e);
+    }
+
+    set_state(source, cipher.clone());
+    let enabled = cipher.is_some();
+    let mut entries = entries;
+    for entry in &mut entries {
+        if let Some(image) = &mut entry.image {
+            image.encrypted = enabled;
+        }
+    }
+    let result = super::images::commit_staged(&staged).and_then(|_| {
+        super::storage::rewrite_all(app_data_dir, &entries, &key_id)
+            .inspect_err(|_| super::images::restore_backups(&staged))
+    });
+    if let Err(e) = result {
+        log::error!("Failed to re-encrypt clipboard history, keeping the old key: {}", e);
+        set_state(old_config.source, old_cipher);
+        let _ = std::fs::remove_file(&pending_path);
+        if keyring_key.is_some() {
+            delete_keyring_key(Some(&key_id));
+        }
+        return Err(e);
+    }
+
+    // The rows are committed: from here on the new key is the one to use.
+    if let Err(e) = std::fs::rename(&pending_path, config_path(app_data_dir)) {
+        log::error!("Failed to save the clipboard encryption settings, retrying on next start: {}", e);
+        return Ok(());
+    }
+    super::images::remove_backups(&staged);
+    if old_config.source == KeySource::Keyring {
+        delete_keyring_key(old_config.key_id.as_deref());
+    }
+    log::info!("Clipboard history encryption set to {:?}", source);
+    Ok(())
+}
+
+/// Finish or undo a key change interrupted by a crash: if the database
+/// records the pending key, the rewrite was committed and the pending
+/// configuration takes over; otherwise it is dropped along with its key.
+fn recover_key_change(app_data_dir: &PathBuf) {
+    let pending_path = pending_config_path(app_data_dir);
+    if !pending_path.exists() {
+        return;
+    }
+    // Unreadable if the crash hit while it was written, before any rows
+    let pending = read_config_file(&pending_path).unwrap_or_default();
+    let stored_key_id = match super::storage::key_id(app_data_dir) {
+        Ok(key_id) => key_id,
+        Err(e) => {
+            log::error!("Failed to check for an interrupted clipboard key change: {}", e);
+            return;
+        }
+    };
+    let committed = pending.key_id.is_some() && stored_key_id == pending.key_id;
+    if committed {
+        let old_config = read_config(app_data_dir);
+        if let Err(e) = std::fs::rename(&pending_path, config_path(app_data_dir)) {
+            log::error!("Failed to finish the clipboard key change: {}", e);
+            return;
+        }
+        if old_config.source == KeySource::Keyring {
+            delete_keyring_key(old_config.key_id.as_deref());
+        }
+    } else {
+        let _ = std::fs::remove_file(&pending_path);
+        if pending.source == KeySource::Keyring {
+            delete_keyring_key(pending.key_id.as_deref());
+        }
+    }
+    super::images::finish_key_change(app_data_dir, committed);
+    log::warn!(
+        "Recovered from an interrupted clipboard key change ({})",
+        if committed { "completed" } else { "rolled back" }
+    );
+}
+
+fn set_state(source: KeySource, cipher: Option<Cipher>) {
+    if let Ok(mut state) = STATE.write() {
+        *state = State { source, cipher };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard_history::entry::{ClipboardEntry, ContentType};
+    use crate::clipboard_history::{images, storage};
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clipboard-crypto-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn stored_content(dir: &PathBuf) -> String {
+        storage::open(dir)
+            .unwrap()
+            .query_row("SELECT content FROM entries", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn encrypt_rotate_and_load() {
+        let _lock = test_lock();
+        set_state(KeySource::None, None);
+        let dir = temp_dir();
+        storage::insert(&dir, &ClipboardEntry::new("secret text".into(), ContentType::Text, "Editor".into()));
+
+        change_key(&dir, KeySource::Passphrase, Some("first")).unwrap();
+        assert!(stored_content(&dir).starts_with(TEXT_PREFIX));
+        assert_eq!(storage::load_all(&dir).unwrap()[0].content, "secret text");
+
+        change_key(&dir, KeySource::Passphrase, Some("second")).unwrap();
+        assert!(!pending_config_path(&dir).exists());
+        assert_eq!(storage::key_id(&dir).unwrap(), read_config(&dir).key_id);
+
+        // As on the next start
+        init(&dir);
+        assert!(is_locked());
+        assert!(unlock(&dir, "first").is_err());
+        unlock(&dir, "second").unwrap();
+        assert_eq!(storage::load_all(&dir).unwrap()[0].content, "secret text");
+
+        change_key(&dir, KeySource::None, None).unwrap();
+        assert_eq!(stored_content(&dir), "secret text");
+        set_state(KeySource::None, None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn interrupted_key_change_is_rolled_back_or_finished() {
+        let _lock = test_lock();
+        set_state(KeySource::None, None);
+        let dir = temp_dir();
+        storage::insert(&dir, &ClipboardEntry::new("secret text".into(), ContentType::Text, "Editor".into()));
+        change_key(&dir, KeySource::Passphrase, Some("first")).unwrap();
+        let config = read_config(&dir);
+        let images_dir = images::images_dir(&dir);
+        std::fs::create_dir_all(&images_dir).unwrap();
+        let image = images_dir.join("a.png");
+        let write = |name: &str, contents: &str| std::fs::write(images_dir.join(name), contents).unwrap();
+
+        // Crashed before the rows were committed: the old key stays
+        let never_committed = KeyConfig {
+            key_id: Some("never-committed".into()),
+            ..config.clone()
+        };
+        write_config_file(&pending_config_path(&dir), &never_committed).unwrap();
+        write("a.png", "new");
+        write("a.png.old", "old");
+        write("b.png.rekey", "staged");
+        init(&dir);
+        assert!(!pending_config_path(&dir).exists());
+        assert_eq!(read_config(&dir).key_id, config.key_id);
+        assert_eq!(std::fs::read_to_string(&image).unwrap(), "old");
+        assert!(!images_dir.join("a.png.old").exists() && !images_dir.join("b.png.rekey").exists());
+        unlock(&dir, "first").unwrap();
+        assert_eq!(storage::load_all(&dir).unwrap()[0].content, "secret text");
+
+        // Crashed after the rows were committed: the new key takes over
+        write_config_file(&config_path(&dir), &KeyConfig::default()).unwrap();
+        write_config_file(&pending_config_path(&dir), &config).unwrap();
+        write("a.png", "new");
+        write("a.png.old", "old");
+        init(&dir);
+        assert!(!pending_config_path(&dir).exists());
+        assert_eq!(read_config(&dir).source, KeySource::Passphrase);
+        assert_eq!(read_config(&dir).key_id, config.key_id);
+        assert_eq!(std::fs::read_to_string(&image).unwrap(), "new");
+        assert!(!images_dir.join("a.png.old").exists());
+
+        set_state(KeySource::None, None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
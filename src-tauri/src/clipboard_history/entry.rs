@@ -1,7 +1,8 @@
+use super::source_app::SourceDetails;
 use serde::{Deserialize, Serialize};
 
 /// The type of content detected in a clipboard entry.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum ContentType {
     Text,
@@ -14,6 +15,150 @@ pub enum ContentType {
     Json,
     Color,
     Image,
+    /// A list of files copied from a file manager.
+    Files,
+    /// IPv4 or IPv6 address, optionally with a CIDR prefix.
+    Ip,
+    Uuid,
+    /// ISO 8601 / RFC 3339 or RFC 2822 date or date-time.
+    #[serde(rename = "datetime")]
+    DateTime,
+    /// Hex digest such as an MD5, SHA-1 or SHA-256 hash.
+    Hash,
+    Markdown,
+    /// A user-defined type from a `CustomClassifier`, serialized as its name.
+    #[serde(untagged)]
+    Custom(String),
+}
+
+/// Details the classifier extracted from an entry, depending on its type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ContentMetadata {
+    Code {
+        /// Best guess, e.g. `"rust"`, `"python"`, `"sql"`.
+        language: String,
+    },
+    Link {
+        scheme: String,
+        host: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        port: Option<u16>,
+    },
+    Color {
+        rgb: [u8; 3],
+        alpha: f32,
+        /// Hue in degrees, saturation and lightness in percent.
+        hsl: [u16; 3],
+    },
+    Path {
+        /// Whether the path existed when it was copied.
+        exists: bool,
+        is_dir: bool,
+        /// A UNC path or one on a network drive, which isn't checked: an
+        /// unreachable server would block the clipboard monitor.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        remote: bool,
+    },
+    Number {
+        value: f64,
+    },
+    Json {
+        /// `"object"` or `"array"`.
+        shape: String,
+        /// Number of keys or elements.
+        len: usize,
+        /// The first top-level keys of an object.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        keys: Vec<String>,
+    },
+    Ip {
+        version: u8,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prefix: Option<u8>,
+        /// Loopback, private or link-local address.
+        local: bool,
+    },
+    Uuid {
+        version: u8,
+    },
+    #[serde(rename = "datetime")]
+    DateTime {
+        /// Milliseconds since the epoch; local midnight for a bare date.
+        timestamp: i64,
+        has_time: bool,
+    },
+    Hash {
+        /// Algorithms producing a digest of this length, e.g. `"sha256"`.
+        algorithm: String,
+    },
+    /// Matched by a user-defined classifier.
+    Custom {
+        label: String,
+        /// The classifier's quick actions, expanded for this entry.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        actions: Vec<QuickAction>,
+    },
+}
+
+/// What a quick action does with its `value`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuickActionKind {
+    /// Open a URL or file with the default handler.
+    #[default]
+    Open,
+    Copy,
+    Paste,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickAction {
+    pub label: String,
+    #[serde(default)]
+    pub kind: QuickActionKind,
+    pub value: String,
+}
+
+/// Representations of an entry besides plain text, restored together with it
+/// when the entry is copied back to the clipboard.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardFormats {
+    /// HTML fragment (`text/html`, or the fragment of Windows "HTML Format").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+    /// RTF document (`text/rtf`, Windows "Rich Text Format").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rtf: Option<String>,
+    /// Absolute paths of copied files (`text/uri-list`, Windows `CF_HDROP`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+impl ClipboardFormats {
+    pub fn is_empty(&self) -> bool {
+        self.html.is_none() && self.rtf.is_none() && self.files.is_empty()
+    }
+}
+
+/// Metadata for an image entry. The PNG and its thumbnail live in the
+/// `clipboard_images` directory next to `clipboard_history.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfo {
+    /// Absolute path of the full-size PNG.
+    pub path: String,
+    /// Absolute path of the thumbnail PNG.
+    pub thumbnail_path: String,
+    pub width: u32,
+    pub height: u32,
+    /// Size of the full-size PNG in bytes.
+    pub byte_size: usize,
+    /// The files are encrypted and must be read through `read_clipboard_image`.
+    #[serde(default)]
+    pub encrypted: bool,
 }
 
 /// A single clipboard history entry.
@@ -24,16 +169,45 @@ pub struct ClipboardEntry {
     pub content: String,
     pub content_type: ContentType,
     pub source_app: String,
+    /// Executable path, window title and icon of the source app.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_details: Option<SourceDetails>,
     pub word_count: usize,
     pub char_count: usize,
     pub timestamp: i64,
     pub pinned: bool,
+    /// Id of the collection the entry was filed in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Lowercase labels, sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     /// Preview text (truncated to 200 chars).
     pub preview: String,
-    /// Hash of content for dedup (used by monitor, not serialized).
-    #[serde(skip)]
-    #[allow(dead_code)]
+    /// Set for `ContentType::Image` entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageInfo>,
+    /// Rich representations captured alongside `content`.
+    #[serde(default, skip_serializing_if = "ClipboardFormats::is_empty")]
+    pub formats: ClipboardFormats,
+    /// What the classifier found out about `content`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ContentMetadata>,
+    /// When set (ms since epoch), the entry looked like a secret: it is kept
+    /// in memory only and removed at this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Hash of the text, or of the PNG bytes for images, used to find duplicates.
+    #[serde(default)]
     pub content_hash: u64,
+    /// How many times this content was copied; duplicates are merged into
+    /// one entry.
+    #[serde(default = "default_copy_count")]
+    pub copy_count: u32,
+}
+
+fn default_copy_count() -> u32 {
+    1
 }
 
 impl ClipboardEntry {
@@ -54,20 +228,148 @@ impl ClipboardEntry {
             content,
             content_type,
             source_app,
+            source_details: None,
             word_count,
             char_count,
             timestamp: chrono::Local::now().timestamp_millis(),
             pinned: false,
+            collection: None,
+            tags: Vec::new(),
             preview,
+            image: None,
+            formats: ClipboardFormats::default(),
+            metadata: None,
+            expires_at: None,
             content_hash,
+            copy_count: 1,
         }
     }
 
+    /// Attach the rich representations captured with the text.
+    pub fn with_formats(mut self, formats: ClipboardFormats) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Option<ContentMetadata>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_source_details(mut self, details: SourceDetails) -> Self {
+        self.source_details = (!details.is_empty()).then_some(details);
+        self
+    }
+
+    /// Build an image entry. `content_hash` is the hash of the PNG bytes.
+    pub fn new_image(image: ImageInfo, content_hash: u64, source_app: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            content: String::new(),
+            content_type: ContentType::Image,
+            source_app,
+            source_details: None,
+            word_count: 0,
+            char_count: 0,
+            timestamp: chrono::Local::now().timestamp_millis(),
+            pinned: false,
+            collection: None,
+            tags: Vec::new(),
+            preview: format!("Image {}×{}", image.width, image.height),
+            image: Some(image),
+            formats: ClipboardFormats::default(),
+            metadata: None,
+            expires_at: None,
+            content_hash,
+            copy_count: 1,
+        }
+    }
+
+    /// Whether the retention policy always keeps the entry: it is pinned or
+    /// filed in a collection.
+    pub fn is_kept(&self) -> bool {
+        self.pinned || self.collection.is_some()
+    }
+
+    /// Whether `other` holds the same content as this entry.
+    pub fn is_duplicate_of(&self, other: &ClipboardEntry) -> bool {
+        self.content_hash == other.content_hash
+            && self.content == other.content
+            && self.content_type == other.content_type
+            && self.image.as_ref().map(|i| i.byte_size) == other.image.as_ref().map(|i| i.byte_size)
+    }
+
+    /// Fold a new copy of the same content into this entry: it moves to
+    /// now, counts the copy and takes the latest source and formats. The
+    /// pinned state is kept. Otherwise the entry takes the copy's expiry, so
+    /// a stored entry copied again as a secret only lives in memory too.
+    pub fn record_copy(&mut self, copy: &ClipboardEntry) {
+        self.timestamp = copy.timestamp;
+        self.copy_count += 1;
+        self.source_app = copy.source_app.clone();
+        self.source_details = copy.source_details.clone();
+        if !copy.formats.is_empty() {
+            self.formats = copy.formats.clone();
+        }
+        if !self.is_kept() {
+            self.expires_at = copy.expires_at;
+        }
+    }
+
+    /// Hash used for dedup. It's stored, so it must stay the same across
+    /// builds (unlike `DefaultHasher`): the first 8 bytes of SHA-256.
     pub fn hash_content(content: &str) -> u64 {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        let mut hasher = DefaultHasher::new();
-        content.hash(&mut hasher);
-        hasher.finish()
+        Self::hash_bytes(content.as_bytes())
+    }
+
+    /// Approximate storage footprint: text, rich formats and image file.
+    pub fn size_bytes(&self) -> u64 {
+        let formats = self.formats.html.as_ref().map_or(0, String::len)
+            + self.formats.rtf.as_ref().map_or(0, String::len)
+            + self.formats.files.iter().map(String::len).sum::<usize>();
+        let image = self.image.as_ref().map_or(0, |image| image.byte_size);
+        (self.content.len() + formats + image) as u64
+    }
+
+    pub fn hash_bytes(bytes: &[u8]) -> u64 {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::digest(bytes);
+        u64::from_be_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn copy_of(entry: &ClipboardEntry, expires_at: Option<i64>) -> ClipboardEntry {
+        let mut copy = ClipboardEntry::new(entry.content.clone(), entry.content_type.clone(), "app".into());
+        copy.expires_at = expires_at;
+        copy
+    }
+
+    #[test]
+    fn secret_copy_moves_a_stored_entry_to_memory() {
+        let mut entry = ClipboardEntry::new("hunter2".into(), ContentType::Text, "app".into());
+        let copy = copy_of(&entry, Some(42));
+        entry.record_copy(&copy);
+        assert_eq!(entry.expires_at, Some(42));
+        assert_eq!(entry.copy_count, 2);
+    }
+
+    #[test]
+    fn plain_copy_keeps_a_secret_entry_stored() {
+        let mut entry = ClipboardEntry::new("hunter2".into(), ContentType::Text, "app".into());
+        entry.expires_at = Some(42);
+        entry.record_copy(&copy_of(&entry, None));
+        assert_eq!(entry.expires_at, None);
+    }
+
+    #[test]
+    fn kept_entries_never_expire() {
+        let mut entry = ClipboardEntry::new("hunter2".into(), ContentType::Text, "app".into());
+        entry.pinned = true;
+        entry.record_copy(&copy_of(&entry, Some(42)));
+        assert_eq!(entry.expires_at, None);
     }
 }
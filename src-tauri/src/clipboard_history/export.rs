@@ -0,0 +1,494 @@
+use super::classifier::{self, CustomClassifier};
+use super::collections::{normalize_tags_lossy, Collection};
+use super::entry::{ClipboardEntry, ContentMetadata, ContentType};
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Version written to JSON exports.
+const JSON_VERSION: u32 = 1;
+const CSV_HEADER: [&str; 8] = ["id", "timestamp", "content_type", "source_app", "pinned", "copy_count", "tags", "content"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Every field of every entry; imports losslessly.
+    Json,
+    /// One row per entry, for spreadsheets. Types are detected again on import.
+    Csv,
+    /// A readable digest grouped by day. Export only.
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    /// Check a caller-supplied export path: it must be absolute and end in
+    /// this format's extension, so an export can't be aimed at the database,
+    /// settings or other files relative to the working directory.
+    pub fn check_path(self, path: &Path) -> Result<(), String> {
+        if !path.is_absolute() {
+            return Err(format!("Export path {} is not absolute", path.display()));
+        }
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        if !extension.eq_ignore_ascii_case(self.extension()) {
+            return Err(format!("Export path {} must end in .{}", path.display(), self.extension()));
+        }
+        Ok(())
+    }
+}
+
+/// Which entries to export. Every filter is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportFilter {
+    /// Inclusive lower bound on the entry timestamp (ms since epoch).
+    pub from: Option<i64>,
+    /// Inclusive upper bound on the entry timestamp (ms since epoch).
+    pub to: Option<i64>,
+    pub content_types: Vec<ContentType>,
+    pub pinned_only: bool,
+}
+
+impl ExportFilter {
+    fn matches(&self, entry: &ClipboardEntry) -> bool {
+        self.from.map_or(true, |from| entry.timestamp >= from)
+            && self.to.map_or(true, |to| entry.timestamp <= to)
+            && (self.content_types.is_empty() || self.content_types.contains(&entry.content_type))
+            && (!self.pinned_only || entry.pinned)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonExport {
+    version: u32,
+    exported_at: i64,
+    entries: Vec<ClipboardEntry>,
+}
+
+/// Accepted JSON documents: an export, or the entry list of the
+/// `clipboard_history.json` file used before the SQLite store.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonImport {
+    Export(JsonExport),
+    Legacy(Vec<ClipboardEntry>),
+}
+
+/// What an import did.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    /// Entries that were not in the history yet.
+    pub added: usize,
+    /// Entries folded into an existing entry with the same content.
+    pub merged: usize,
+}
+
+/// Render the entries matching `filter`. Images (whose files stay in the app
+/// data directory) and secrets that only live in memory are left out.
+pub fn export(entries: &[ClipboardEntry], format: ExportFormat, filter: &ExportFilter) -> Result<String, String> {
+    let entries: Vec<&ClipboardEntry> = entries
+        .iter()
+        .filter(|e| e.image.is_none() && e.expires_at.is_none() && filter.matches(e))
+        .collect();
+    match format {
+        ExportFormat::Json => {
+            let export = JsonExport {
+                version: JSON_VERSION,
+                exported_at: Local::now().timestamp_millis(),
+                entries: entries.into_iter().cloned().collect(),
+            };
+            serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+        }
+        ExportFormat::Csv => to_csv(&entries),
+        ExportFormat::Markdown => Ok(to_markdown(&entries)),
+    }
+}
+
+/// First characters that make spreadsheets read a cell as a formula.
+const CSV_FORMULA_CHARS: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Whether `value`, once any leading `'` are removed, would be read as a
+/// formula.
+fn is_formula_like(value: &str) -> bool {
+    value.trim_start_matches('\'').starts_with(CSV_FORMULA_CHARS)
+}
+
+/// Quote a cell that a spreadsheet would run as a formula with a leading
+/// `'`. Cells already starting with `'` before such a character get one more,
+/// so `unescape_cell` restores every value exactly.
+fn escape_cell(value: &str) -> String {
+    if is_formula_like(value) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn unescape_cell(value: &str) -> &str {
+    match value.strip_prefix('\'') {
+        Some(rest) if is_formula_like(value) => rest,
+        _ => value,
+    }
+}
+
+fn to_csv(entries: &[&ClipboardEntry]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(CSV_HEADER).map_err(|e| e.to_string())?;
+    for entry in entries {
+        let record = [
+            entry.id.clone(),
+            local_time(entry.timestamp).to_rfc3339(),
+            type_name(&entry.content_type),
+            entry.source_app.clone(),
+            entry.pinned.to_string(),
+            entry.copy_count.to_string(),
+            entry.tags.join(" "),
+            entry.content.clone(),
+        ];
+        writer
+            .write_record(record.iter().map(|value| escape_cell(value)))
+            .map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+fn to_markdown(entries: &[&ClipboardEntry]) -> String {
+    let mut out = format!(
+        "# Clipboard history\n\nExported {} · {} entries\n",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        entries.len()
+    );
+    let mut day = String::new();
+    for entry in entries {
+        let time = local_time(entry.timestamp);
+        let entry_day = time.format("%Y-%m-%d").to_string();
+        if entry_day != day {
+            out.push_str(&format!("\n## {}\n", entry_day));
+            day = entry_day;
+        }
+
+        let mut heading = format!("{} · {} · {}", time.format("%H:%M"), type_name(&entry.content_type), entry.source_app);
+        if entry.pinned {
+            heading.push_str(" · pinned");
+        }
+        if entry.copy_count > 1 {
+            heading.push_str(&format!(" · copied {}×", entry.copy_count));
+        }
+        for tag in &entry.tags {
+            heading.push_str(&format!(" #{}", tag));
+        }
+        let language = match &entry.metadata {
+            Some(ContentMetadata::Code { language }) if language != "unknown" => language.as_str(),
+            _ if entry.content_type == ContentType::Json => "json",
+            _ if entry.content_type == ContentType::Markdown => "markdown",
+            _ => "",
+        };
+        // The fence must be longer than any backtick run in the content
+        let longest_run = entry
+            .content
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        out.push_str(&format!(
+            "\n### {}\n\n{}{}\n{}\n{}\n",
+            heading,
+            fence,
+            language,
+            entry.content.trim_end_matches('\n'),
+            fence
+        ));
+    }
+    out
+}
+
+/// Parse an export. Entries keep their ids, timestamps, pinned state and
+/// tags; content hashes are recomputed. Entries filed in a collection that
+/// isn't in `collections` are taken out of it.
+pub fn parse(
+    text: &str,
+    format: ExportFormat,
+    custom: &[CustomClassifier],
+    collections: &[Collection],
+) -> Result<Vec<ClipboardEntry>, String> {
+    let entries = match format {
+        ExportFormat::Json => match serde_json::from_str(text).map_err(|e| format!("Invalid export: {}", e))? {
+            JsonImport::Export(export) if export.version > JSON_VERSION => {
+                return Err(format!("Export version {} is newer than this app supports", export.version));
+            }
+            JsonImport::Export(export) => export.entries,
+            JsonImport::Legacy(entries) => entries,
+        },
+        ExportFormat::Csv => from_csv(text, custom)?,
+        ExportFormat::Markdown => return Err("Markdown digests can't be imported".into()),
+    };
+    Ok(entries
+        .into_iter()
+        .filter(|e| e.image.is_none() && !e.content.is_empty())
+        .map(|mut entry| {
+            entry.content_hash = ClipboardEntry::hash_content(&entry.content);
+            entry.expires_at = None;
+            entry.copy_count = entry.copy_count.max(1);
+            if let Some(id) = &entry.collection {
+                if !collections.iter().any(|c| &c.id == id) {
+                    entry.collection = None;
+                }
+            }
+            entry.tags = normalize_tags_lossy(&entry.tags);
+            entry
+        })
+        .collect())
+}
+
+fn from_csv(text: &str, custom: &[CustomClassifier]) -> Result<Vec<ClipboardEntry>, String> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let content_column = column("content").ok_or("The CSV file has no content column")?;
+
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Invalid CSV: {}", e))?;
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(unescape_cell)
+                .filter(|value| !value.is_empty())
+        };
+
+        let content = field(Some(content_column)).unwrap_or_default().to_string();
+        let classification = classifier::classify_with(&content, custom);
+        let source_app = field(column("source_app")).unwrap_or("Unknown").to_string();
+        let mut entry = ClipboardEntry::new(content, classification.content_type, source_app)
+            .with_metadata(classification.metadata);
+        if let Some(id) = field(column("id")) {
+            entry.id = id.to_string();
+        }
+        if let Some(timestamp) = field(column("timestamp")).and_then(parse_timestamp) {
+            entry.timestamp = timestamp;
+        }
+        entry.pinned = field(column("pinned")).is_some_and(|pinned| pinned.eq_ignore_ascii_case("true"));
+        if let Some(count) = field(column("copy_count")).and_then(|count| count.parse().ok()) {
+            entry.copy_count = count;
+        }
+        if let Some(tags) = field(column("tags")) {
+            entry.tags = tags.split_whitespace().map(str::to_string).collect();
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// RFC 3339 as exported, or milliseconds since the epoch.
+fn parse_timestamp(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.timestamp_millis())
+        .ok()
+        .or_else(|| value.parse().ok())
+}
+
+/// Fold `imported` into `entries`, newest first. An entry whose content is
+/// already in the history is merged into it: copies are added up, it stays
+/// pinned if either was and keeps the later timestamp. Returns the new and
+/// changed entries to store; secrets kept in memory stay there.
+pub fn merge(entries: &mut Vec<ClipboardEntry>, imported: Vec<ClipboardEntry>) -> (Vec<ClipboardEntry>, ImportSummary) {
+    let mut summary = ImportSummary::default();
+    let mut changed_ids = HashSet::new();
+    for mut entry in imported {
+        match entries.iter_mut().find(|e| e.is_duplicate_of(&entry)) {
+            Some(existing) => {
+                existing.copy_count = existing.copy_count.saturating_add(entry.copy_count);
+                existing.pinned |= entry.pinned;
+                if entry.timestamp > existing.timestamp {
+                    existing.timestamp = entry.timestamp;
+                    existing.source_app = entry.source_app;
+                    existing.source_details = entry.source_details;
+                }
+                if existing.metadata.is_none() {
+                    existing.metadata = entry.metadata;
+                }
+                if existing.collection.is_none() {
+                    existing.collection = entry.collection;
+                }
+                existing.tags.extend(entry.tags);
+                existing.tags.sort();
+                existing.tags.dedup();
+                changed_ids.insert(existing.id.clone());
+                summary.merged += 1;
+            }
+            None => {
+                if entries.iter().any(|e| e.id == entry.id) {
+                    entry.id = uuid::Uuid::new_v4().to_string();
+                }
+                changed_ids.insert(entry.id.clone());
+                entries.push(entry);
+                summary.added += 1;
+            }
+        }
+    }
+    entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+
+    let changed = entries
+        .iter()
+        .filter(|e| e.expires_at.is_none() && changed_ids.contains(&e.id))
+        .cloned()
+        .collect();
+    (changed, summary)
+}
+
+fn local_time(timestamp: i64) -> DateTime<Local> {
+    Local
+        .timestamp_millis_opt(timestamp)
+        .single()
+        .unwrap_or_else(Local::now)
+}
+
+fn type_name(content_type: &ContentType) -> String {
+    match serde_json::to_value(content_type) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => "text".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard_history::collections::normalize_tags;
+
+    fn entry(content: &str, timestamp: i64) -> ClipboardEntry {
+        let mut entry = ClipboardEntry::new(content.into(), classifier::classify(content).content_type, "Code".into());
+        entry.timestamp = timestamp;
+        entry
+    }
+
+    fn sample() -> Vec<ClipboardEntry> {
+        let mut pinned = entry("https://example.com/a", 1_705_314_600_123);
+        pinned.pinned = true;
+        pinned.copy_count = 3;
+        pinned.tags = vec!["c++".into(), "links".into(), "to-do".into(), "été".into()];
+        vec![
+            pinned,
+            entry("=HYPERLINK(\"http://evil.example\", \"x\")", 1_705_314_500_000),
+            entry("'=already quoted", 1_705_314_400_000),
+            entry("-5", 1_705_314_300_000),
+            entry("@SUM(1+1)", 1_705_314_200_000),
+            entry("'just a quote", 1_705_314_100_000),
+            entry("multi\nline, with \"quotes\"", 1_705_314_000_000),
+        ]
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let entries = sample();
+        let json = export(&entries, ExportFormat::Json, &ExportFilter::default()).unwrap();
+        let parsed = parse(&json, ExportFormat::Json, &[], &[]).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(&entries).unwrap());
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let entries = sample();
+        let csv = export(&entries, ExportFormat::Csv, &ExportFilter::default()).unwrap();
+        let parsed = parse(&csv, ExportFormat::Csv, &[], &[]).unwrap();
+        assert_eq!(parsed.len(), entries.len());
+        for (parsed, entry) in parsed.iter().zip(&entries) {
+            assert_eq!(parsed.id, entry.id);
+            assert_eq!(parsed.timestamp, entry.timestamp);
+            assert_eq!(parsed.content, entry.content);
+            assert_eq!(parsed.content_type, entry.content_type);
+            assert_eq!(parsed.content_hash, entry.content_hash);
+            assert_eq!(parsed.source_app, entry.source_app);
+            assert_eq!(parsed.pinned, entry.pinned);
+            assert_eq!(parsed.copy_count, entry.copy_count);
+            assert_eq!(parsed.tags, entry.tags);
+        }
+
+        // Tags can't hold the separator; a bad tag only drops itself
+        assert!(normalize_tags(&["two words".into()]).is_err());
+        let long_tag = "x".repeat(40);
+        let csv = format!("id,tags,content\n1,\"#Work  {} Links\",hello\n", long_tag);
+        let parsed = parse(&csv, ExportFormat::Csv, &[], &[]).unwrap();
+        assert_eq!(parsed[0].tags, vec!["links".to_string(), "work".to_string()]);
+    }
+
+    #[test]
+    fn export_paths_must_be_absolute_with_the_format_extension() {
+        let dir = std::env::temp_dir();
+        assert!(ExportFormat::Csv.check_path(&dir.join("history.csv")).is_ok());
+        assert!(ExportFormat::Markdown.check_path(&dir.join("History.MD")).is_ok());
+        assert!(ExportFormat::Csv.check_path(Path::new("history.csv")).is_err());
+        assert!(ExportFormat::Json.check_path(&dir.join("clipboard_history.db")).is_err());
+        assert!(ExportFormat::Json.check_path(&dir.join("history")).is_err());
+    }
+
+    #[test]
+    fn csv_cells_never_start_a_formula() {
+        let csv = export(&sample(), ExportFormat::Csv, &ExportFilter::default()).unwrap();
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        for record in reader.records() {
+            for cell in record.unwrap().iter() {
+                assert!(!cell.starts_with(CSV_FORMULA_CHARS), "{:?}", cell);
+            }
+        }
+        assert!(csv.contains("\"'=HYPERLINK("));
+        assert!(csv.contains(",''=already quoted"));
+        assert!(csv.contains(",'just a quote"));
+    }
+
+    #[test]
+    fn merge_folds_duplicates_into_existing_entries() {
+        let mut existing = entry("hello", 1_000);
+        existing.tags = vec!["a".into()];
+        let mut entries = vec![entry("other", 2_000), existing.clone()];
+
+        let mut newer_copy = entry("hello", 3_000);
+        newer_copy.copy_count = 2;
+        newer_copy.pinned = true;
+        newer_copy.source_app = "Browser".into();
+        newer_copy.tags = vec!["a".into(), "b".into()];
+        // Same id as an existing entry, different content
+        let mut clashing = entry("new", 500);
+        clashing.id = existing.id.clone();
+
+        let (changed, summary) = merge(&mut entries, vec![newer_copy, clashing]);
+        assert_eq!((summary.added, summary.merged), (1, 1));
+        assert_eq!(entries.len(), 3);
+
+        let merged = entries.iter().find(|e| e.content == "hello").unwrap();
+        assert_eq!(merged.id, existing.id);
+        assert_eq!(merged.copy_count, 3);
+        assert!(merged.pinned);
+        assert_eq!(merged.timestamp, 3_000);
+        assert_eq!(merged.source_app, "Browser");
+        assert_eq!(merged.tags, vec!["a".to_string(), "b".to_string()]);
+
+        let added = entries.iter().find(|e| e.content == "new").unwrap();
+        assert_ne!(added.id, existing.id);
+        // Newest first
+        let timestamps: Vec<i64> = entries.iter().map(|e| e.timestamp).collect();
+        assert_eq!(timestamps, vec![3_000, 2_000, 500]);
+
+        let mut changed_ids: Vec<&str> = changed.iter().map(|e| e.id.as_str()).collect();
+        changed_ids.sort();
+        let mut expected = vec![merged.id.as_str(), added.id.as_str()];
+        expected.sort();
+        assert_eq!(changed_ids, expected);
+
+        // Importing the same entries again only merges
+        let (_, summary) = merge(&mut entries, vec![entry("hello", 10), entry("new", 10)]);
+        assert_eq!((summary.added, summary.merged), (0, 2));
+        assert_eq!(entries.len(), 3);
+    }
+}
@@ -0,0 +1,263 @@
+// ---------------------------------------------------------------------------
+// Windows: "HTML Format" (CF_HTML) and CF_HDROP
+// ---------------------------------------------------------------------------
+
+/// Extract the copied fragment from Windows "HTML Format" data. The header's
+/// offsets are byte offsets into the UTF-8 payload.
+#[cfg(target_os = "windows")]
+pub fn cf_html_to_fragment(data: &[u8]) -> Option<String> {
+    let header = String::from_utf8_lossy(&data[..data.len().min(512)]);
+    let offset = |key: &str| {
+        header
+            .lines()
+            .take_while(|line| !line.starts_with('<'))
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.trim().parse::<usize>().ok())
+    };
+
+    let (start, end) = match (offset("StartFragment:"), offset("EndFragment:")) {
+        (Some(start), Some(end)) => (start, end),
+        _ => (offset("StartHTML:")?, offset("EndHTML:")?),
+    };
+    let fragment = data.get(start..end)?;
+    Some(String::from_utf8_lossy(fragment).into_owned())
+}
+
+/// Wrap an HTML fragment in a Windows "HTML Format" document.
+#[cfg(target_os = "windows")]
+pub fn fragment_to_cf_html(fragment: &str) -> Vec<u8> {
+    const PREFIX: &str = "<html><body>\r\n<!--StartFragment-->";
+    const SUFFIX: &str = "<!--EndFragment-->\r\n</body></html>";
+
+    let header = |start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize| {
+        format!(
+            "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n",
+            start_html, end_html, start_fragment, end_fragment
+        )
+    };
+    // Every offset is zero-padded to ten digits, so the header length is fixed.
+    let start_html = header(0, 0, 0, 0).len();
+    let start_fragment = start_html + PREFIX.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + SUFFIX.len();
+
+    let mut data = header(start_html, end_html, start_fragment, end_fragment).into_bytes();
+    data.extend_from_slice(PREFIX.as_bytes());
+    data.extend_from_slice(fragment.as_bytes());
+    data.extend_from_slice(SUFFIX.as_bytes());
+    data.push(0);
+    data
+}
+
+/// Size of the DROPFILES header that starts `CF_HDROP` data.
+#[cfg(target_os = "windows")]
+const DROPFILES_SIZE: usize = 20;
+
+/// Read the file list from `CF_HDROP` data: a DROPFILES header followed by a
+/// double-NUL-terminated list of paths.
+#[cfg(target_os = "windows")]
+pub fn hdrop_to_paths(data: &[u8]) -> Vec<String> {
+    let u32_at = |at: usize| data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    let (Some(offset), Some(wide)) = (u32_at(0), u32_at(16)) else {
+        return Vec::new();
+    };
+    let Some(list) = data.get(offset as usize..) else {
+        return Vec::new();
+    };
+
+    if wide != 0 {
+        let units: Vec<u16> = list
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        units
+            .split(|&unit| unit == 0)
+            .take_while(|path| !path.is_empty())
+            .map(String::from_utf16_lossy)
+            .collect()
+    } else {
+        list.split(|&byte| byte == 0)
+            .take_while(|path| !path.is_empty())
+            .map(|path| String::from_utf8_lossy(path).into_owned())
+            .collect()
+    }
+}
+
+/// Build `CF_HDROP` data listing `paths` as wide strings.
+#[cfg(target_os = "windows")]
+pub fn paths_to_hdrop(paths: &[String]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&(DROPFILES_SIZE as u32).to_le_bytes()); // pFiles
+    data.extend_from_slice(&[0u8; 12]); // pt, fNC
+    data.extend_from_slice(&1u32.to_le_bytes()); // fWide
+    for path in paths {
+        for unit in path.encode_utf16().chain(std::iter::once(0)) {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+    data.extend_from_slice(&[0, 0]);
+    data
+}
+
+// ---------------------------------------------------------------------------
+// Linux: text/uri-list
+// ---------------------------------------------------------------------------
+
+/// Local paths of the `file://` URIs in a `text/uri-list` (or
+/// `x-special/gnome-copied-files`) payload. Other lines are ignored.
+#[cfg(target_os = "linux")]
+pub fn uri_list_to_paths(list: &str) -> Vec<String> {
+    list.lines()
+        .filter_map(|line| line.trim().strip_prefix("file://"))
+        // Skip the (usually empty or "localhost") host part.
+        .filter_map(|rest| rest.find('/').map(|slash| &rest[slash..]))
+        .map(percent_decode)
+        .collect()
+}
+
+/// Build a `text/uri-list` payload for local paths.
+#[cfg(target_os = "linux")]
+pub fn paths_to_uri_list(paths: &[String]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("file://{}\r\n", percent_encode(path, b"/")))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match percent_escape(&bytes[i..]) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// ---------------------------------------------------------------------------
+// Percent-encoding, shared with the URL transforms
+// ---------------------------------------------------------------------------
+
+/// Percent-encode everything except RFC 3986 unreserved characters and the
+/// bytes in `keep`.
+pub(super) fn percent_encode(text: &str, keep: &[u8]) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(byte as char),
+            _ if keep.contains(&byte) => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+/// The byte encoded by the `%XX` escape `bytes` starts with, if it does
+/// start with `%` and two ASCII hex digits.
+pub(super) fn percent_escape(bytes: &[u8]) -> Option<u8> {
+    match bytes {
+        [b'%', high, low, ..] if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+            let digit = |byte: u8| (byte as char).to_digit(16).unwrap_or(0) as u8;
+            Some(digit(*high) << 4 | digit(*low))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn cf_html_round_trip() {
+        let fragment = "<b>Grüße</b> & <i>more</i>";
+        let data = fragment_to_cf_html(fragment);
+        assert_eq!(data.last(), Some(&0));
+        assert_eq!(cf_html_to_fragment(&data).as_deref(), Some(fragment));
+        assert_eq!(cf_html_to_fragment(&fragment_to_cf_html("")).as_deref(), Some(""));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn cf_html_from_other_producers() {
+        // Without fragment offsets the whole document is taken
+        let html = "<html><body>hi</body></html>";
+        let header = |start: usize, end: usize| {
+            format!("Version:0.9\r\nStartHTML:{:08}\r\nEndHTML:{:08}\r\n", start, end)
+        };
+        let start = header(0, 0).len();
+        let data = format!("{}{}", header(start, start + html.len()), html);
+        assert_eq!(cf_html_to_fragment(data.as_bytes()).as_deref(), Some(html));
+
+        assert_eq!(cf_html_to_fragment(b"Version:0.9\r\n"), None);
+        // Offsets past the end of the data
+        assert_eq!(cf_html_to_fragment(b"StartFragment:10\r\nEndFragment:99\r\n"), None);
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn hdrop_round_trip() {
+        let paths = vec![r"C:\Users\alice\report.docx".to_string(), r"D:\Fotos\straße.png".to_string()];
+        assert_eq!(hdrop_to_paths(&paths_to_hdrop(&paths)), paths);
+        assert!(hdrop_to_paths(&paths_to_hdrop(&[])).is_empty());
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn hdrop_ansi_and_truncated() {
+        let mut data = vec![0u8; DROPFILES_SIZE];
+        data[0] = DROPFILES_SIZE as u8;
+        data.extend_from_slice(b"C:\\a.txt\0C:\\b.txt\0\0");
+        assert_eq!(hdrop_to_paths(&data), vec![r"C:\a.txt".to_string(), r"C:\b.txt".to_string()]);
+
+        assert!(hdrop_to_paths(&data[..10]).is_empty());
+        data[0] = 200;
+        assert!(hdrop_to_paths(&data).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn uri_list_round_trip() {
+        let paths = vec![
+            "/home/alice/report.docx".to_string(),
+            "/tmp/with space/100%/straße #1.png".to_string(),
+        ];
+        let list = paths_to_uri_list(&paths);
+        assert_eq!(list.lines().next(), Some("file:///home/alice/report.docx"));
+        assert!(!list.contains(' '));
+        assert_eq!(uri_list_to_paths(&list), paths);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn uri_list_from_other_producers() {
+        let list = "# comment\r\nfile://localhost/etc/hosts\r\nhttps://example.com/\r\ncopy\nfile:///a%20b\n";
+        assert_eq!(uri_list_to_paths(list), vec!["/etc/hosts".to_string(), "/a b".to_string()]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn percent_encoding() {
+        assert_eq!(percent_encode("/a b/ü~_.-", b"/"), "/a%20b/%C3%BC~_.-");
+        assert_eq!(percent_decode("/a%20b/%C3%BC"), "/a b/ü");
+        // Lowercase hex, and `%` not followed by two hex digits
+        assert_eq!(percent_decode("%c3%bc"), "ü");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%+F%-1"), "%+F%-1");
+        for text in ["", "/", "/ä/ö/ü", "/%25/%", "/tab\there"] {
+            assert_eq!(percent_decode(&percent_encode(text, b"/")), text);
+        }
+    }
+}
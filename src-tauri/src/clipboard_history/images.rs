@@ -0,0 +1,332 @@
+use super::crypto::{self, Cipher};
+use super::entry::{ClipboardEntry, ImageInfo};
+use image::{DynamicImage, ImageFormat};
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const DIR_NAME: &str = "clipboard_images";
+/// Longest edge of generated thumbnails, in pixels.
+const THUMBNAIL_SIZE: u32 = 256;
+/// How long an unreferenced image file is left alone: the monitor writes
+/// the files before it adds the entry.
+const ORPHAN_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+/// Directory holding image entry files, next to `clipboard_history.json`.
+pub fn images_dir(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(DIR_NAME)
+}
+
+/// Write a PNG clipboard image and its thumbnail to the images directory,
+/// encrypted when history encryption is on.
+pub fn save(app_data_dir: &Path, png: &[u8]) -> Result<ImageInfo, String> {
+    let img = image::load_from_memory_with_format(png, ImageFormat::Png).map_err(|e| e.to_string())?;
+
+    let dir = images_dir(app_data_dir);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let name = uuid::Uuid::new_v4().to_string();
+    let path = dir.join(format!("{}.png", name));
+    let thumbnail_path = dir.join(format!("{}.thumb.png", name));
+
+    let thumbnail = encode_png(&img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE))
+        .ok_or("Failed to encode thumbnail")?;
+    std::fs::write(&path, crypto::encrypt_bytes(png)?).map_err(|e| e.to_string())?;
+    std::fs::write(&thumbnail_path, crypto::encrypt_bytes(&thumbnail)?).map_err(|e| e.to_string())?;
+
+    Ok(ImageInfo {
+        path: path.to_string_lossy().into_owned(),
+        thumbnail_path: thumbnail_path.to_string_lossy().into_owned(),
+        width: img.width(),
+        height: img.height(),
+        byte_size: png.len(),
+        encrypted: crypto::is_enabled(),
+    })
+}
+
+/// Read an image file written by `save`, decrypting it if needed. Only files
+/// in the images directory can be read.
+pub fn read(app_data_dir: &Path, path: &str) -> Result<Vec<u8>, String> {
+    let path = Path::new(path);
+    if path.parent() != Some(images_dir(app_data_dir).as_path()) {
+        return Err("Not a clipboard image".into());
+    }
+    crypto::decrypt_bytes(std::fs::read(path).map_err(|e| e.to_string())?)
+}
+
+/// Temporary file holding the re-encrypted contents of an image file.
+fn staged_path(path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.rekey", path))
+}
+
+/// Write every image file of `entries` re-encrypted from `old` to `new` next
+/// to the original. Returns the original paths; nothing is replaced until
+/// `commit_staged`.
+pub(super) fn stage_reencrypt(
+    entries: &[ClipboardEntry],
+    old: Option<&Cipher>,
+    new: Option<&Cipher>,
+) -> Result<Vec<String>, String> {
+    let paths: Vec<String> = entries
+        .iter()
+        .filter_map(|e| e.image.as_ref())
+        .flat_map(|image| [image.path.clone(), image.thumbnail_path.clone()])
+        .collect();
+    for (i, path) in paths.iter().enumerate() {
+        let result = std::fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| crypto::decrypt_bytes_with(old, data))
+            .and_then(|data| crypto::encrypt_bytes_with(new, &data))
+            .and_then(|data| std::fs::write(staged_path(path), data).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            discard_staged(&paths[..i]);
+            return Err(format!("Failed to re-encrypt {}: {}", path, e));
+        }
+    }
+    Ok(paths)
+}
+
+/// The previous contents of an image file, kept while a key change is
+/// committed.
+fn backup_path(path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.old", path))
+}
+
+/// Replace the files at `paths` with their staged versions, keeping the
+/// previous files until `remove_backups` or `restore_backups`. If a file
+/// can't be replaced, the ones already replaced are restored, the staged
+/// files are removed and an error is returned.
+pub(super) fn commit_staged(paths: &[String]) -> Result<(), String> {
+    for (i, path) in paths.iter().enumerate() {
+        let result = std::fs::rename(path, backup_path(path)).and_then(|_| std::fs::rename(staged_path(path), path));
+        if let Err(e) = result {
+            restore_backups(&paths[..=i]);
+            discard_staged(&paths[i..]);
+            return Err(format!("Failed to replace {}: {}", path, e));
+        }
+    }
+    Ok(())
+}
+
+/// Put back the files replaced by `commit_staged`.
+pub(super) fn restore_backups(paths: &[String]) {
+    for path in paths {
+        let backup = backup_path(path);
+        if backup.exists() {
+            if let Err(e) = std::fs::rename(&backup, path) {
+                log::error!("Failed to restore {}: {}", path, e);
+            }
+        }
+    }
+}
+
+pub(super) fn remove_backups(paths: &[String]) {
+    for path in paths {
+        let _ = std::fs::remove_file(backup_path(path));
+    }
+}
+
+pub(super) fn discard_staged(paths: &[String]) {
+    for path in paths {
+        let _ = std::fs::remove_file(staged_path(path));
+    }
+}
+
+/// Clean up after a key change that was interrupted: once it was committed
+/// only the backups are left to remove, otherwise the backups go back in
+/// place and the staged files are dropped.
+pub(super) fn finish_key_change(app_data_dir: &Path, committed: bool) {
+    let Ok(files) = std::fs::read_dir(images_dir(app_data_dir)) else {
+        return;
+    };
+    for file in files.flatten() {
+        let path = file.path();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("old") if !committed => {
+                if let Err(e) = std::fs::rename(&path, path.with_extension("")) {
+                    log::error!("Failed to restore {}: {}", path.display(), e);
+                }
+            }
+            Some("old" | "rekey") => {
+                let _ = std::fs::remove_file(&path);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Delete the files backing an image entry.
+pub fn delete_files(image: &ImageInfo) {
+    let _ = std::fs::remove_file(&image.path);
+    let _ = std::fs::remove_file(&image.thumbnail_path);
+}
+
+/// Delete image files that no entry references any more (e.g. entries dropped
+/// by the max entry limit). Files written in the last `ORPHAN_GRACE_PERIOD`
+/// may belong to a copy that isn't in the history yet, and key change
+/// leftovers are handled by `crypto`, so both are kept.
+pub fn remove_orphans<'a>(app_data_dir: &Path, referenced: impl IntoIterator<Item = &'a ImageInfo>) {
+    let referenced: HashSet<PathBuf> = referenced
+        .into_iter()
+        .flat_map(|image| [PathBuf::from(&image.path), PathBuf::from(&image.thumbnail_path)])
+        .collect();
+
+    let Ok(files) = std::fs::read_dir(images_dir(app_data_dir)) else {
+        return;
+    };
+    for file in files.flatten() {
+        let path = file.path();
+        let recent = file
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map_or(true, |modified| modified.elapsed().map_or(true, |age| age < ORPHAN_GRACE_PERIOD));
+        let key_change = matches!(path.extension().and_then(|e| e.to_str()), Some("rekey" | "old"));
+        if !referenced.contains(&path) && !recent && !key_change {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
+
+fn encode_png(img: &DynamicImage) -> Option<Vec<u8>> {
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).ok()?;
+    Some(png)
+}
+
+#[cfg(target_os = "windows")]
+const BI_RGB: u32 = 0;
+#[cfg(target_os = "windows")]
+const BI_BITFIELDS: u32 = 3;
+/// Largest DIB width or height accepted from the clipboard, in pixels.
+#[cfg(target_os = "windows")]
+const MAX_DIB_EDGE: usize = 32_768;
+
+/// Convert a packed DIB (`CF_DIB`: BITMAPINFOHEADER followed by pixels) to PNG.
+/// Handles uncompressed 24- and 32-bit bitmaps, which is what screenshots and
+/// browsers put on the clipboard.
+#[cfg(target_os = "windows")]
+pub fn dib_to_png(dib: &[u8]) -> Option<Vec<u8>> {
+    let u32_at = |at: usize| Some(u32::from_le_bytes(dib.get(at..at + 4)?.try_into().ok()?));
+    let u16_at = |at: usize| Some(u16::from_le_bytes(dib.get(at..at + 2)?.try_into().ok()?));
+
+    let header_size = u32_at(0)? as usize;
+    let width = u32_at(4)? as i32;
+    let height = u32_at(8)? as i32;
+    let bit_count = u16_at(14)?;
+    let compression = u32_at(16)?;
+    let colors_used = u32_at(32)? as usize;
+
+    if width <= 0 || height == 0 || !matches!(bit_count, 24 | 32) {
+        return None;
+    }
+    if compression != BI_RGB && compression != BI_BITFIELDS {
+        return None;
+    }
+    let width = width as usize;
+    let rows = height.unsigned_abs() as usize;
+    if width > MAX_DIB_EDGE || rows > MAX_DIB_EDGE {
+        return None;
+    }
+
+    // Every size comes from the clipboard owner, so none of this may overflow
+    let masks = if compression == BI_BITFIELDS && header_size == 40 { 12 } else { 0 };
+    let pixels_at = header_size.checked_add(masks)?.checked_add(colors_used.checked_mul(4)?)?;
+    let bytes_per_pixel = bit_count as usize / 8;
+    let stride = width.checked_mul(bit_count as usize)?.div_ceil(32).checked_mul(4)?;
+    let pixels = dib.get(pixels_at..pixels_at.checked_add(stride.checked_mul(rows)?)?)?;
+
+    let mut rgba = Vec::with_capacity(width * rows * 4);
+    for y in 0..rows {
+        // Positive heights are stored bottom-up.
+        let row = if height > 0 { rows - 1 - y } else { y };
+        let line = &pixels[row * stride..row * stride + width * bytes_per_pixel];
+        for px in line.chunks_exact(bytes_per_pixel) {
+            let alpha = if bytes_per_pixel == 4 { px[3] } else { 255 };
+            rgba.extend_from_slice(&[px[2], px[1], px[0], alpha]);
+        }
+    }
+    // Most producers leave the alpha byte of 32-bit DIBs zeroed.
+    if bytes_per_pixel == 4 && rgba.chunks_exact(4).all(|px| px[3] == 0) {
+        rgba.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+    }
+
+    let img = image::RgbaImage::from_raw(width as u32, rows as u32, rgba)?;
+    encode_png(&DynamicImage::ImageRgba8(img))
+}
+
+/// Convert PNG data to a packed 32-bit bottom-up DIB for `CF_DIB`.
+#[cfg(target_os = "windows")]
+pub fn png_to_dib(png: &[u8]) -> Option<Vec<u8>> {
+    let img = image::load_from_memory_with_format(png, ImageFormat::Png).ok()?.to_rgba8();
+    let (width, height) = img.dimensions();
+
+    let mut dib = Vec::with_capacity(40 + img.as_raw().len());
+    dib.extend_from_slice(&40u32.to_le_bytes());
+    dib.extend_from_slice(&(width as i32).to_le_bytes());
+    dib.extend_from_slice(&(height as i32).to_le_bytes());
+    dib.extend_from_slice(&1u16.to_le_bytes());
+    dib.extend_from_slice(&32u16.to_le_bytes());
+    dib.extend_from_slice(&BI_RGB.to_le_bytes());
+    dib.extend_from_slice(&(width * height * 4).to_le_bytes());
+    dib.extend_from_slice(&[0u8; 16]);
+
+    for row in img.rows().rev() {
+        for px in row {
+            dib.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+        }
+    }
+    Some(dib)
+}
+
+#[cfg(all(test, target_os = "windows"))]
+mod tests {
+    use super::*;
+
+    /// A BITMAPINFOHEADER for an uncompressed 32-bit bitmap.
+    fn dib_header(width: i32, height: i32, colors_used: u32) -> Vec<u8> {
+        let mut dib = Vec::new();
+        dib.extend_from_slice(&40u32.to_le_bytes());
+        dib.extend_from_slice(&width.to_le_bytes());
+        dib.extend_from_slice(&height.to_le_bytes());
+        dib.extend_from_slice(&1u16.to_le_bytes());
+        dib.extend_from_slice(&32u16.to_le_bytes());
+        dib.extend_from_slice(&BI_RGB.to_le_bytes());
+        dib.extend_from_slice(&[0u8; 12]);
+        dib.extend_from_slice(&colors_used.to_le_bytes());
+        dib.extend_from_slice(&[0u8; 4]);
+        dib
+    }
+
+    #[test]
+    fn dib_round_trip() {
+        // One bottom-up row: a red and a blue pixel, stored as BGRA
+        let mut dib = dib_header(2, 1, 0);
+        dib.extend_from_slice(&[0, 0, 255, 255, 255, 0, 0, 255]);
+        let png = dib_to_png(&dib).unwrap();
+        let img = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(img.as_raw(), &[255, 0, 0, 255, 0, 0, 255, 255]);
+        assert_eq!(dib_to_png(&png_to_dib(&png).unwrap()).unwrap(), png);
+    }
+
+    #[test]
+    fn malformed_dibs_are_rejected() {
+        // Shorter than the header says
+        assert_eq!(dib_to_png(&dib_header(2, 2, 0)), None);
+        assert_eq!(dib_to_png(&dib_header(2, 2, 0)[..20]), None);
+        // Sizes that would overflow the offset and stride arithmetic
+        let mut dib = dib_header(2, 1, u32::MAX);
+        dib.extend_from_slice(&[0; 8]);
+        assert_eq!(dib_to_png(&dib), None);
+        let mut dib = dib_header(i32::MAX, i32::MIN, 0);
+        dib.extend_from_slice(&[0; 64]);
+        assert_eq!(dib_to_png(&dib), None);
+        // Over the size cap, even with enough data
+        let mut dib = dib_header(40_000, 1, 0);
+        dib.extend(std::iter::repeat(0).take(40_000 * 4));
+        assert_eq!(dib_to_png(&dib), None);
+        let mut dib = dib_header(-1, 1, 0);
+        dib.extend_from_slice(&[0; 4]);
+        assert_eq!(dib_to_png(&dib), None);
+    }
+}
@@ -0,0 +1,239 @@
+mod wayland;
+mod x11;
+
+use super::entry::ClipboardFormats;
+use super::formats;
+
+/// The selection buffers shared by X11 and Wayland clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// The explicit copy/paste clipboard (Ctrl+C / Ctrl+V).
+    Clipboard,
+    /// The "select to copy, middle-click to paste" buffer.
+    Primary,
+}
+
+/// Text MIME types (and legacy X11 target names), in order of preference.
+const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+    "TEXT",
+];
+
+const PNG_MIME_TYPE: &str = "image/png";
+const HTML_MIME_TYPE: &str = "text/html";
+const RTF_MIME_TYPES: &[&str] = &["text/rtf", "application/rtf", "text/richtext"];
+const URI_LIST_MIME_TYPE: &str = "text/uri-list";
+/// GNOME file managers only paste files offered under this type.
+const GNOME_FILES_MIME_TYPE: &str = "x-special/gnome-copied-files";
+/// Offered with the value `secret` by password managers (KeePassXC and
+/// others) that don't want clipboard managers to record the copy.
+const PASSWORD_MANAGER_HINT_MIME_TYPE: &str = "x-kde-passwordManagerHint";
+
+/// Whether to talk to the compositor through wlr-data-control rather than X11.
+fn use_wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some() && wayland::is_available()
+}
+
+/// Read a selection once per format, each as the first of its MIME types
+/// the owner can provide.
+fn read(selection: Selection, formats: &[&[&str]]) -> Vec<Option<Vec<u8>>> {
+    let result = if use_wayland() {
+        wayland::read(selection, formats)
+    } else {
+        x11::read(selection, formats)
+    };
+    result.unwrap_or_else(|e| {
+        log::warn!("Failed to read {:?} selection: {}", selection, e);
+        vec![None; formats.len()]
+    })
+}
+
+fn read_one(selection: Selection, mime_types: &[&str]) -> Option<Vec<u8>> {
+    read(selection, &[mime_types]).pop().flatten()
+}
+
+/// Take ownership of a selection and serve `contents` (MIME type and bytes)
+/// to other clients until someone else replaces it.
+fn write(selection: Selection, contents: Vec<(String, Vec<u8>)>) {
+    let result = if use_wayland() {
+        wayland::write(selection, contents)
+    } else {
+        x11::write(selection, contents)
+    };
+    if let Err(e) = result {
+        log::error!("Failed to set {:?} selection: {}", selection, e);
+    }
+}
+
+/// Read the text content of a selection.
+pub fn read_text(selection: Selection) -> Option<String> {
+    read_one(selection, TEXT_MIME_TYPES).map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Replace a selection with `text`, offered under every text type.
+pub fn write_text(selection: Selection, text: &str) {
+    write(selection, text_contents(text));
+}
+
+/// `text` under each text type in its encoding. `STRING` is Latin-1, so it
+/// is only offered when the text fits; `TEXT` leaves the encoding to the
+/// owner and isn't offered, clients that can't take UTF-8 use `STRING`.
+fn text_contents(text: &str) -> Vec<(String, Vec<u8>)> {
+    let latin1: Option<Vec<u8>> = text.chars().map(|c| u8::try_from(c).ok()).collect();
+    TEXT_MIME_TYPES
+        .iter()
+        .filter_map(|&mime_type| {
+            let bytes = match mime_type {
+                "STRING" => latin1.clone()?,
+                "TEXT" => return None,
+                _ => text.as_bytes().to_vec(),
+            };
+            Some((mime_type.to_string(), bytes))
+        })
+        .collect()
+}
+
+/// Read the HTML, RTF and file list representations of a selection.
+pub fn read_formats(selection: Selection) -> ClipboardFormats {
+    let mut results = read(
+        selection,
+        &[&[HTML_MIME_TYPE], RTF_MIME_TYPES, &[URI_LIST_MIME_TYPE, GNOME_FILES_MIME_TYPE]],
+    )
+    .into_iter();
+    let mut next = || results.next().flatten();
+
+    ClipboardFormats {
+        html: next().map(|bytes| decode_html(&bytes)),
+        rtf: next().map(|bytes| String::from_utf8_lossy(&bytes).into_owned()),
+        files: next()
+            .map(|bytes| formats::uri_list_to_paths(&String::from_utf8_lossy(&bytes)))
+            .unwrap_or_default(),
+    }
+}
+
+/// Replace a selection with `text` plus every representation in `formats`.
+pub fn write_contents(selection: Selection, text: &str, formats: &ClipboardFormats) {
+    let mut contents = if text.is_empty() {
+        Vec::new()
+    } else {
+        text_contents(text)
+    };
+    if let Some(html) = &formats.html {
+        contents.push((HTML_MIME_TYPE.to_string(), html.as_bytes().to_vec()));
+    }
+    if let Some(rtf) = &formats.rtf {
+        for mime_type in RTF_MIME_TYPES {
+            contents.push((mime_type.to_string(), rtf.as_bytes().to_vec()));
+        }
+    }
+    if !formats.files.is_empty() {
+        let uri_list = formats::paths_to_uri_list(&formats.files);
+        let gnome_files = format!("copy\n{}", uri_list.replace("\r\n", "\n").trim_end());
+        contents.push((URI_LIST_MIME_TYPE.to_string(), uri_list.into_bytes()));
+        contents.push((GNOME_FILES_MIME_TYPE.to_string(), gnome_files.into_bytes()));
+    }
+    write(selection, contents);
+}
+
+/// Some applications (notably Firefox) serve `text/html` as UTF-16 with a BOM.
+fn decode_html(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Whether the selection owner asked clipboard managers not to record it.
+pub fn is_marked_secret(selection: Selection) -> bool {
+    read_one(selection, &[PASSWORD_MANAGER_HINT_MIME_TYPE])
+        .is_some_and(|hint| hint.trim_ascii() == b"secret")
+}
+
+/// Read a selection as PNG-encoded image data.
+pub fn read_image(selection: Selection) -> Option<Vec<u8>> {
+    read_one(selection, &[PNG_MIME_TYPE])
+}
+
+/// Replace a selection with a PNG image.
+pub fn write_image(selection: Selection, png: &[u8]) {
+    write(selection, vec![(PNG_MIME_TYPE.to_string(), png.to_vec())]);
+}
+
+/// The focused top-level window, unless it belongs to this process. Only X11
+/// (including XWayland) exposes this.
+pub fn active_window() -> Option<u32> {
+    x11::active_window().unwrap_or_else(|e| {
+        log::debug!("Failed to get the active window: {}", e);
+        None
+    })
+}
+
+pub use x11::WindowInfo;
+
+/// The focused top-level window, including this app's own. X11 only.
+pub fn active_window_info() -> Option<WindowInfo> {
+    x11::active_window_info().unwrap_or_else(|e| {
+        log::debug!("Failed to get the active window: {}", e);
+        None
+    })
+}
+
+/// The window's icon closest to `size` pixels, as width, height and ARGB
+/// pixels.
+pub fn window_icon(window: u32, size: u32) -> Option<(u32, u32, Vec<u32>)> {
+    x11::window_icon(window, size).unwrap_or_else(|e| {
+        log::debug!("Failed to get the icon of window {:#x}: {}", window, e);
+        None
+    })
+}
+
+/// Ask the window manager to focus `window`.
+pub fn activate_window(window: u32) -> bool {
+    x11::activate_window(window)
+        .map_err(|e| log::warn!("Failed to activate window {:#x}: {}", window, e))
+        .is_ok()
+}
+
+/// Watch the CLIPBOARD selection and call `on_change` every time another
+/// client takes ownership of it. Blocks for as long as the watcher runs.
+///
+/// Uses the wlr-data-control protocol when running under a Wayland compositor
+/// that supports it, and falls back to X11 XFixes selection notifications
+/// (which also covers XWayland sessions).
+pub fn watch_clipboard<F: FnMut()>(mut on_change: F) -> Result<(), String> {
+    if use_wayland() {
+        match wayland::watch_clipboard(&mut on_change) {
+            Ok(()) => return Ok(()),
+            Err(e) => log::warn!("Wayland clipboard watcher stopped ({}), falling back to X11", e),
+        }
+    }
+    x11::watch_clipboard(&mut on_change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_target_is_latin1() {
+        let contents = text_contents("café");
+        let bytes = |mime_type: &str| contents.iter().find(|(m, _)| m == mime_type).map(|(_, b)| b.clone());
+        assert_eq!(bytes("UTF8_STRING"), Some("café".as_bytes().to_vec()));
+        assert_eq!(bytes("STRING"), Some(b"caf\xe9".to_vec()));
+        assert_eq!(bytes("TEXT"), None);
+
+        // Not representable in Latin-1: only the UTF-8 types
+        let contents = text_contents("€5");
+        assert!(contents.iter().all(|(mime_type, _)| mime_type != "STRING"));
+        assert_eq!(contents.len(), 3);
+    }
+}
@@ -0,0 +1,350 @@
+use super::Selection;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::fd::AsFd;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
+use wayland_client::backend::ObjectId;
+use wayland_client::protocol::{wl_registry, wl_seat::WlSeat};
+use wayland_client::{
+    delegate_noop, event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_device_v1::{
+    self, ZwlrDataControlDeviceV1,
+};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_offer_v1::{
+    self, ZwlrDataControlOfferV1,
+};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_source_v1::{
+    self, ZwlrDataControlSourceV1,
+};
+
+/// Contents a data source serves: each MIME type it offers and its bytes.
+type SourceContents = Vec<(String, Arc<[u8]>)>;
+
+/// How long to wait for the selection source to write its data.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct State {
+    seat: Option<WlSeat>,
+    manager: Option<ZwlrDataControlManagerV1>,
+    /// MIME types advertised by each live offer.
+    offers: HashMap<ObjectId, Vec<String>>,
+    /// The current CLIPBOARD selection offer, if any.
+    selection: Option<ZwlrDataControlOfferV1>,
+    /// The current PRIMARY selection offer, if any.
+    primary: Option<ZwlrDataControlOfferV1>,
+    selection_changed: bool,
+    finished: bool,
+}
+
+impl State {
+    /// Store `offer` as the current offer for `selection`, destroying the one
+    /// it replaces.
+    fn replace_offer(&mut self, selection: Selection, offer: Option<ZwlrDataControlOfferV1>) {
+        let slot = match selection {
+            Selection::Clipboard => &mut self.selection,
+            Selection::Primary => &mut self.primary,
+        };
+        if let Some(old) = std::mem::replace(slot, offer) {
+            self.offers.remove(&old.id());
+            old.destroy();
+        }
+        if selection == Selection::Clipboard {
+            self.selection_changed = true;
+        }
+    }
+
+    fn offer(&self, selection: Selection) -> Option<&ZwlrDataControlOfferV1> {
+        match selection {
+            Selection::Clipboard => self.selection.as_ref(),
+            Selection::Primary => self.primary.as_ref(),
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for State {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global { name, interface, version } = event {
+            if interface == WlSeat::interface().name && state.seat.is_none() {
+                state.seat = Some(registry.bind(name, version.min(2), qh, ()));
+            } else if interface == ZwlrDataControlManagerV1::interface().name {
+                state.manager = Some(registry.bind(name, version.min(2), qh, ()));
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::DataOffer { id } => {
+                state.offers.insert(id.id(), Vec::new());
+            }
+            zwlr_data_control_device_v1::Event::Selection { id } => {
+                state.replace_offer(Selection::Clipboard, id);
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id } => {
+                state.replace_offer(Selection::Primary, id);
+            }
+            zwlr_data_control_device_v1::Event::Finished => {
+                state.finished = true;
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        offer: &ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+            state.offers.entry(offer.id()).or_default().push(mime_type);
+        }
+    }
+}
+
+/// Sources we create carry the contents they serve as user data.
+impl Dispatch<ZwlrDataControlSourceV1, SourceContents> for State {
+    fn event(
+        _: &mut Self,
+        source: &ZwlrDataControlSourceV1,
+        event: zwlr_data_control_source_v1::Event,
+        contents: &SourceContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_source_v1::Event::Send { mime_type, fd } => {
+                let Some((_, bytes)) = contents.iter().find(|(m, _)| *m == mime_type) else {
+                    return;
+                };
+                // Write off the dispatch thread so a slow reader can't stall it.
+                let bytes = Arc::clone(bytes);
+                thread::spawn(move || {
+                    let _ = std::fs::File::from(fd).write_all(&bytes);
+                });
+            }
+            zwlr_data_control_source_v1::Event::Cancelled => source.destroy(),
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(State: ignore WlSeat);
+delegate_noop!(State: ZwlrDataControlManagerV1);
+
+/// A compositor connection with a data-control device bound to the first seat.
+struct Session {
+    conn: Connection,
+    queue: EventQueue<State>,
+    state: State,
+    manager: ZwlrDataControlManagerV1,
+    device: ZwlrDataControlDeviceV1,
+}
+
+/// Connect to the compositor and bind a data-control device for the first seat.
+/// The current selections are known once this returns.
+fn connect() -> Result<Session, String> {
+    let conn = Connection::connect_to_env().map_err(|e| e.to_string())?;
+    let mut queue = conn.new_event_queue();
+    let qh = queue.handle();
+    let _registry = conn.display().get_registry(&qh, ());
+
+    let mut state = State::default();
+    queue.roundtrip(&mut state).map_err(|e| e.to_string())?;
+
+    let (Some(seat), Some(manager)) = (state.seat.clone(), state.manager.clone()) else {
+        return Err("compositor does not support wlr-data-control".into());
+    };
+    let device = manager.get_data_device(&seat, &qh, ());
+    queue.roundtrip(&mut state).map_err(|e| e.to_string())?;
+
+    Ok(Session {
+        conn,
+        queue,
+        state,
+        manager,
+        device,
+    })
+}
+
+/// Whether the compositor supports wlr-data-control. Checked once per process.
+pub fn is_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| match connect() {
+        Ok(_) => true,
+        Err(e) => {
+            log::info!("wlr-data-control unavailable: {}", e);
+            false
+        }
+    })
+}
+
+/// Receive the content of `offer` as `mime_type`.
+fn receive(
+    conn: &Connection,
+    offer: &ZwlrDataControlOfferV1,
+    mime_type: &str,
+) -> Result<Vec<u8>, String> {
+    let (reader, writer) =
+        rustix::pipe::pipe_with(rustix::pipe::PipeFlags::CLOEXEC).map_err(|e| e.to_string())?;
+    offer.receive(mime_type.to_string(), writer.as_fd());
+    conn.flush().map_err(|e| e.to_string())?;
+    // Close our copy of the write end so EOF arrives once the source is done.
+    drop(writer);
+
+    let mut file = std::fs::File::from(reader);
+    let mut data = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let timeout = rustix::event::Timespec {
+        tv_sec: READ_TIMEOUT.as_secs() as _,
+        tv_nsec: 0,
+    };
+    loop {
+        let mut fds = [rustix::event::PollFd::new(&file, rustix::event::PollFlags::IN)];
+        let ready = rustix::event::poll(&mut fds, Some(&timeout)).map_err(|e| e.to_string())?;
+        if ready == 0 {
+            return Err("timed out reading selection".into());
+        }
+        match file.read(&mut chunk).map_err(|e| e.to_string())? {
+            0 => return Ok(data),
+            n => data.extend_from_slice(&chunk[..n]),
+        }
+    }
+}
+
+/// Read `selection` once per format, each as the first of its MIME types the
+/// source offers. A format that fails to transfer is logged and left out
+/// rather than failing the others.
+pub fn read(selection: Selection, formats: &[&[&str]]) -> Result<Vec<Option<Vec<u8>>>, String> {
+    let session = connect()?;
+    let Some(offer) = session.state.offer(selection) else {
+        return Ok(vec![None; formats.len()]);
+    };
+    let offered = session.state.offers.get(&offer.id()).cloned().unwrap_or_default();
+
+    let mut results = Vec::with_capacity(formats.len());
+    for mime_types in formats {
+        let mime_type = mime_types
+            .iter()
+            .find(|wanted| offered.iter().any(|m| m == *wanted));
+        results.push(mime_type.and_then(|mime_type| {
+            receive(&session.conn, offer, mime_type)
+                .map_err(|e| log::warn!("Failed to read {} from {:?} selection: {}", mime_type, selection, e))
+                .ok()
+        }));
+    }
+    Ok(results)
+}
+
+/// Keeps the connection that serves our data sources alive, with its event
+/// queue dispatched on a background thread.
+struct Owner {
+    conn: Connection,
+    qh: QueueHandle<State>,
+    manager: ZwlrDataControlManagerV1,
+    device: ZwlrDataControlDeviceV1,
+}
+
+impl Owner {
+    fn start() -> Result<Self, String> {
+        let Session {
+            conn,
+            mut queue,
+            mut state,
+            manager,
+            device,
+        } = connect()?;
+        let qh = queue.handle();
+        thread::spawn(move || loop {
+            if let Err(e) = queue.blocking_dispatch(&mut state) {
+                log::error!("Wayland selection owner stopped: {}", e);
+                return;
+            }
+        });
+        Ok(Self {
+            conn,
+            qh,
+            manager,
+            device,
+        })
+    }
+}
+
+static OWNER: OnceLock<Result<Owner, String>> = OnceLock::new();
+
+/// Offer `contents` (MIME type and bytes) as the new content of `selection`
+/// through a data-control source.
+pub fn write(selection: Selection, contents: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    let owner = OWNER.get_or_init(Owner::start).as_ref()?;
+    if selection == Selection::Primary && owner.device.version() < 2 {
+        return Err("compositor does not support the primary selection".into());
+    }
+
+    let contents: SourceContents = contents
+        .into_iter()
+        .map(|(mime_type, bytes)| (mime_type, Arc::from(bytes)))
+        .collect();
+    let mime_types: Vec<String> = contents.iter().map(|(m, _)| m.clone()).collect();
+    let source = owner.manager.create_data_source(&owner.qh, contents);
+    for mime_type in mime_types {
+        source.offer(mime_type);
+    }
+    match selection {
+        Selection::Clipboard => owner.device.set_selection(Some(&source)),
+        Selection::Primary => owner.device.set_primary_selection(Some(&source)),
+    }
+    owner.conn.flush().map_err(|e| e.to_string())
+}
+
+/// Listen for CLIPBOARD selection changes through wlr-data-control and call
+/// `on_change` for each one.
+pub fn watch_clipboard(on_change: &mut dyn FnMut()) -> Result<(), String> {
+    let Session {
+        mut queue,
+        mut state,
+        ..
+    } = connect()?;
+    // Only report changes made after startup, like the other backends.
+    state.selection_changed = false;
+
+    log::info!("Clipboard monitor started (Wayland)");
+
+    loop {
+        queue.blocking_dispatch(&mut state).map_err(|e| e.to_string())?;
+        if state.finished {
+            return Err("data-control device was destroyed".into());
+        }
+        if std::mem::take(&mut state.selection_changed) && state.selection.is_some() {
+            on_change();
+        }
+    }
+}
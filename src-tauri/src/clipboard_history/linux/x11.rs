@@ -0,0 +1,672 @@
+use super::Selection;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConnectionExt as _,
+    CreateWindowAux, EventMask, PropMode, Property, SelectionNotifyEvent, SelectionRequestEvent,
+    Window, WindowClass, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, CURRENT_TIME};
+
+/// How long to wait for the selection owner to answer a conversion request.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+/// Largest property we write at once when serving a selection; bigger
+/// contents are sent in chunks of this size with the INCR protocol.
+const MAX_PROPERTY_BYTES: usize = 256 * 1024;
+/// How long a requestor may take to ask for the next INCR chunk before the
+/// transfer is abandoned.
+const INCR_TIMEOUT: Duration = Duration::from_secs(5);
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        INCR,
+        OHMY_SELECTION,
+    }
+}
+
+impl Atoms {
+    fn selection(&self, selection: Selection) -> Atom {
+        match selection {
+            Selection::Clipboard => self.CLIPBOARD,
+            Selection::Primary => AtomEnum::PRIMARY.into(),
+        }
+    }
+}
+
+/// Open a connection with a hidden 1x1 window that receives property changes.
+fn open_window() -> Result<(RustConnection, Window, Atoms), String> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let screen = &conn.setup().roots[screen_num];
+    let window = conn.generate_id().map_err(|e| e.to_string())?;
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        window,
+        screen.root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        screen.root_visual,
+        &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let atoms = Atoms::new(&conn)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    Ok((conn, window, atoms))
+}
+
+fn intern(conn: &RustConnection, name: &str) -> Result<Atom, String> {
+    Ok(conn
+        .intern_atom(false, name.as_bytes())
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?
+        .atom)
+}
+
+/// A connection with a hidden window used as the requestor for selection
+/// conversions.
+struct X11Clipboard {
+    conn: RustConnection,
+    window: Window,
+    atoms: Atoms,
+}
+
+impl X11Clipboard {
+    fn connect() -> Result<Self, String> {
+        let (conn, window, atoms) = open_window()?;
+        Ok(Self { conn, window, atoms })
+    }
+
+    /// Wait until an event matching `matches` arrives, discarding any others.
+    /// Returns `None` if `deadline` passes first.
+    fn wait_for<F: Fn(&Event) -> bool>(
+        &self,
+        deadline: Instant,
+        matches: F,
+    ) -> Result<Option<Event>, String> {
+        loop {
+            while let Some(event) = self.conn.poll_for_event().map_err(|e| e.to_string())? {
+                if matches(&event) {
+                    return Ok(Some(event));
+                }
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Ask the owner of `selection` to convert it to `target` and return the
+    /// raw bytes, or `None` if the owner refuses.
+    fn convert(&self, selection: Atom, target: Atom) -> Result<Option<Vec<u8>>, String> {
+        let property = self.atoms.OHMY_SELECTION;
+        self.conn
+            .convert_selection(self.window, selection, target, property, CURRENT_TIME)
+            .map_err(|e| e.to_string())?;
+        self.conn.flush().map_err(|e| e.to_string())?;
+
+        let window = self.window;
+        let deadline = Instant::now() + READ_TIMEOUT;
+        let notify = self.wait_for(deadline, |event| {
+            matches!(event, Event::SelectionNotify(ev) if ev.requestor == window && ev.selection == selection)
+        })?;
+        let Some(Event::SelectionNotify(notify)) = notify else {
+            return Ok(None);
+        };
+        if notify.property == x11rb::NONE {
+            return Ok(None);
+        }
+
+        let reply = self
+            .conn
+            .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX / 4)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+
+        if reply.type_ == self.atoms.INCR {
+            self.read_incremental(deadline).map(Some)
+        } else {
+            Ok(Some(reply.value))
+        }
+    }
+
+    /// Collect a selection transferred with the INCR protocol: the owner writes
+    /// chunks to our property, and each deletion asks for the next one until an
+    /// empty chunk marks the end.
+    fn read_incremental(&self, mut deadline: Instant) -> Result<Vec<u8>, String> {
+        let window = self.window;
+        let property = self.atoms.OHMY_SELECTION;
+        let mut data = Vec::new();
+        loop {
+            let event = self.wait_for(deadline, |event| {
+                matches!(event, Event::PropertyNotify(ev)
+                    if ev.window == window && ev.atom == property && ev.state == Property::NEW_VALUE)
+            })?;
+            if event.is_none() {
+                return Err("Timed out during incremental selection transfer".into());
+            }
+
+            let chunk = self
+                .conn
+                .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX / 4)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?;
+            if chunk.value.is_empty() {
+                return Ok(data);
+            }
+            data.extend_from_slice(&chunk.value);
+            deadline = Instant::now() + READ_TIMEOUT;
+        }
+    }
+
+    /// The targets the current owner of `selection` advertises.
+    fn targets(&self, selection: Atom) -> Result<Vec<Atom>, String> {
+        let Some(bytes) = self.convert(selection, self.atoms.TARGETS)? else {
+            return Ok(Vec::new());
+        };
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+
+    /// Convert `selection` to the first of `mime_types` listed in `available`.
+    fn convert_first(
+        &self,
+        selection: Atom,
+        available: &[Atom],
+        mime_types: &[&str],
+    ) -> Result<Option<Vec<u8>>, String> {
+        for mime_type in mime_types {
+            let target = intern(&self.conn, mime_type)?;
+            // Owners that don't answer TARGETS get asked for every type directly.
+            if !available.is_empty() && !available.contains(&target) {
+                continue;
+            }
+            if let Some(bytes) = self.convert(selection, target)? {
+                return Ok(Some(bytes));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A selection we own: the targets we advertise and the bytes behind each one.
+type OwnedContents = Vec<(Atom, Arc<[u8]>)>;
+
+/// An INCR transfer we are serving: each time the requestor deletes the
+/// property we write the next chunk, and an empty chunk ends the transfer.
+struct IncrTransfer {
+    target: Atom,
+    bytes: Arc<[u8]>,
+    sent: usize,
+    last_activity: Instant,
+}
+
+/// Transfers in progress, by requestor window and property.
+type IncrTransfers = HashMap<(Window, Atom), IncrTransfer>;
+
+/// Owns selections on behalf of the app: a dedicated connection whose window
+/// answers conversion requests from other clients on a background thread.
+struct X11Owner {
+    conn: Arc<RustConnection>,
+    window: Window,
+    atoms: Atoms,
+    /// Contents currently served for each selection atom we own.
+    contents: Arc<Mutex<HashMap<Atom, OwnedContents>>>,
+}
+
+impl X11Owner {
+    fn start() -> Result<Self, String> {
+        let (conn, window, atoms) = open_window()?;
+        let owner = Self {
+            conn: Arc::new(conn),
+            window,
+            atoms,
+            contents: Arc::new(Mutex::new(HashMap::new())),
+        };
+
+        let conn = Arc::clone(&owner.conn);
+        let contents = Arc::clone(&owner.contents);
+        thread::spawn(move || {
+            let mut transfers = IncrTransfers::new();
+            loop {
+                let event = match conn.wait_for_event() {
+                    Ok(event) => event,
+                    Err(e) => {
+                        log::error!("X11 selection owner stopped: {}", e);
+                        return;
+                    }
+                };
+                match event {
+                    Event::SelectionRequest(request) => {
+                        if let Err(e) = serve_request(&conn, &atoms, &contents, &mut transfers, &request) {
+                            log::warn!("Failed to answer X11 selection request: {}", e);
+                        }
+                    }
+                    Event::PropertyNotify(event) if event.state == Property::DELETE => {
+                        if let Err(e) = send_next_chunk(&conn, &mut transfers, event.window, event.atom) {
+                            log::warn!("Failed to continue X11 selection transfer: {}", e);
+                            transfers.remove(&(event.window, event.atom));
+                        }
+                    }
+                    Event::SelectionClear(clear) if clear.owner == window => {
+                        if let Ok(mut contents) = contents.lock() {
+                            contents.remove(&clear.selection);
+                        }
+                    }
+                    _ => {}
+                }
+                // A requestor that went away never asks for the rest
+                transfers.retain(|_, transfer| transfer.last_activity.elapsed() < INCR_TIMEOUT);
+            }
+        });
+        Ok(owner)
+    }
+
+    fn set(&self, selection: Selection, contents: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+        let selection = self.atoms.selection(selection);
+        let mut owned = Vec::with_capacity(contents.len());
+        for (mime_type, bytes) in contents {
+            owned.push((intern(&self.conn, &mime_type)?, Arc::from(bytes)));
+        }
+        self.contents
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(selection, owned);
+
+        self.conn
+            .set_selection_owner(self.window, selection, CURRENT_TIME)
+            .map_err(|e| e.to_string())?;
+        let owner = self
+            .conn
+            .get_selection_owner(selection)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?
+            .owner;
+        if owner != self.window {
+            return Err("another client kept ownership of the selection".into());
+        }
+        Ok(())
+    }
+}
+
+/// Answer a `ConvertSelection` from another client with the stored contents,
+/// or refuse it if we no longer hold the selection or the target is unknown.
+/// Contents too large for one property start an INCR transfer.
+fn serve_request(
+    conn: &RustConnection,
+    atoms: &Atoms,
+    contents: &Mutex<HashMap<Atom, OwnedContents>>,
+    transfers: &mut IncrTransfers,
+    request: &SelectionRequestEvent,
+) -> Result<(), String> {
+    // Obsolete clients may pass no property; the target then doubles as one.
+    let property = if request.property == x11rb::NONE {
+        request.target
+    } else {
+        request.property
+    };
+    let owned = contents
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(&request.selection)
+        .cloned();
+    let max_bytes = max_property_bytes(conn);
+
+    let served = match owned {
+        Some(owned) if request.target == atoms.TARGETS => {
+            let mut targets = vec![atoms.TARGETS];
+            targets.extend(owned.iter().map(|(target, _)| *target));
+            conn.change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::ATOM,
+                &targets,
+            )
+            .map_err(|e| e.to_string())?;
+            true
+        }
+        Some(owned) => match owned.iter().find(|(target, _)| *target == request.target) {
+            Some((_, bytes)) if bytes.len() <= max_bytes => {
+                conn.change_property8(
+                    PropMode::REPLACE,
+                    request.requestor,
+                    property,
+                    request.target,
+                    bytes,
+                )
+                .map_err(|e| e.to_string())?;
+                true
+            }
+            Some((target, bytes)) => {
+                // Announce the size; the requestor deleting the property asks
+                // for the first chunk.
+                conn.change_window_attributes(
+                    request.requestor,
+                    &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+                )
+                .map_err(|e| e.to_string())?;
+                let size = u32::try_from(bytes.len()).unwrap_or(u32::MAX);
+                conn.change_property32(PropMode::REPLACE, request.requestor, property, atoms.INCR, &[size])
+                    .map_err(|e| e.to_string())?;
+                transfers.insert(
+                    (request.requestor, property),
+                    IncrTransfer {
+                        target: *target,
+                        bytes: Arc::clone(bytes),
+                        sent: 0,
+                        last_activity: Instant::now(),
+                    },
+                );
+                true
+            }
+            None => false,
+        },
+        None => false,
+    };
+
+    let notify = SelectionNotifyEvent {
+        response_type: SELECTION_NOTIFY_EVENT,
+        sequence: 0,
+        time: request.time,
+        requestor: request.requestor,
+        selection: request.selection,
+        target: request.target,
+        property: if served { property } else { x11rb::NONE },
+    };
+    conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify)
+        .map_err(|e| e.to_string())?;
+    conn.flush().map_err(|e| e.to_string())
+}
+
+/// Write the next chunk of the INCR transfer to `property` of `requestor`,
+/// if there is one; after the empty final chunk the transfer is done.
+fn send_next_chunk(
+    conn: &RustConnection,
+    transfers: &mut IncrTransfers,
+    requestor: Window,
+    property: Atom,
+) -> Result<(), String> {
+    let Some(transfer) = transfers.get_mut(&(requestor, property)) else {
+        return Ok(());
+    };
+    let end = (transfer.sent + max_property_bytes(conn)).min(transfer.bytes.len());
+    let chunk = &transfer.bytes[transfer.sent..end];
+    let done = chunk.is_empty();
+    conn.change_property8(PropMode::REPLACE, requestor, property, transfer.target, chunk)
+        .map_err(|e| e.to_string())?;
+    if done {
+        transfers.remove(&(requestor, property));
+        conn.change_window_attributes(requestor, &ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT))
+            .map_err(|e| e.to_string())?;
+    } else {
+        transfer.sent = end;
+        transfer.last_activity = Instant::now();
+    }
+    conn.flush().map_err(|e| e.to_string())
+}
+
+/// Largest property to write in one request, leaving headroom for the
+/// request header.
+fn max_property_bytes(conn: &RustConnection) -> usize {
+    conn.maximum_request_bytes().saturating_sub(64).min(MAX_PROPERTY_BYTES)
+}
+
+static OWNER: OnceLock<Result<X11Owner, String>> = OnceLock::new();
+
+/// Read `selection` once per format, each as the first of its MIME types the
+/// owner can provide. A format that fails to convert is logged and left out
+/// rather than failing the others.
+pub fn read(selection: Selection, formats: &[&[&str]]) -> Result<Vec<Option<Vec<u8>>>, String> {
+    let clipboard = X11Clipboard::connect()?;
+    let atom = clipboard.atoms.selection(selection);
+    let available = clipboard.targets(atom)?;
+
+    let mut results = Vec::with_capacity(formats.len());
+    for mime_types in formats {
+        results.push(clipboard.convert_first(atom, &available, mime_types).unwrap_or_else(|e| {
+            log::warn!("Failed to read {:?} from {:?} selection: {}", mime_types, selection, e);
+            None
+        }));
+    }
+    Ok(results)
+}
+
+/// Take ownership of `selection` and serve `contents` (MIME type and bytes)
+/// from the owner thread.
+pub fn write(selection: Selection, contents: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    let owner = OWNER.get_or_init(X11Owner::start).as_ref()?;
+    owner.set(selection, contents)?;
+    owner.conn.flush().map_err(|e| e.to_string())
+}
+
+/// Listen for XFixes selection-owner changes on CLIPBOARD and call `on_change`
+/// for each one.
+pub fn watch_clipboard(on_change: &mut dyn FnMut()) -> Result<(), String> {
+    let clipboard = X11Clipboard::connect()?;
+    let selection = clipboard.atoms.selection(Selection::Clipboard);
+    let conn = &clipboard.conn;
+
+    conn.xfixes_query_version(5, 0)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| format!("XFixes unavailable: {}", e))?;
+    conn.xfixes_select_selection_input(
+        clipboard.window,
+        selection,
+        SelectionEventMask::SET_SELECTION_OWNER
+            | SelectionEventMask::SELECTION_WINDOW_DESTROY
+            | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+    )
+    .map_err(|e| e.to_string())?;
+    conn.flush().map_err(|e| e.to_string())?;
+
+    log::info!("Clipboard monitor started (X11)");
+
+    loop {
+        let event = conn.wait_for_event().map_err(|e| e.to_string())?;
+        if let Event::XfixesSelectionNotify(event) = event {
+            if event.selection == selection && event.owner != x11rb::NONE {
+                on_change();
+            }
+        }
+    }
+}
+
+/// The window manager's active window (EWMH `_NET_ACTIVE_WINDOW`), unless
+/// it belongs to this process.
+pub fn active_window() -> Result<Option<Window>, String> {
+    Ok(active_window_info()?
+        .filter(|info| info.pid != Some(std::process::id()))
+        .map(|info| info.window))
+}
+
+/// Details of the window manager's active window, including our own.
+pub struct WindowInfo {
+    pub window: Window,
+    pub pid: Option<u32>,
+    pub title: Option<String>,
+}
+
+pub fn active_window_info() -> Result<Option<WindowInfo>, String> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let root = conn.setup().roots[screen_num].root;
+    let Some(window) = window_property(&conn, root, "_NET_ACTIVE_WINDOW", AtomEnum::WINDOW.into())?
+        .filter(|&window| window != 0)
+    else {
+        return Ok(None);
+    };
+    let pid = window_property(&conn, window, "_NET_WM_PID", AtomEnum::CARDINAL.into())?;
+    let utf8_string = intern(&conn, "UTF8_STRING")?;
+    let title = match text_property(&conn, window, "_NET_WM_NAME", utf8_string)? {
+        Some(title) => Some(title),
+        None => text_property(&conn, window, "WM_NAME", AtomEnum::STRING.into())?,
+    };
+    Ok(Some(WindowInfo { window, pid, title }))
+}
+
+/// The icon of `window` closest to `size` pixels from `_NET_WM_ICON`, as
+/// width, height and ARGB pixels.
+pub fn window_icon(window: Window, size: u32) -> Result<Option<(u32, u32, Vec<u32>)>, String> {
+    let (conn, _) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let property = intern(&conn, "_NET_WM_ICON")?;
+    let reply = conn
+        .get_property(false, window, property, AtomEnum::CARDINAL, 0, u32::MAX / 4)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    let Some(values) = reply.value32() else {
+        return Ok(None);
+    };
+    let values: Vec<u32> = values.collect();
+
+    // The property holds any number of width, height, pixels... icons
+    let mut icons = Vec::new();
+    let mut rest = values.as_slice();
+    while let [width, height, tail @ ..] = rest {
+        let len = (*width as usize).saturating_mul(*height as usize);
+        if len == 0 || tail.len() < len {
+            break;
+        }
+        icons.push((*width, *height, &tail[..len]));
+        rest = &tail[len..];
+    }
+    // The smallest icon at least `size` wide, else the largest
+    let best = icons
+        .iter()
+        .filter(|(width, _, _)| *width >= size)
+        .min_by_key(|(width, _, _)| *width)
+        .or_else(|| icons.iter().max_by_key(|(width, _, _)| *width));
+    Ok(best.map(|(width, height, pixels)| (*width, *height, pixels.to_vec())))
+}
+
+/// Ask the window manager to activate `window`, as a pager would (window
+/// managers don't apply focus stealing prevention to those requests).
+pub fn activate_window(window: Window) -> Result<(), String> {
+    const SOURCE_PAGER: u32 = 2;
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let root = conn.setup().roots[screen_num].root;
+    let active_window = intern(&conn, "_NET_ACTIVE_WINDOW")?;
+    let event = ClientMessageEvent::new(
+        32,
+        window,
+        active_window,
+        [SOURCE_PAGER, CURRENT_TIME, 0, 0, 0],
+    );
+    conn.send_event(
+        false,
+        root,
+        EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+        event,
+    )
+    .map_err(|e| e.to_string())?;
+    conn.flush().map_err(|e| e.to_string())
+}
+
+/// A string property of a window, if set and of type `property_type`.
+fn text_property(
+    conn: &RustConnection,
+    window: Window,
+    name: &str,
+    property_type: Atom,
+) -> Result<Option<String>, String> {
+    let property = intern(conn, name)?;
+    let reply = conn
+        .get_property(false, window, property, property_type, 0, 1024)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    Ok((reply.type_ == property_type && !reply.value.is_empty())
+        .then(|| String::from_utf8_lossy(&reply.value).into_owned()))
+}
+
+/// The first 32-bit value of a window property.
+fn window_property(
+    conn: &RustConnection,
+    window: Window,
+    name: &str,
+    property_type: Atom,
+) -> Result<Option<u32>, String> {
+    let property = intern(conn, name)?;
+    let reply = conn
+        .get_property(false, window, property, property_type, 0, 1)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    Ok(reply.value32().and_then(|mut values| values.next()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    /// Takes over the CLIPBOARD selection of the display, so it only runs on
+    /// request: `xvfb-run cargo test -- --ignored x11`.
+    #[test]
+    #[ignore = "needs an X11 display and replaces its clipboard"]
+    fn watcher_sees_written_selection() {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("DISPLAY is not set, skipping");
+            return;
+        }
+
+        let (notify, notified) = mpsc::channel();
+        thread::spawn(move || {
+            let result = watch_clipboard(&mut || {
+                let _ = notify.send(());
+            });
+            if let Err(e) = result {
+                eprintln!("Clipboard watcher stopped: {}", e);
+            }
+        });
+
+        // The watcher may not be listening yet when the first write lands
+        let text = format!("x11 clipboard test {}", std::process::id());
+        let seen = (0..10).any(|_| {
+            write(Selection::Clipboard, super::super::text_contents(&text)).unwrap();
+            notified.recv_timeout(Duration::from_millis(500)).is_ok()
+        });
+        assert!(seen, "watch_clipboard was not notified of the new owner");
+
+        let read = read(Selection::Clipboard, &[super::super::TEXT_MIME_TYPES]).unwrap();
+        assert_eq!(read, vec![Some(text.into_bytes())]);
+    }
+
+    /// Contents over `MAX_PROPERTY_BYTES` are served in INCR chunks. Run
+    /// with `xvfb-run cargo test -- --ignored x11`.
+    #[test]
+    #[ignore = "needs an X11 display and replaces its clipboard"]
+    fn large_selection_is_served_incrementally() {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("DISPLAY is not set, skipping");
+            return;
+        }
+
+        let bytes: Vec<u8> = (0..MAX_PROPERTY_BYTES * 3 + 17).map(|i| (i % 251) as u8).collect();
+        write(Selection::Clipboard, vec![("image/png".into(), bytes.clone())]).unwrap();
+        let read = read(Selection::Clipboard, &[&["image/png"]]).unwrap();
+        assert_eq!(read, vec![Some(bytes)]);
+    }
+}
@@ -1,16 +1,34 @@
+pub mod actions;
 pub mod classifier;
+pub mod collections;
+pub mod crypto;
 pub mod entry;
+pub mod export;
+pub mod formats;
+pub mod images;
+#[cfg(target_os = "linux")]
+pub mod linux;
 pub mod monitor;
+pub mod paste_stack;
+pub mod retention;
+pub mod search;
+pub mod sensitive;
+pub mod settings;
 pub mod source_app;
 pub mod storage;
+pub mod transform;
 
 use entry::ClipboardEntry;
+use settings::ClipboardSettings;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, RwLock};
 
 /// Shared clipboard history state accessible from Tauri commands.
 pub struct ClipboardHistoryState(pub Arc<RwLock<Vec<ClipboardEntry>>>);
 
+/// Clipboard settings shared by the commands, the monitor and the retention timer.
+pub struct ClipboardSettingsState(pub Arc<RwLock<ClipboardSettings>>);
+
 /// When `true`, the clipboard monitor ignores the next clipboard change.
 /// Used by the text expansion injector to suppress self-monitoring.
 pub static SUPPRESS_CLIPBOARD_MONITOR: AtomicBool = AtomicBool::new(false);
@@ -1,27 +1,259 @@
-use super::entry::{ClipboardEntry, ContentType};
-use super::{classifier, source_app, storage, SUPPRESS_CLIPBOARD_MONITOR};
+use super::entry::{ClipboardEntry, ClipboardFormats, ContentType};
+use super::sensitive::{self, SecretAction, SensitiveSettings};
+use super::settings::ClipboardSettings;
+use super::{classifier, crypto, images, retention, source_app, storage, SUPPRESS_CLIPBOARD_MONITOR};
+use crate::text_expansion::injector;
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, RwLock};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
+/// Images larger than this (encoded as PNG) are not recorded.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+const MAX_IMAGE_BYTES: usize = 20_000_000;
+/// HTML or RTF representations larger than this are not recorded.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+const MAX_RICH_BYTES: usize = 5_000_000;
+
 /// Start the clipboard monitor on a background thread.
-/// Creates a hidden message-only window and listens for WM_CLIPBOARDUPDATE.
+/// On Windows this creates a hidden message-only window and listens for
+/// WM_CLIPBOARDUPDATE; on Linux it watches the X11 or Wayland CLIPBOARD selection.
 pub fn start_monitor(
     app_handle: AppHandle,
     entries: Arc<RwLock<Vec<ClipboardEntry>>>,
+    settings: Arc<RwLock<ClipboardSettings>>,
     app_data_dir: PathBuf,
 ) {
     std::thread::spawn(move || {
-        run_monitor_loop(app_handle, entries, app_data_dir);
+        run_monitor_loop(app_handle, entries, settings, app_data_dir);
     });
 }
 
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+struct MonitorContext {
+    app_handle: AppHandle,
+    entries: Arc<RwLock<Vec<ClipboardEntry>>>,
+    settings: Arc<RwLock<ClipboardSettings>>,
+    app_data_dir: PathBuf,
+    last_time: Instant,
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+impl MonitorContext {
+    fn new(
+        app_handle: AppHandle,
+        entries: Arc<RwLock<Vec<ClipboardEntry>>>,
+        settings: Arc<RwLock<ClipboardSettings>>,
+        app_data_dir: PathBuf,
+    ) -> Self {
+        Self {
+            app_handle,
+            entries,
+            settings,
+            app_data_dir,
+            last_time: Instant::now(),
+        }
+    }
+}
+
+/// Shared pipeline for every platform: debounce, filter sensitive content,
+/// read, dedup, classify, persist and notify the frontend. Text takes
+/// precedence over images when the clipboard holds both.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn handle_clipboard_update(ctx: &mut MonitorContext) {
+    // Check suppression flag (set by text expansion injector)
+    if SUPPRESS_CLIPBOARD_MONITOR.load(Ordering::SeqCst) {
+        return;
+    }
+
+    // Debounce: skip if last change was < 100ms ago
+    let now = Instant::now();
+    if now.duration_since(ctx.last_time).as_millis() < 100 {
+        return;
+    }
+    ctx.last_time = now;
+
+    // Nothing can be stored until an encrypted history is unlocked.
+    if crypto::is_locked() {
+        return;
+    }
+
+    let sensitive = ctx
+        .settings
+        .read()
+        .map(|s| s.sensitive.clone())
+        .unwrap_or_default();
+    if sensitive.respect_clipboard_hints && injector::clipboard_excluded_from_history() {
+        log::debug!("Skipping clipboard content marked as excluded from history");
+        return;
+    }
+    let source_app::ForegroundApp { name: source_app, details } =
+        source_app::foreground_app(Some(&ctx.app_data_dir));
+    if sensitive.is_denied_app(&source_app) {
+        log::debug!("Skipping clipboard content from denied app {}", source_app);
+        return;
+    }
+
+    let formats = injector::get_clipboard_formats();
+    // File managers don't always offer the file list as text.
+    let text = injector::get_clipboard_text()
+        .filter(|t| !t.is_empty())
+        .or_else(|| (!formats.files.is_empty()).then(|| formats.files.join("\n")));
+
+    let entry = match text {
+        Some(text) => text_entry(ctx, text, formats, source_app, &sensitive),
+        None => match injector::get_clipboard_image() {
+            Some(png) => image_entry(ctx, &png, source_app),
+            None => return,
+        },
+    };
+    let Some(entry) = entry else {
+        return;
+    };
+    let entry = entry.with_source_details(details);
+
+    // Add to state
+    if let Ok(mut entries) = ctx.entries.write() {
+        let merge = ctx.settings.read().is_ok_and(|s| s.merge_duplicates);
+        let duplicate = merge
+            .then(|| entries.iter().position(|e| e.is_duplicate_of(&entry)))
+            .flatten();
+        let mut was_stored = false;
+        let entry = match duplicate {
+            Some(index) => {
+                let mut existing = entries.remove(index);
+                was_stored = existing.expires_at.is_none();
+                existing.record_copy(&entry);
+                // The existing entry already has the image files.
+                if let Some(image) = &entry.image {
+                    images::delete_files(image);
+                }
+                existing
+            }
+            None => entry,
+        };
+        entries.insert(0, entry.clone());
+
+        // Persist, unless it is a secret that only lives in memory until it expires
+        match entry.expires_at {
+            None => storage::insert(&ctx.app_data_dir, &entry),
+            Some(_) => {
+                // A stored entry copied again as a secret leaves the disk
+                if was_stored {
+                    storage::delete(&ctx.app_data_dir, &entry.id);
+                }
+                sensitive::schedule_expiry(
+                    ctx.app_handle.clone(),
+                    ctx.entries.clone(),
+                    ctx.app_data_dir.clone(),
+                    entry.id.clone(),
+                    Duration::from_secs(sensitive.expire_after_secs),
+                )
+            }
+        }
+
+        // Emit event to frontend (a merged entry replaces its old position)
+        let _ = ctx.app_handle.emit("clipboard-changed", &entry);
+
+        // Enforce limits now that the history grew
+        if let Ok(settings) = ctx.settings.read() {
+            retention::apply(&ctx.app_handle, &ctx.app_data_dir, &settings.retention, &mut entries);
+        }
+    }
+}
+
+/// Dedup: with merging off, skip content identical to the newest entry. With
+/// merging on, nothing is skipped so the merge can bump the copy count.
+/// Checking the history rather than the last seen hash means content copied
+/// again after its entry was deleted or expired is stored again.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn repeats_newest(ctx: &MonitorContext, hash: u64) -> bool {
+    let merge = ctx.settings.read().is_ok_and(|s| s.merge_duplicates);
+    !merge
+        && ctx
+            .entries
+            .read()
+            .is_ok_and(|entries| entries.first().is_some_and(|e| e.content_hash == hash))
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn text_entry(
+    ctx: &MonitorContext,
+    text: String,
+    mut formats: ClipboardFormats,
+    source_app: String,
+    sensitive: &SensitiveSettings,
+) -> Option<ClipboardEntry> {
+    // Skip entries > 1MB
+    if text.len() > 1_000_000 {
+        return None;
+    }
+
+    let hash = ClipboardEntry::hash_content(&text);
+    if repeats_newest(ctx, hash) {
+        return None;
+    }
+
+    let secret = sensitive.detect_secret(&text);
+    if let Some(kind) = &secret {
+        if sensitive.secret_action == SecretAction::Skip {
+            log::info!("Skipping clipboard text that looks like a {}", kind);
+            return None;
+        }
+    }
+
+    // Drop oversized rich representations but keep the text
+    if formats.html.as_ref().is_some_and(|html| html.len() > MAX_RICH_BYTES) {
+        formats.html = None;
+    }
+    if formats.rtf.as_ref().is_some_and(|rtf| rtf.len() > MAX_RICH_BYTES) {
+        formats.rtf = None;
+    }
+
+    let classification = if formats.files.is_empty() {
+        match ctx.settings.read() {
+            Ok(settings) => classifier::classify_with(&text, &settings.custom_classifiers),
+            Err(_) => classifier::classify(&text),
+        }
+    } else {
+        ContentType::Files.into()
+    };
+    let mut entry = ClipboardEntry::new(text, classification.content_type, source_app)
+        .with_formats(formats)
+        .with_metadata(classification.metadata);
+    if secret.is_some() {
+        entry.expires_at = Some(entry.timestamp + sensitive.expire_after_secs as i64 * 1000);
+    }
+    Some(entry)
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn image_entry(ctx: &MonitorContext, png: &[u8], source_app: String) -> Option<ClipboardEntry> {
+    if png.len() > MAX_IMAGE_BYTES {
+        return None;
+    }
+
+    let hash = ClipboardEntry::hash_bytes(png);
+    if repeats_newest(ctx, hash) {
+        return None;
+    }
+
+    let image = match images::save(&ctx.app_data_dir, png) {
+        Ok(image) => image,
+        Err(e) => {
+            log::error!("Failed to store clipboard image: {}", e);
+            return None;
+        }
+    };
+    Some(ClipboardEntry::new_image(image, hash, source_app))
+}
+
 #[cfg(target_os = "windows")]
 fn run_monitor_loop(
     app_handle: AppHandle,
     entries: Arc<RwLock<Vec<ClipboardEntry>>>,
+    settings: Arc<RwLock<ClipboardSettings>>,
     app_data_dir: PathBuf,
 ) {
     use std::ffi::c_void;
@@ -86,14 +318,6 @@ fn run_monitor_loop(
         static MONITOR_CTX: std::cell::RefCell<Option<MonitorContext>> = const { std::cell::RefCell::new(None) };
     }
 
-    struct MonitorContext {
-        app_handle: AppHandle,
-        entries: Arc<RwLock<Vec<ClipboardEntry>>>,
-        app_data_dir: PathBuf,
-        last_hash: u64,
-        last_time: Instant,
-    }
-
     unsafe extern "system" fn wnd_proc(
         hwnd: *mut c_void,
         msg: u32,
@@ -111,63 +335,6 @@ fn run_monitor_loop(
         DefWindowProcW(hwnd, msg, w_param, l_param)
     }
 
-    fn handle_clipboard_update(ctx: &mut MonitorContext) {
-        // Check suppression flag (set by text expansion injector)
-        if SUPPRESS_CLIPBOARD_MONITOR.load(Ordering::SeqCst) {
-            return;
-        }
-
-        // Debounce: skip if last change was < 100ms ago
-        let now = Instant::now();
-        if now.duration_since(ctx.last_time).as_millis() < 100 {
-            return;
-        }
-        ctx.last_time = now;
-
-        // Read clipboard text
-        let text = match crate::text_expansion::injector::get_clipboard_text() {
-            Some(t) if !t.is_empty() => t,
-            _ => return,
-        };
-
-        // Skip entries > 1MB
-        if text.len() > 1_000_000 {
-            return;
-        }
-
-        // Dedup: skip if content matches last entry
-        let hash = ClipboardEntry::hash_content(&text);
-        if hash == ctx.last_hash {
-            return;
-        }
-        ctx.last_hash = hash;
-
-        // Classify content
-        let content_type = classifier::classify(&text);
-
-        // Skip image type for now (text-only MVP)
-        if content_type == ContentType::Image {
-            return;
-        }
-
-        // Detect source application
-        let source_app = source_app::get_foreground_app_name();
-
-        // Build entry
-        let entry = ClipboardEntry::new(text, content_type, source_app);
-
-        // Add to state
-        if let Ok(mut entries) = ctx.entries.write() {
-            entries.insert(0, entry.clone());
-
-            // Persist
-            storage::save(&ctx.app_data_dir, &entries);
-        }
-
-        // Emit event to frontend
-        let _ = ctx.app_handle.emit("clipboard-changed", &entry);
-    }
-
     // Register window class
     unsafe {
         let class_name: Vec<u16> = "OhMyClipboardMonitor\0"
@@ -224,13 +391,7 @@ fn run_monitor_loop(
 
         // Set context for the wndproc
         MONITOR_CTX.with(|ctx| {
-            *ctx.borrow_mut() = Some(MonitorContext {
-                app_handle,
-                entries,
-                app_data_dir,
-                last_hash: 0,
-                last_time: Instant::now(),
-            });
+            *ctx.borrow_mut() = Some(MonitorContext::new(app_handle, entries, settings, app_data_dir));
         });
 
         // Message pump
@@ -242,11 +403,26 @@ fn run_monitor_loop(
     }
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(target_os = "linux")]
+fn run_monitor_loop(
+    app_handle: AppHandle,
+    entries: Arc<RwLock<Vec<ClipboardEntry>>>,
+    settings: Arc<RwLock<ClipboardSettings>>,
+    app_data_dir: PathBuf,
+) {
+    let mut ctx = MonitorContext::new(app_handle, entries, settings, app_data_dir);
+    let result = super::linux::watch_clipboard(|| handle_clipboard_update(&mut ctx));
+    if let Err(e) = result {
+        log::error!("Clipboard monitor stopped: {}", e);
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 fn run_monitor_loop(
     _app_handle: AppHandle,
     _entries: Arc<RwLock<Vec<ClipboardEntry>>>,
+    _settings: Arc<RwLock<ClipboardSettings>>,
     _app_data_dir: PathBuf,
 ) {
-    log::warn!("Clipboard monitoring is only supported on Windows");
+    log::warn!("Clipboard monitoring is only supported on Windows and Linux");
 }
@@ -0,0 +1,85 @@
+use super::entry::ClipboardEntry;
+use super::images;
+use crate::text_expansion::injector;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Ids of history entries queued for sequential pasting, front first.
+pub struct PasteStackState(pub Mutex<VecDeque<String>>);
+
+/// Replace the queued entries and notify the frontend.
+pub fn set(app_handle: &AppHandle, stack: &PasteStackState, ids: Vec<String>) -> Result<(), String> {
+    let mut queue = stack.0.lock().map_err(|e| e.to_string())?;
+    *queue = ids.into();
+    let _ = app_handle.emit("paste-stack-changed", Vec::from(queue.clone()));
+    Ok(())
+}
+
+/// Paste the next queued entry into the focused window. Ids of entries that
+/// were deleted in the meantime are skipped. The entry is only taken off the
+/// queue once it's ready to paste, so one whose image can't be read stays
+/// queued.
+pub fn paste_next(
+    app_handle: &AppHandle,
+    stack: &PasteStackState,
+    entries: &Arc<RwLock<Vec<ClipboardEntry>>>,
+) {
+    let next = {
+        let Ok(mut queue) = stack.0.lock() else { return };
+        let Ok(entries) = entries.read() else { return };
+        let mut next = None;
+        let queued = queue.len();
+        while let Some(id) = queue.front() {
+            if let Some(entry) = entries.iter().find(|e| &e.id == id) {
+                next = Some(entry.clone());
+                break;
+            }
+            queue.pop_front();
+        }
+        if queue.len() != queued {
+            let _ = app_handle.emit("paste-stack-changed", Vec::from(queue.clone()));
+        }
+        next
+    };
+    let Some(entry) = next else {
+        log::info!("Paste stack is empty");
+        return;
+    };
+
+    let image = match &entry.image {
+        Some(image) => {
+            let read = app_handle
+                .path()
+                .app_data_dir()
+                .map_err(|e| e.to_string())
+                .and_then(|dir| images::read(&dir, &image.path));
+            match read {
+                Ok(png) => Some(png),
+                Err(e) => {
+                    log::error!("Failed to read image for paste stack: {}", e);
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    {
+        let Ok(mut queue) = stack.0.lock() else { return };
+        // The queue may have been replaced while the image was read.
+        if queue.front() != Some(&entry.id) {
+            return;
+        }
+        queue.pop_front();
+        let _ = app_handle.emit("paste-stack-changed", Vec::from(queue.clone()));
+    }
+
+    std::thread::spawn(move || {
+        // The hotkey's modifiers would turn Ctrl+V into another shortcut.
+        injector::release_modifiers();
+        std::thread::sleep(Duration::from_millis(50));
+        injector::paste_entry(&entry.content, &entry.formats, image.as_deref());
+    });
+}
@@ -0,0 +1,149 @@
+use super::entry::{ClipboardEntry, ContentType};
+use super::settings::ClipboardSettings;
+use super::{images, storage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// How often the background timer applies the policy (for age-based expiry).
+const TIMER_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Limits on entries that are neither pinned nor in a collection. `None`
+/// means unlimited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionLimits {
+    /// Keep at most this many entries (newest first).
+    pub max_entries: Option<usize>,
+    /// Keep entries until their combined size exceeds this many bytes.
+    pub max_bytes: Option<u64>,
+    /// Drop entries older than this many days.
+    pub max_age_days: Option<u32>,
+}
+
+/// When unpinned entries are dropped from the history. Pinned entries and
+/// entries in a collection are always kept and don't count toward any limit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionPolicy {
+    #[serde(flatten)]
+    pub limits: RetentionLimits,
+    /// Limits for specific content types. Entries of an overridden type are
+    /// counted on their own; fields left unset fall back to `limits`.
+    pub overrides: HashMap<ContentType, RetentionLimits>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            limits: RetentionLimits {
+                max_entries: Some(500),
+                max_bytes: None,
+                max_age_days: None,
+            },
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// The limits that apply to `content_type`.
+    fn limits_for(&self, content_type: &ContentType) -> RetentionLimits {
+        match self.overrides.get(content_type) {
+            Some(o) => RetentionLimits {
+                max_entries: o.max_entries.or(self.limits.max_entries),
+                max_bytes: o.max_bytes.or(self.limits.max_bytes),
+                max_age_days: o.max_age_days.or(self.limits.max_age_days),
+            },
+            None => self.limits.clone(),
+        }
+    }
+
+    /// IDs of the entries the policy drops at time `now` (ms since epoch).
+    pub fn expired(&self, entries: &[ClipboardEntry], now: i64) -> Vec<String> {
+        let mut unpinned: Vec<&ClipboardEntry> = entries.iter().filter(|e| !e.is_kept()).collect();
+        unpinned.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+
+        // Running (count, bytes) per group; overridden types form their own group.
+        let mut totals: HashMap<Option<&ContentType>, (usize, u64)> = HashMap::new();
+        let mut expired = Vec::new();
+        for entry in unpinned {
+            let limits = self.limits_for(&entry.content_type);
+            let group = self
+                .overrides
+                .contains_key(&entry.content_type)
+                .then_some(&entry.content_type);
+            let (count, bytes) = totals.entry(group).or_default();
+            *count += 1;
+            *bytes += entry.size_bytes();
+
+            let too_old = limits
+                .max_age_days
+                .is_some_and(|days| now - entry.timestamp > days as i64 * DAY_MS);
+            let too_many = limits.max_entries.is_some_and(|max| *count > max);
+            let too_big = limits.max_bytes.is_some_and(|max| *bytes > max);
+            if too_old || too_many || too_big {
+                expired.push(entry.id.clone());
+            }
+        }
+        expired
+    }
+}
+
+/// Apply `policy` to the in-memory history and the store.
+pub fn apply(
+    app_handle: &AppHandle,
+    app_data_dir: &PathBuf,
+    policy: &RetentionPolicy,
+    entries: &mut Vec<ClipboardEntry>,
+) {
+    let now = chrono::Local::now().timestamp_millis();
+    let expired = policy.expired(entries, now);
+    if expired.is_empty() {
+        return;
+    }
+
+    remove(app_handle, app_data_dir, &expired, entries);
+    log::info!("Retention policy removed {} clipboard entries", expired.len());
+}
+
+/// Drop `ids` from the in-memory history and the store, delete their image
+/// files and tell the frontend which entries went away.
+pub fn remove(
+    app_handle: &AppHandle,
+    app_data_dir: &PathBuf,
+    ids: &[String],
+    entries: &mut Vec<ClipboardEntry>,
+) {
+    storage::delete_many(app_data_dir, ids);
+    entries.retain(|e| {
+        let keep = !ids.contains(&e.id);
+        if !keep {
+            if let Some(image) = &e.image {
+                images::delete_files(image);
+            }
+        }
+        keep
+    });
+    let _ = app_handle.emit("clipboard-entries-removed", ids);
+}
+
+/// Apply the retention policy now and then periodically on a background thread.
+pub fn start_timer(
+    app_handle: AppHandle,
+    entries: Arc<RwLock<Vec<ClipboardEntry>>>,
+    settings: Arc<RwLock<ClipboardSettings>>,
+    app_data_dir: PathBuf,
+) {
+    std::thread::spawn(move || loop {
+        let policy = settings.read().map(|s| s.retention.clone());
+        if let (Ok(policy), Ok(mut entries)) = (policy, entries.write()) {
+            apply(&app_handle, &app_data_dir, &policy, &mut entries);
+        }
+        std::thread::sleep(TIMER_INTERVAL);
+    });
+}
@@ -0,0 +1,179 @@
+use super::entry::{ClipboardEntry, ContentType};
+use super::{crypto, storage};
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// Filters and paging for `search_clipboard_history`. Every filter is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchQuery {
+    /// Free text; every word must match the start of a word in the content.
+    pub text: String,
+    pub content_types: Vec<ContentType>,
+    pub source_apps: Vec<String>,
+    /// Inclusive lower bound on the entry timestamp (ms since epoch).
+    pub from: Option<i64>,
+    /// Inclusive upper bound on the entry timestamp (ms since epoch).
+    pub to: Option<i64>,
+    pub pinned: Option<bool>,
+    /// Only entries in this collection.
+    pub collection: Option<String>,
+    /// Only entries carrying every one of these tags.
+    pub tags: Vec<String>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// One page of search results, plus the total number of matches.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    pub entries: Vec<ClipboardEntry>,
+    pub total: usize,
+}
+
+/// Turn free text into an FTS5 query: each whitespace-separated word becomes
+/// a quoted prefix term, so user input can't inject FTS syntax.
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Search the stored history. Text matches are ranked by relevance (BM25),
+/// everything else by recency. Encrypted content isn't indexed, so an
+/// encrypted history is searched in `history`, the decrypted copy in memory.
+pub fn search(app_data_dir: &PathBuf, query: &SearchQuery, history: &[ClipboardEntry]) -> Result<SearchResults, String> {
+    if crypto::is_locked() {
+        return Err("Clipboard history is locked".into());
+    }
+    if crypto::is_enabled() {
+        return Ok(search_decrypted(history, query));
+    }
+    let conn = storage::open(app_data_dir)?;
+
+    let mut joins = String::new();
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Value> = Vec::new();
+    let mut order = "e.timestamp DESC";
+
+    if let Some(fts) = fts_query(&query.text) {
+        joins.push_str(" JOIN entries_fts ON entries_fts.rowid = e.rowid");
+        conditions.push("entries_fts MATCH ?".into());
+        params.push(Value::Text(fts));
+        order = "bm25(entries_fts), e.timestamp DESC";
+    }
+    if !query.content_types.is_empty() {
+        let names = query
+            .content_types
+            .iter()
+            .map(storage::content_type_name)
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        conditions.push(format!("e.content_type IN ({})", placeholders(names.len())));
+        params.extend(names.into_iter().map(Value::Text));
+    }
+    if !query.source_apps.is_empty() {
+        conditions.push(format!("e.source_app IN ({})", placeholders(query.source_apps.len())));
+        params.extend(query.source_apps.iter().cloned().map(Value::Text));
+    }
+    if let Some(from) = query.from {
+        conditions.push("e.timestamp >= ?".into());
+        params.push(Value::Integer(from));
+    }
+    if let Some(to) = query.to {
+        conditions.push("e.timestamp <= ?".into());
+        params.push(Value::Integer(to));
+    }
+    if let Some(pinned) = query.pinned {
+        conditions.push("e.pinned = ?".into());
+        params.push(Value::Integer(pinned as i64));
+    }
+    if let Some(collection) = &query.collection {
+        conditions.push("e.collection = ?".into());
+        params.push(Value::Text(collection.clone()));
+    }
+    for tag in &query.tags {
+        conditions.push("EXISTS (SELECT 1 FROM json_each(e.tags) WHERE value = ?)".into());
+        params.push(Value::Text(tag.to_lowercase()));
+    }
+
+    let filter = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+
+    let total: usize = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM entries e{joins}{filter}"),
+            rusqlite::params_from_iter(&params),
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let columns = storage::COLUMNS
+        .split(',')
+        .map(|column| format!("e.{}", column.trim()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {columns} FROM entries e{joins}{filter} ORDER BY {order} LIMIT ? OFFSET ?"
+        ))
+        .map_err(|e| e.to_string())?;
+    params.push(Value::Integer(limit as i64));
+    params.push(Value::Integer(query.offset as i64));
+    let entries = stmt
+        .query_map(rusqlite::params_from_iter(&params), storage::from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(SearchResults { entries, total })
+}
+
+/// The same search done in memory, by recency only. Entries that are only
+/// kept in memory aren't stored, so they are left out as well.
+fn search_decrypted(entries: &[ClipboardEntry], query: &SearchQuery) -> SearchResults {
+    let words: Vec<String> = query.text.split_whitespace().map(str::to_lowercase).collect();
+    let matches: Vec<&ClipboardEntry> = entries
+        .iter()
+        .filter(|e| e.expires_at.is_none())
+        .filter(|e| {
+            query.content_types.is_empty() || query.content_types.contains(&e.content_type)
+        })
+        .filter(|e| query.source_apps.is_empty() || query.source_apps.contains(&e.source_app))
+        .filter(|e| query.from.map_or(true, |from| e.timestamp >= from))
+        .filter(|e| query.to.map_or(true, |to| e.timestamp <= to))
+        .filter(|e| query.pinned.map_or(true, |pinned| e.pinned == pinned))
+        .filter(|e| query.collection.is_none() || e.collection == query.collection)
+        .filter(|e| query.tags.iter().all(|tag| e.tags.contains(&tag.to_lowercase())))
+        .filter(|e| {
+            let content = e.content.to_lowercase();
+            let content_words: Vec<&str> = content
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|w| !w.is_empty())
+                .collect();
+            words
+                .iter()
+                .all(|word| content_words.iter().any(|w| w.starts_with(word.as_str())))
+        })
+        .collect();
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let total = matches.len();
+    let entries = matches.into_iter().skip(query.offset).take(limit).cloned().collect();
+    SearchResults { entries, total }
+}
+
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
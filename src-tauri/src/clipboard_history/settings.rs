@@ -0,0 +1,102 @@
+use super::classifier::CustomClassifier;
+use super::retention::RetentionPolicy;
+use super::sensitive::SensitiveSettings;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "clipboard_settings.json";
+
+/// User-configurable clipboard history behaviour, persisted as JSON in the
+/// app data directory. Missing fields take their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClipboardSettings {
+    pub retention: RetentionPolicy,
+    pub sensitive: SensitiveSettings,
+    /// Move an existing entry with the same content to the top instead of
+    /// adding a duplicate.
+    pub merge_duplicates: bool,
+    /// User-defined content types, checked before the built-in ones.
+    pub custom_classifiers: Vec<CustomClassifier>,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        Self {
+            retention: RetentionPolicy::default(),
+            sensitive: SensitiveSettings::default(),
+            merge_duplicates: true,
+            custom_classifiers: Vec::new(),
+        }
+    }
+}
+
+impl ClipboardSettings {
+    /// Compile the user-supplied patterns. Each one is compiled on its own,
+    /// so an invalid pattern doesn't disable the others; the errors are
+    /// reported together. Done once when the settings are loaded or
+    /// replaced, not per copy.
+    pub fn compile(&mut self) -> Result<(), String> {
+        let errors: Vec<String> = std::iter::once(self.sensitive.compile())
+            .chain(self.custom_classifiers.iter_mut().map(CustomClassifier::compile))
+            .filter_map(Result::err)
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+
+fn settings_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(FILE_NAME)
+}
+
+/// Load settings from disk, falling back to defaults.
+pub fn load(app_data_dir: &Path) -> ClipboardSettings {
+    let mut settings = match std::fs::read_to_string(settings_path(app_data_dir)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            log::error!("Invalid clipboard settings, using defaults: {}", e);
+            ClipboardSettings::default()
+        }),
+        Err(_) => ClipboardSettings::default(),
+    };
+    if let Err(e) = settings.compile() {
+        log::error!("Ignoring invalid clipboard patterns: {}", e);
+    }
+    settings
+}
+
+/// Save settings to disk.
+pub fn save(app_data_dir: &PathBuf, settings: &ClipboardSettings) -> Result<(), String> {
+    std::fs::create_dir_all(app_data_dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(settings_path(app_data_dir), json).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard_history::classifier::classify_with;
+    use crate::clipboard_history::entry::ContentType;
+
+    #[test]
+    fn invalid_patterns_dont_disable_the_others() {
+        let mut settings: ClipboardSettings = serde_json::from_str(
+            r#"{
+                "sensitive": { "customPatterns": ["(", "^secret-\\d+$"] },
+                "customClassifiers": [
+                    { "name": "broken", "label": "Broken", "pattern": "[" },
+                    { "name": "ticket", "label": "Ticket", "pattern": "[A-Z]+-\\d+" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let errors = settings.compile().unwrap_err();
+        assert!(errors.contains("secret pattern") && errors.contains("\"broken\""), "{}", errors);
+        assert!(settings.sensitive.detect_secret("secret-42").is_some());
+        let classification = classify_with("ABC-1", &settings.custom_classifiers);
+        assert_eq!(classification.content_type, ContentType::Custom("ticket".into()));
+    }
+}
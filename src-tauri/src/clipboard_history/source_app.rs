@@ -1,51 +1,402 @@
-/// Get the name of the foreground application (Windows only).
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Directory of cached application icons, next to the clipboard images.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+const ICONS_DIR_NAME: &str = "app_icons";
+/// Preferred edge length of X11 window icons, in pixels.
+#[cfg(target_os = "linux")]
+const ICON_SIZE: u32 = 32;
+
+/// Where an entry was copied from, besides the application name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceDetails {
+    /// Full path of the application's executable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe_path: Option<String>,
+    /// Title of the window that had focus.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Path of the cached PNG icon of the application.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+impl SourceDetails {
+    pub fn is_empty(&self) -> bool {
+        self.exe_path.is_none() && self.title.is_none() && self.icon.is_none()
+    }
+}
+
+/// The application owning the focused window.
+pub struct ForegroundApp {
+    /// Executable name without `.exe`, or "Unknown".
+    pub name: String,
+    pub details: SourceDetails,
+}
+
+impl ForegroundApp {
+    fn unknown() -> Self {
+        Self { name: "Unknown".into(), details: SourceDetails::default() }
+    }
+}
+
+/// Whether `app` (an app name) is in `apps`, a user-edited list of names
+/// compared case-insensitively.
+pub fn is_listed(apps: &[String], app: &str) -> bool {
+    apps.iter().any(|listed| listed.trim().eq_ignore_ascii_case(app))
+}
+
+/// The executable file name without a `.exe` extension.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn app_name(exe_path: &str) -> String {
+    exe_path
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or("Unknown")
+        .trim_end_matches(".exe")
+        .trim_end_matches(".EXE")
+        .to_string()
+}
+
+/// The icon cached for `key` (the executable path), extracting it with
+/// `extract` (PNG data) the first time.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn cached_icon(app_data_dir: &Path, key: &str, extract: impl FnOnce() -> Option<Vec<u8>>) -> Option<String> {
+    let dir = app_data_dir.join(ICONS_DIR_NAME);
+    let path = dir.join(format!("{:016x}.png", super::entry::ClipboardEntry::hash_content(key)));
+    if !path.exists() {
+        let png = extract()?;
+        std::fs::create_dir_all(&dir)
+            .and_then(|_| std::fs::write(&path, png))
+            .map_err(|e| log::warn!("Failed to cache the icon of {}: {}", key, e))
+            .ok()?;
+    }
+    Some(path.to_string_lossy().into_owned())
+}
+
+/// Encode straight (non-premultiplied) RGBA pixels as PNG.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn encode_icon(width: u32, height: u32, rgba: Vec<u8>) -> Option<Vec<u8>> {
+    let img = image::RgbaImage::from_raw(width, height, rgba)?;
+    let mut png = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .ok()?;
+    Some(png)
+}
+
+/// The application owning the foreground window, with its executable path,
+/// window title and (with an `app_data_dir` to cache it in) icon.
 #[cfg(target_os = "windows")]
-pub fn get_foreground_app_name() -> String {
+pub fn foreground_app(app_data_dir: Option<&Path>) -> ForegroundApp {
     unsafe {
         let hwnd = GetForegroundWindow();
         if hwnd.is_null() {
-            return "Unknown".into();
+            return ForegroundApp::unknown();
         }
 
-        let mut process_id: u32 = 0;
-        GetWindowThreadProcessId(hwnd, &mut process_id);
-        if process_id == 0 {
-            return "Unknown".into();
+        let title = window_title(hwnd);
+        let Some(exe_path) = process_image_path(hwnd) else {
+            return ForegroundApp {
+                name: "Unknown".into(),
+                details: SourceDetails { title, ..Default::default() },
+            };
+        };
+        let icon = app_data_dir.and_then(|dir| cached_icon(dir, &exe_path, || exe_icon(&exe_path)));
+        ForegroundApp {
+            name: app_name(&exe_path),
+            details: SourceDetails { exe_path: Some(exe_path), title, icon },
         }
+    }
+}
 
-        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
-        if handle.is_null() {
-            return "Unknown".into();
+#[cfg(target_os = "windows")]
+unsafe fn window_title(hwnd: *mut std::ffi::c_void) -> Option<String> {
+    let len = GetWindowTextLengthW(hwnd);
+    if len <= 0 {
+        return None;
+    }
+    let mut buf = vec![0u16; len as usize + 1];
+    let copied = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+    (copied > 0).then(|| String::from_utf16_lossy(&buf[..copied as usize]))
+}
+
+/// Full path of the executable of the process owning `hwnd`.
+#[cfg(target_os = "windows")]
+unsafe fn process_image_path(hwnd: *mut std::ffi::c_void) -> Option<String> {
+    let mut process_id: u32 = 0;
+    GetWindowThreadProcessId(hwnd, &mut process_id);
+    if process_id == 0 {
+        return None;
+    }
+
+    let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+    if handle.is_null() {
+        return None;
+    }
+
+    let mut buf = [0u16; 1024];
+    let mut size = buf.len() as u32;
+    let ok = QueryFullProcessImageNameW(handle, 0, buf.as_mut_ptr(), &mut size);
+    CloseHandle(handle);
+
+    (ok != 0).then(|| String::from_utf16_lossy(&buf[..size as usize]))
+}
+
+/// The large icon of an executable, as PNG.
+#[cfg(target_os = "windows")]
+fn exe_icon(exe_path: &str) -> Option<Vec<u8>> {
+    let wide: Vec<u16> = exe_path.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        let mut icon = std::ptr::null_mut();
+        if ExtractIconExW(wide.as_ptr(), 0, &mut icon, std::ptr::null_mut(), 1) == 0 || icon.is_null() {
+            return None;
         }
+        let png = icon_to_png(icon);
+        DestroyIcon(icon);
+        png
+    }
+}
+
+#[cfg(target_os = "windows")]
+unsafe fn icon_to_png(icon: *mut std::ffi::c_void) -> Option<Vec<u8>> {
+    let mut info: IconInfo = std::mem::zeroed();
+    if GetIconInfo(icon, &mut info) == 0 {
+        return None;
+    }
+    let png = bitmap_to_png(info.color_bitmap);
+    if !info.color_bitmap.is_null() {
+        DeleteObject(info.color_bitmap);
+    }
+    if !info.mask_bitmap.is_null() {
+        DeleteObject(info.mask_bitmap);
+    }
+    png
+}
+
+/// Read a color bitmap as 32-bit top-down BGRA and encode it as PNG.
+#[cfg(target_os = "windows")]
+unsafe fn bitmap_to_png(bitmap: *mut std::ffi::c_void) -> Option<Vec<u8>> {
+    if bitmap.is_null() {
+        return None;
+    }
+    let mut bm: Bitmap = std::mem::zeroed();
+    if GetObjectW(bitmap, std::mem::size_of::<Bitmap>() as i32, &mut bm as *mut _ as *mut _) == 0 {
+        return None;
+    }
+    let (width, height) = (bm.width as u32, bm.height as u32);
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut header = BitmapInfoHeader {
+        size: std::mem::size_of::<BitmapInfoHeader>() as u32,
+        width: bm.width,
+        // Negative height: top-down rows
+        height: -bm.height,
+        planes: 1,
+        bit_count: 32,
+        compression: BI_RGB,
+        ..std::mem::zeroed()
+    };
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    let dc = GetDC(std::ptr::null_mut());
+    let lines = GetDIBits(dc, bitmap, 0, height, pixels.as_mut_ptr() as *mut _, &mut header, DIB_RGB_COLORS);
+    ReleaseDC(std::ptr::null_mut(), dc);
+    if lines == 0 {
+        return None;
+    }
+
+    for px in pixels.chunks_exact_mut(4) {
+        px.swap(0, 2);
+    }
+    // Icons without an alpha channel leave it zeroed
+    if pixels.chunks_exact(4).all(|px| px[3] == 0) {
+        pixels.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+    }
+    encode_icon(width, height, pixels)
+}
+
+/// The application owning the active X11 window: `_NET_WM_PID` resolved
+/// through /proc, the window title and `_NET_WM_ICON`. Wayland clients can't
+/// see other applications' windows, so this is "Unknown" there.
+#[cfg(target_os = "linux")]
+pub fn foreground_app(app_data_dir: Option<&Path>) -> ForegroundApp {
+    let Some(info) = super::linux::active_window_info() else {
+        return ForegroundApp::unknown();
+    };
+
+    let exe_path = info.pid.and_then(|pid| std::fs::read_link(format!("/proc/{}/exe", pid)).ok()).map(|path| {
+        let path = path.to_string_lossy();
+        // Executables replaced since the process started
+        path.strip_suffix(" (deleted)").unwrap_or(&path).to_string()
+    });
+    // /proc/<pid>/exe of other users' processes can't be read, comm can
+    let name = match &exe_path {
+        Some(path) => app_name(path),
+        None => info
+            .pid
+            .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok())
+            .map(|comm| comm.trim().to_string())
+            .filter(|comm| !comm.is_empty())
+            .unwrap_or_else(|| "Unknown".into()),
+    };
 
-        let mut buf = [0u16; 260];
-        let mut size = buf.len() as u32;
-        let ok = QueryFullProcessImageNameW(handle, 0, buf.as_mut_ptr(), &mut size);
-        CloseHandle(handle);
+    let icon_key = exe_path.clone().unwrap_or_else(|| name.clone());
+    let icon = app_data_dir.and_then(|dir| {
+        cached_icon(dir, &icon_key, || {
+            let (width, height, argb) = super::linux::window_icon(info.window, ICON_SIZE)?;
+            let rgba = argb
+                .iter()
+                .flat_map(|px| {
+                    let [a, r, g, b] = px.to_be_bytes();
+                    [r, g, b, a]
+                })
+                .collect();
+            encode_icon(width, height, rgba)
+        })
+    });
 
-        if ok == 0 {
-            return "Unknown".into();
+    ForegroundApp {
+        name,
+        details: SourceDetails { exe_path, title: info.title, icon },
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn foreground_app(_app_data_dir: Option<&Path>) -> ForegroundApp {
+    ForegroundApp::unknown()
+}
+
+/// Whether `foreground_app` can tell which application has focus. Wayland
+/// clients can't, so it's always "Unknown" there.
+#[cfg(target_os = "windows")]
+pub fn can_identify_foreground_app() -> bool {
+    true
+}
+
+#[cfg(target_os = "linux")]
+pub fn can_identify_foreground_app() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_none()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn can_identify_foreground_app() -> bool {
+    false
+}
+
+#[cfg(target_os = "windows")]
+type RawWindow = isize;
+#[cfg(target_os = "linux")]
+type RawWindow = u32;
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+type RawWindow = ();
+
+/// A top-level window of another application, remembered so focus can be
+/// given back to it later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowHandle(RawWindow);
+
+/// The window that currently has focus, unless it belongs to this app.
+#[cfg(target_os = "windows")]
+pub fn foreground_window() -> Option<WindowHandle> {
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_null() {
+            return None;
         }
+        let mut process_id: u32 = 0;
+        GetWindowThreadProcessId(hwnd, &mut process_id);
+        (process_id != std::process::id()).then_some(WindowHandle(hwnd as isize))
+    }
+}
 
-        let path = String::from_utf16_lossy(&buf[..size as usize]);
-        // Extract just the filename without extension
-        path.rsplit('\\')
-            .next()
-            .unwrap_or("Unknown")
-            .trim_end_matches(".exe")
-            .trim_end_matches(".EXE")
-            .to_string()
+/// Bring `window` to the front and give it keyboard focus.
+#[cfg(target_os = "windows")]
+pub fn activate_window(window: WindowHandle) -> bool {
+    let hwnd = window.0 as *mut std::ffi::c_void;
+    unsafe {
+        if IsWindow(hwnd) == 0 {
+            return false;
+        }
+        if IsIconic(hwnd) != 0 {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+        SetForegroundWindow(hwnd) != 0
     }
 }
 
-#[cfg(not(target_os = "windows"))]
-pub fn get_foreground_app_name() -> String {
-    "Unknown".into()
+/// The window that currently has focus (X11 `_NET_ACTIVE_WINDOW`), unless it
+/// belongs to this app. Not available to Wayland clients.
+#[cfg(target_os = "linux")]
+pub fn foreground_window() -> Option<WindowHandle> {
+    super::linux::active_window().map(WindowHandle)
+}
+
+/// Ask the window manager to activate `window`.
+#[cfg(target_os = "linux")]
+pub fn activate_window(window: WindowHandle) -> bool {
+    super::linux::activate_window(window.0)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn foreground_window() -> Option<WindowHandle> {
+    None
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn activate_window(_window: WindowHandle) -> bool {
+    false
 }
 
 // Win32 FFI bindings
 #[cfg(target_os = "windows")]
 const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+#[cfg(target_os = "windows")]
+const SW_RESTORE: i32 = 9;
+#[cfg(target_os = "windows")]
+const BI_RGB: u32 = 0;
+#[cfg(target_os = "windows")]
+const DIB_RGB_COLORS: u32 = 0;
+
+#[cfg(target_os = "windows")]
+#[repr(C)]
+struct IconInfo {
+    is_icon: i32,
+    hotspot_x: u32,
+    hotspot_y: u32,
+    mask_bitmap: *mut std::ffi::c_void,
+    color_bitmap: *mut std::ffi::c_void,
+}
+
+#[cfg(target_os = "windows")]
+#[repr(C)]
+struct Bitmap {
+    kind: i32,
+    width: i32,
+    height: i32,
+    width_bytes: i32,
+    planes: u16,
+    bits_pixel: u16,
+    bits: *mut std::ffi::c_void,
+}
+
+#[cfg(target_os = "windows")]
+#[repr(C)]
+struct BitmapInfoHeader {
+    size: u32,
+    width: i32,
+    height: i32,
+    planes: u16,
+    bit_count: u16,
+    compression: u32,
+    size_image: u32,
+    x_pels_per_meter: i32,
+    y_pels_per_meter: i32,
+    clr_used: u32,
+    clr_important: u32,
+}
 
 #[cfg(target_os = "windows")]
 extern "system" {
@@ -59,4 +410,32 @@ extern "system" {
         size: *mut u32,
     ) -> i32;
     fn CloseHandle(handle: *mut std::ffi::c_void) -> i32;
+    fn IsWindow(hwnd: *mut std::ffi::c_void) -> i32;
+    fn IsIconic(hwnd: *mut std::ffi::c_void) -> i32;
+    fn ShowWindow(hwnd: *mut std::ffi::c_void, cmd: i32) -> i32;
+    fn SetForegroundWindow(hwnd: *mut std::ffi::c_void) -> i32;
+    fn GetWindowTextLengthW(hwnd: *mut std::ffi::c_void) -> i32;
+    fn GetWindowTextW(hwnd: *mut std::ffi::c_void, text: *mut u16, max_count: i32) -> i32;
+    fn ExtractIconExW(
+        file: *const u16,
+        index: i32,
+        large: *mut *mut std::ffi::c_void,
+        small: *mut *mut std::ffi::c_void,
+        count: u32,
+    ) -> u32;
+    fn GetIconInfo(icon: *mut std::ffi::c_void, info: *mut IconInfo) -> i32;
+    fn DestroyIcon(icon: *mut std::ffi::c_void) -> i32;
+    fn GetObjectW(object: *mut std::ffi::c_void, size: i32, out: *mut std::ffi::c_void) -> i32;
+    fn GetDC(hwnd: *mut std::ffi::c_void) -> *mut std::ffi::c_void;
+    fn ReleaseDC(hwnd: *mut std::ffi::c_void, dc: *mut std::ffi::c_void) -> i32;
+    fn GetDIBits(
+        dc: *mut std::ffi::c_void,
+        bitmap: *mut std::ffi::c_void,
+        start: u32,
+        lines: u32,
+        bits: *mut std::ffi::c_void,
+        info: *mut BitmapInfoHeader,
+        usage: u32,
+    ) -> i32;
+    fn DeleteObject(object: *mut std::ffi::c_void) -> i32;
 }
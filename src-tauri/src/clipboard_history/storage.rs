@@ -1,54 +1,678 @@
-use super::entry::ClipboardEntry;
-use std::path::PathBuf;
+use super::collections::Collection;
+use super::entry::{ClipboardEntry, ContentType, ImageInfo};
+use super::{crypto, images};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-const FILE_NAME: &str = "clipboard_history.json";
-const MAX_ENTRIES: usize = 500;
+const FILE_NAME: &str = "clipboard_history.db";
+/// History file used before the SQLite store; imported once, then renamed.
+const LEGACY_FILE_NAME: &str = "clipboard_history.json";
 
-/// Get the storage file path inside the app data directory.
+/// Schema migrations, applied in order. `PRAGMA user_version` records how
+/// many have run.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE entries (
+        id           TEXT PRIMARY KEY,
+        content      TEXT NOT NULL,
+        content_type TEXT NOT NULL,
+        source_app   TEXT NOT NULL,
+        word_count   INTEGER NOT NULL,
+        char_count   INTEGER NOT NULL,
+        timestamp    INTEGER NOT NULL,
+        pinned       INTEGER NOT NULL,
+        preview      TEXT NOT NULL,
+        image        TEXT,
+        formats      TEXT,
+        content_hash INTEGER NOT NULL
+    );
+    CREATE INDEX entries_timestamp ON entries (timestamp);",
+    // Full-text index over `content`, kept in sync by triggers. Entries are
+    // never re-inserted (see `upsert`), so their rowids stay stable.
+    "CREATE VIRTUAL TABLE entries_fts USING fts5 (
+        content,
+        content = 'entries',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER entries_fts_insert AFTER INSERT ON entries BEGIN
+        INSERT INTO entries_fts (rowid, content) VALUES (new.rowid, new.content);
+    END;
+    CREATE TRIGGER entries_fts_delete AFTER DELETE ON entries BEGIN
+        INSERT INTO entries_fts (entries_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+    END;
+    CREATE TRIGGER entries_fts_update AFTER UPDATE OF content ON entries BEGIN
+        INSERT INTO entries_fts (entries_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+        INSERT INTO entries_fts (rowid, content) VALUES (new.rowid, new.content);
+    END;
+    INSERT INTO entries_fts (entries_fts) VALUES ('rebuild');
+    CREATE INDEX entries_source_app ON entries (source_app);",
+    // Encrypted content (see `crypto`) is kept out of the full-text index.
+    "DROP TRIGGER entries_fts_insert;
+    DROP TRIGGER entries_fts_delete;
+    DROP TRIGGER entries_fts_update;
+    CREATE TRIGGER entries_fts_insert AFTER INSERT ON entries
+    WHEN new.content NOT LIKE 'enc1:%' BEGIN
+        INSERT INTO entries_fts (rowid, content) VALUES (new.rowid, new.content);
+    END;
+    CREATE TRIGGER entries_fts_delete AFTER DELETE ON entries
+    WHEN old.content NOT LIKE 'enc1:%' BEGIN
+        INSERT INTO entries_fts (entries_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+    END;
+    CREATE TRIGGER entries_fts_update AFTER UPDATE OF content ON entries BEGIN
+        INSERT INTO entries_fts (entries_fts, rowid, content)
+            SELECT 'delete', old.rowid, old.content WHERE old.content NOT LIKE 'enc1:%';
+        INSERT INTO entries_fts (rowid, content)
+            SELECT new.rowid, new.content WHERE new.content NOT LIKE 'enc1:%';
+    END;",
+    "ALTER TABLE entries ADD COLUMN copy_count INTEGER NOT NULL DEFAULT 1;",
+    "ALTER TABLE entries ADD COLUMN metadata TEXT;",
+    "ALTER TABLE entries ADD COLUMN source_details TEXT;",
+    // Collection names are stored as-is, like source app names.
+    "CREATE TABLE collections (
+        id         TEXT PRIMARY KEY,
+        name       TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    ALTER TABLE entries ADD COLUMN collection TEXT REFERENCES collections (id);
+    ALTER TABLE entries ADD COLUMN tags TEXT;
+    CREATE INDEX entries_collection ON entries (collection);",
+    // Hashes were `DefaultHasher` output, which changes between Rust releases.
+    // Text hashes of 0 are recomputed on load; image hashes would need the
+    // files, so a pre-existing image copied again is stored once more.
+    "UPDATE entries SET content_hash = 0 WHERE image IS NULL;",
+    // Values that must change in the same transaction as the rows, such as
+    // the id of the key that encrypted them (see `crypto::change_key`).
+    "CREATE TABLE meta (
+        key   TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+];
+
+pub(super) const COLUMNS: &str = "id, content, content_type, source_app, word_count, char_count, \
+     timestamp, pinned, preview, image, formats, content_hash, copy_count, metadata, source_details, collection, tags";
+
+/// Get the database file path inside the app data directory.
 pub fn storage_path(app_data_dir: &PathBuf) -> PathBuf {
     app_data_dir.join(FILE_NAME)
 }
 
-/// Load clipboard history from disk.
+/// Open the database, creating it and applying pending migrations.
+pub(super) fn open(app_data_dir: &PathBuf) -> Result<Connection, String> {
+    std::fs::create_dir_all(app_data_dir).map_err(|e| e.to_string())?;
+    let mut conn = Connection::open(storage_path(app_data_dir)).map_err(|e| e.to_string())?;
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+
+    let version: usize = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if version < MIGRATIONS.len() {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for migration in &MIGRATIONS[version..] {
+            tx.execute_batch(migration).map_err(|e| e.to_string())?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len())
+            .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+    Ok(conn)
+}
+
+/// Run `f` in a transaction on a freshly opened connection, logging failures.
+fn with_transaction(
+    app_data_dir: &PathBuf,
+    what: &str,
+    f: impl FnOnce(&Transaction) -> rusqlite::Result<()>,
+) {
+    let result = open(app_data_dir).and_then(|mut conn| {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        f(&tx).map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        log::error!("Failed to {}: {}", what, e);
+    }
+}
+
+/// Import `clipboard_history.json` into an empty database and rename it so
+/// it is only imported once. A file that can't be read or parsed is left in
+/// place, so nothing is lost and the import is tried again next time.
+fn migrate_legacy_json(app_data_dir: &Path, conn: &mut Connection) -> Result<(), String> {
+    let legacy_path = app_data_dir.join(LEGACY_FILE_NAME);
+    let json = match std::fs::read_to_string(&legacy_path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Failed to read {}: {}", legacy_path.display(), e)),
+    };
+    let mut entries: Vec<ClipboardEntry> = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse {}: {}", legacy_path.display(), e))?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for entry in &mut entries {
+        // The JSON file never stored hashes.
+        entry.content_hash = ClipboardEntry::hash_content(&entry.content);
+        upsert(&tx, entry).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    std::fs::rename(&legacy_path, legacy_path.with_extension("json.migrated"))
+        .map_err(|e| e.to_string())?;
+    log::info!("Migrated {} clipboard entries to SQLite", entries.len());
+    Ok(())
+}
+
+/// Load clipboard history from disk, newest first. Returns nothing while an
+/// encrypted history is locked.
 pub fn load(app_data_dir: &PathBuf) -> Vec<ClipboardEntry> {
-    let path = storage_path(app_data_dir);
-    match std::fs::read_to_string(&path) {
-        Ok(json) => serde_json::from_str(&json).unwrap_or_default(),
-        Err(_) => Vec::new(),
+    if crypto::is_locked() {
+        log::info!("Clipboard history is locked; load it after unlocking");
+        return Vec::new();
+    }
+    load_all(app_data_dir).unwrap_or_else(|e| {
+        log::error!("Failed to load clipboard history: {}", e);
+        Vec::new()
+    })
+}
+
+/// Every stored entry, decrypted, newest first. A row that fails to decode
+/// or decrypt is logged and skipped rather than hiding the whole history.
+pub(super) fn load_all(app_data_dir: &PathBuf) -> Result<Vec<ClipboardEntry>, String> {
+    let mut conn = open(app_data_dir)?;
+    // Still load what the database has; the JSON file stays for a retry
+    if let Err(e) = migrate_legacy_json(app_data_dir, &mut conn) {
+        log::error!("Failed to import the legacy clipboard history: {}", e);
+    }
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {COLUMNS} FROM entries ORDER BY timestamp DESC, rowid DESC"
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, from_row(row))))
+        .map_err(|e| e.to_string())?;
+    let mut entries = Vec::new();
+    for row in rows {
+        match row.map_err(|e| e.to_string())? {
+            (_, Ok(entry)) => entries.push(entry),
+            (id, Err(e)) => log::error!("Skipping unreadable clipboard entry {}: {}", id, e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Delete image files left behind by entries that were dropped. Stored
+/// entries keep their files even when they can't be decrypted (the image
+/// column isn't encrypted); `in_memory` adds the entries that aren't stored.
+pub fn remove_orphaned_images(app_data_dir: &PathBuf, in_memory: &[ClipboardEntry]) {
+    let stored = open(app_data_dir).and_then(|conn| {
+        let mut stmt = conn
+            .prepare("SELECT image FROM entries WHERE image IS NOT NULL")
+            .map_err(|e| e.to_string())?;
+        let images = stmt
+            .query_map([], |row| from_json::<ImageInfo>(&row.get::<_, String>(0)?, 0))
+            .map_err(|e| e.to_string())?;
+        images.collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())
+    });
+    match stored {
+        Ok(stored) => {
+            let in_memory = in_memory.iter().filter_map(|e| e.image.as_ref());
+            images::remove_orphans(app_data_dir, stored.iter().chain(in_memory));
+        }
+        Err(e) => log::error!("Failed to look for orphaned clipboard images: {}", e),
+    }
+}
+
+/// Write every entry again with the current key after it changed, recording
+/// `key_id` in the same transaction, then scrub what the old representation
+/// left behind: the index, free pages, the WAL and the migrated JSON file.
+pub(super) fn rewrite_all(app_data_dir: &PathBuf, entries: &[ClipboardEntry], key_id: &str) -> Result<(), String> {
+    let mut conn = open(app_data_dir)?;
+    conn.pragma_update(None, "secure_delete", true)
+        .map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for entry in entries {
+        upsert(&tx, entry).map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "INSERT INTO meta (key, value) VALUES ('key_id', ?1)
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        [key_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    // The rows are committed, so an error means nothing was written: old
+    // pages left behind by a failed vacuum are only logged.
+    if let Err(e) = conn.execute_batch(
+        "INSERT INTO entries_fts (entries_fts) VALUES ('optimize');
+         VACUUM;
+         PRAGMA wal_checkpoint(TRUNCATE);",
+    ) {
+        log::warn!("Failed to compact clipboard history after rewriting it: {}", e);
     }
+    let _ = std::fs::remove_file(app_data_dir.join(LEGACY_FILE_NAME).with_extension("json.migrated"));
+    Ok(())
+}
+
+/// The id of the key the stored rows were last rewritten with, if any.
+pub(super) fn key_id(app_data_dir: &PathBuf) -> Result<Option<String>, String> {
+    open(app_data_dir)?
+        .query_row("SELECT value FROM meta WHERE key = 'key_id'", [], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())
+}
+
+/// Every collection, oldest first.
+pub fn load_collections(app_data_dir: &PathBuf) -> Result<Vec<Collection>, String> {
+    let conn = open(app_data_dir)?;
+    let mut stmt = conn
+        .prepare("SELECT id, name, created_at FROM collections ORDER BY created_at, rowid")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Collection {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Store a new collection or a renamed one.
+pub fn save_collection(app_data_dir: &PathBuf, collection: &Collection) -> Result<(), String> {
+    open(app_data_dir)?
+        .execute(
+            "INSERT INTO collections (id, name, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (id) DO UPDATE SET name = excluded.name",
+            params![collection.id, collection.name, collection.created_at],
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Delete a collection; its entries go back to the plain history.
+pub fn delete_collection(app_data_dir: &PathBuf, id: &str) -> Result<(), String> {
+    let mut conn = open(app_data_dir)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("UPDATE entries SET collection = NULL WHERE collection = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM collections WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Store a new entry. Limits are enforced separately by `retention`.
+pub fn insert(app_data_dir: &PathBuf, entry: &ClipboardEntry) {
+    with_transaction(app_data_dir, "save clipboard entry", |tx| upsert(tx, entry));
+}
+
+/// Store or update several entries at once, e.g. after an import.
+pub fn insert_many(app_data_dir: &PathBuf, entries: &[ClipboardEntry]) {
+    with_transaction(app_data_dir, "save clipboard entries", |tx| {
+        entries.iter().try_for_each(|entry| upsert(tx, entry))
+    });
+}
+
+/// Persist changes to an existing entry (e.g. its pinned state).
+pub fn update(app_data_dir: &PathBuf, entry: &ClipboardEntry) {
+    with_transaction(app_data_dir, "update clipboard entry", |tx| upsert(tx, entry));
+}
+
+/// Delete a single entry.
+pub fn delete(app_data_dir: &PathBuf, id: &str) {
+    with_transaction(app_data_dir, "delete clipboard entry", |tx| {
+        tx.execute("DELETE FROM entries WHERE id = ?1", [id]).map(|_| ())
+    });
 }
 
-/// Save clipboard history to disk, enforcing the max entry limit.
-/// Removes oldest unpinned entries when over the limit.
+/// Delete several entries at once.
+pub fn delete_many(app_data_dir: &PathBuf, ids: &[String]) {
+    with_transaction(app_data_dir, "delete clipboard entries", |tx| {
+        let mut stmt = tx.prepare("DELETE FROM entries WHERE id = ?1")?;
+        for id in ids {
+            stmt.execute([id])?;
+        }
+        Ok(())
+    });
+}
+
+/// Make the stored history match `entries`, as the JSON store's `save` did:
+/// rows of other entries are deleted and the rest written again. Entries
+/// that only live in memory until they expire are left out. Single changes
+/// are cheaper through `insert`, `update` and `delete`; limits are enforced
+/// by `retention`.
 pub fn save(app_data_dir: &PathBuf, entries: &[ClipboardEntry]) {
-    let path = storage_path(app_data_dir);
-
-    // Ensure the directory exists
-    if let Some(parent) = path.parent() {
-        let _ = std::fs::create_dir_all(parent);
-    }
-
-    // Enforce max entries: keep all pinned + newest unpinned up to MAX_ENTRIES
-    let to_save: Vec<&ClipboardEntry> = if entries.len() > MAX_ENTRIES {
-        let pinned: Vec<&ClipboardEntry> = entries.iter().filter(|e| e.pinned).collect();
-        let mut unpinned: Vec<&ClipboardEntry> = entries.iter().filter(|e| !e.pinned).collect();
-        // Sort unpinned by timestamp descending (newest first)
-        unpinned.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        let remaining = MAX_ENTRIES.saturating_sub(pinned.len());
-        unpinned.truncate(remaining);
-        let mut result = pinned;
-        result.extend(unpinned);
-        result
+    with_transaction(app_data_dir, "save clipboard history", |tx| {
+        let kept: HashSet<&str> = entries
+            .iter()
+            .filter(|e| e.expires_at.is_none())
+            .map(|e| e.id.as_str())
+            .collect();
+        let stored: Vec<String> = tx
+            .prepare("SELECT id FROM entries")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut delete = tx.prepare("DELETE FROM entries WHERE id = ?1")?;
+        for id in stored.iter().filter(|id| !kept.contains(id.as_str())) {
+            delete.execute([id])?;
+        }
+        entries
+            .iter()
+            .filter(|e| e.expires_at.is_none())
+            .try_for_each(|entry| upsert(tx, entry))
+    });
+}
+
+fn upsert(tx: &Transaction, entry: &ClipboardEntry) -> rusqlite::Result<()> {
+    let image = entry.image.as_ref().map(to_json).transpose()?;
+    let formats = if entry.formats.is_empty() {
+        None
     } else {
-        entries.iter().collect()
+        Some(encrypt(&to_json(&entry.formats)?)?)
     };
+    // Hosts, languages and the like say a lot about the content
+    let metadata = entry
+        .metadata
+        .as_ref()
+        .map(|metadata| encrypt(&to_json(metadata)?))
+        .transpose()?;
+    // Window titles name documents, pages and conversations
+    let source_details = entry
+        .source_details
+        .as_ref()
+        .map(|details| encrypt(&to_json(details)?))
+        .transpose()?;
+    let tags = if entry.tags.is_empty() {
+        None
+    } else {
+        Some(encrypt(&to_json(&entry.tags)?)?)
+    };
+    // A hash of short text is easy to reverse, so encrypted text entries
+    // store none and get it recomputed on load.
+    let content_hash = if crypto::is_enabled() && entry.image.is_none() {
+        0
+    } else {
+        entry.content_hash
+    };
+    tx.execute(
+        // An upsert rather than INSERT OR REPLACE: REPLACE deletes the old row
+        // without firing the delete trigger that keeps the FTS index in sync.
+        &format!(
+            "INSERT INTO entries ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+             ON CONFLICT (id) DO UPDATE SET
+                content = excluded.content,
+                content_type = excluded.content_type,
+                source_app = excluded.source_app,
+                word_count = excluded.word_count,
+                char_count = excluded.char_count,
+                timestamp = excluded.timestamp,
+                pinned = excluded.pinned,
+                preview = excluded.preview,
+                image = excluded.image,
+                formats = excluded.formats,
+                content_hash = excluded.content_hash,
+                copy_count = excluded.copy_count,
+                metadata = excluded.metadata,
+                source_details = excluded.source_details,
+                collection = excluded.collection,
+                tags = excluded.tags"
+        ),
+        params![
+            entry.id,
+            encrypt(&entry.content)?,
+            content_type_name(&entry.content_type)?,
+            entry.source_app,
+            entry.word_count,
+            entry.char_count,
+            entry.timestamp,
+            entry.pinned,
+            encrypt(&entry.preview)?,
+            image,
+            formats,
+            // SQLite integers are signed; store the hash's bits as-is.
+            content_hash as i64,
+            entry.copy_count,
+            metadata,
+            source_details,
+            entry.collection,
+            tags,
+        ],
+    )?;
+    Ok(())
+}
+
+pub(super) fn from_row(row: &Row) -> rusqlite::Result<ClipboardEntry> {
+    let content_type: String = row.get(2)?;
+    let image: Option<String> = row.get(9)?;
+    let formats: Option<String> = row.get(10)?;
+    let metadata: Option<String> = row.get(13)?;
+    let source_details: Option<String> = row.get(14)?;
+    let tags: Option<String> = row.get(16)?;
+    let content = decrypt(row.get(1)?, 1)?;
+    let content_hash = match row.get::<_, i64>(11)? as u64 {
+        0 => ClipboardEntry::hash_content(&content),
+        hash => hash,
+    };
+    Ok(ClipboardEntry {
+        id: row.get(0)?,
+        content,
+        content_type: serde_json::from_value(serde_json::Value::String(content_type)).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        source_app: row.get(3)?,
+        source_details: source_details
+            .map(|json| from_json(&decrypt(json, 14)?, 14))
+            .transpose()?,
+        word_count: row.get(4)?,
+        char_count: row.get(5)?,
+        timestamp: row.get(6)?,
+        pinned: row.get(7)?,
+        collection: row.get(15)?,
+        tags: tags
+            .map(|json| from_json(&decrypt(json, 16)?, 16))
+            .transpose()?
+            .unwrap_or_default(),
+        preview: decrypt(row.get(8)?, 8)?,
+        image: image.map(|json| from_json(&json, 9)).transpose()?,
+        formats: formats
+            .map(|json| from_json(&decrypt(json, 10)?, 10))
+            .transpose()?
+            .unwrap_or_default(),
+        metadata: metadata
+            .map(|json| from_json(&decrypt(json, 13)?, 13))
+            .transpose()?,
+        // Expiring entries are never stored.
+        expires_at: None,
+        content_hash,
+        copy_count: row.get(12)?,
+    })
+}
+
+fn encrypt(text: &str) -> rusqlite::Result<String> {
+    crypto::encrypt_text(text).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))
+}
+
+fn decrypt(value: String, column: usize) -> rusqlite::Result<String> {
+    crypto::decrypt_text(value).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+/// The serialized (lowercase) name of a content type, e.g. `"link"`.
+pub(super) fn content_type_name(content_type: &ContentType) -> rusqlite::Result<String> {
+    Ok(to_json(content_type)?.trim_matches('"').to_string())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(json: &str, column: usize) -> rusqlite::Result<T> {
+    serde_json::from_str(json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clipboard-storage-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn insert_raw(conn: &Connection, id: &str, content: &str, content_hash: i64) {
+        conn.execute(
+            "INSERT INTO entries (id, content, content_type, source_app, word_count, char_count,
+                 timestamp, pinned, preview, image, formats, content_hash)
+             VALUES (?1, ?2, 'text', 'Editor', 2, 11, 1, 0, ?2, NULL, NULL, ?3)",
+            params![id, content, content_hash],
+        )
+        .unwrap();
+    }
 
-    match serde_json::to_string(&to_save) {
-        Ok(json) => {
-            if let Err(e) = std::fs::write(&path, json) {
-                log::error!("Failed to save clipboard history: {}", e);
+    #[test]
+    fn every_migration_step_upgrades_to_the_current_schema() {
+        let _lock = crypto::test_lock();
+        let hash_reset = MIGRATIONS
+            .iter()
+            .position(|migration| migration.contains("SET content_hash = 0"))
+            .unwrap();
+        for applied in 1..MIGRATIONS.len() {
+            let dir = temp_dir();
+            let conn = Connection::open(storage_path(&dir)).unwrap();
+            for migration in &MIGRATIONS[..applied] {
+                conn.execute_batch(migration).unwrap();
             }
+            conn.pragma_update(None, "user_version", applied).unwrap();
+            // An old `DefaultHasher` hash while the migration resetting them
+            // is pending; 0 (recomputed on load) once it has run
+            let old_hash = if applied <= hash_reset { 12345 } else { 0 };
+            insert_raw(&conn, "a", "hello world", old_hash);
+            drop(conn);
+
+            let entries = load_all(&dir).unwrap();
+            assert_eq!(entries.len(), 1, "from version {}", applied);
+            let entry = &entries[0];
+            assert_eq!(entry.content_type, ContentType::Text);
+            assert_eq!(entry.content_hash, ClipboardEntry::hash_content("hello world"));
+            assert_eq!(entry.copy_count, 1);
+            assert!(entry.tags.is_empty() && entry.collection.is_none() && entry.metadata.is_none());
+
+            let conn = open(&dir).unwrap();
+            let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+            assert_eq!(version, MIGRATIONS.len());
+            let indexed: i64 = conn
+                .query_row("SELECT count(*) FROM entries_fts WHERE entries_fts MATCH 'hello'", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(indexed, 1, "from version {}", applied);
+            drop(conn);
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn legacy_json_is_imported_once() {
+        let _lock = crypto::test_lock();
+        let dir = temp_dir();
+        let mut entry = ClipboardEntry::new("from json".into(), ContentType::Text, "Editor".into());
+        entry.content_hash = 0;
+        std::fs::write(dir.join(LEGACY_FILE_NAME), serde_json::to_string(&[&entry]).unwrap()).unwrap();
+
+        let entries = load_all(&dir).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, entry.id);
+        assert_eq!(entries[0].content_hash, ClipboardEntry::hash_content("from json"));
+        assert!(!dir.join(LEGACY_FILE_NAME).exists());
+        assert!(dir.join(LEGACY_FILE_NAME).with_extension("json.migrated").exists());
+
+        assert_eq!(load_all(&dir).unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn unreadable_legacy_json_is_kept_for_a_retry() {
+        let _lock = crypto::test_lock();
+        let dir = temp_dir();
+        std::fs::write(dir.join(LEGACY_FILE_NAME), "not json").unwrap();
+        assert!(load_all(&dir).unwrap().is_empty());
+        assert!(dir.join(LEGACY_FILE_NAME).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn unreadable_rows_are_skipped() {
+        let _lock = crypto::test_lock();
+        let dir = temp_dir();
+        insert(&dir, &ClipboardEntry::new("readable".into(), ContentType::Text, "Editor".into()));
+        let conn = open(&dir).unwrap();
+        // Encrypted with a key that isn't loaded
+        insert_raw(&conn, "sealed", "enc1:AAAA", 0);
+        insert_raw(&conn, "broken", "plain", 0);
+        conn.execute("UPDATE entries SET formats = 'not json' WHERE id = 'broken'", []).unwrap();
+        drop(conn);
+
+        let entries = load_all(&dir).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].content, "readable");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn only_old_unreferenced_images_are_removed() {
+        let _lock = crypto::test_lock();
+        let dir = temp_dir();
+        let images_dir = images::images_dir(&dir);
+        std::fs::create_dir_all(&images_dir).unwrap();
+        let image = |name: &str| ImageInfo {
+            path: images_dir.join(format!("{}.png", name)).to_string_lossy().into_owned(),
+            thumbnail_path: images_dir.join(format!("{}.thumb.png", name)).to_string_lossy().into_owned(),
+            width: 1,
+            height: 1,
+            byte_size: 1,
+            encrypted: false,
+        };
+        let an_hour_ago = std::time::SystemTime::now() - Duration::from_secs(3600);
+        let write = |path: PathBuf, old: bool| {
+            let file = std::fs::File::create(&path).unwrap();
+            if old {
+                file.set_modified(an_hour_ago).unwrap();
+            }
+            path
+        };
+        let with_image = |name: &str| {
+            let mut entry = ClipboardEntry::new(String::new(), ContentType::Image, "Editor".into());
+            entry.image = Some(image(name));
+            entry
+        };
+
+        let stored = with_image("stored");
+        insert(&dir, &stored);
+        // A secret image that only lives in memory
+        let in_memory = with_image("in-memory");
+        let kept = [
+            write(PathBuf::from(&image("stored").path), true),
+            write(PathBuf::from(&image("in-memory").thumbnail_path), true),
+            // Written by the monitor, not in the history yet
+            write(images_dir.join("new.png"), false),
+            write(images_dir.join("key-change.png.old"), true),
+        ];
+        let orphan = write(images_dir.join("dropped.png"), true);
+
+        remove_orphaned_images(&dir, &[in_memory]);
+        for path in &kept {
+            assert!(path.exists(), "{}", path.display());
         }
-        Err(e) => log::error!("Failed to serialize clipboard history: {}", e),
+        assert!(!orphan.exists());
+        let _ = std::fs::remove_dir_all(&dir);
     }
 }
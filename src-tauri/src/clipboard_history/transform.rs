@@ -0,0 +1,186 @@
+use super::entry::ContentType;
+use super::formats::{percent_encode, percent_escape};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// A named text transformation applied when pasting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transform {
+    /// Drop rich formats (HTML, RTF) and paste the text only.
+    Plain,
+    Trim,
+    Upper,
+    Lower,
+    Title,
+    UrlEncode,
+    UrlDecode,
+    Base64Encode,
+    Base64Decode,
+    JsonPretty,
+    JsonMinify,
+}
+
+impl Transform {
+    pub fn apply(self, text: &str) -> Result<String, String> {
+        Ok(match self {
+            Transform::Plain => text.to_string(),
+            Transform::Trim => text.trim().to_string(),
+            Transform::Upper => text.to_uppercase(),
+            Transform::Lower => text.to_lowercase(),
+            Transform::Title => title_case(text),
+            Transform::UrlEncode => percent_encode(text, b""),
+            Transform::UrlDecode => url_decode(text)?,
+            Transform::Base64Encode => STANDARD.encode(text),
+            Transform::Base64Decode => base64_decode(text)?,
+            Transform::JsonPretty => serde_json::to_string_pretty(&parse_json(text)?).map_err(|e| e.to_string())?,
+            Transform::JsonMinify => serde_json::to_string(&parse_json(text)?).map_err(|e| e.to_string())?,
+        })
+    }
+}
+
+/// Run `transforms` over `text` in order.
+pub fn apply_all(text: &str, transforms: &[Transform]) -> Result<String, String> {
+    transforms
+        .iter()
+        .try_fold(text.to_string(), |text, transform| transform.apply(&text))
+}
+
+/// What "paste transformed" does for an entry when no transforms are given.
+pub fn defaults_for(content_type: &ContentType) -> Vec<Transform> {
+    match content_type {
+        ContentType::Json => vec![Transform::JsonPretty],
+        ContentType::Link
+        | ContentType::Email
+        | ContentType::Phone
+        | ContentType::Path
+        | ContentType::Number
+        | ContentType::Color => vec![Transform::Trim],
+        _ => vec![Transform::Plain],
+    }
+}
+
+/// Capitalize the first letter of every word and lowercase the rest.
+fn title_case(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut word_start = true;
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if word_start {
+                out.extend(c.to_uppercase());
+            } else {
+                out.extend(c.to_lowercase());
+            }
+            word_start = false;
+        } else {
+            out.push(c);
+            word_start = c.is_whitespace() || c == '-' || c == '_';
+        }
+    }
+    out
+}
+
+/// Decode `%XX` escapes, and `+` as a space as in form-encoded query strings.
+fn url_decode(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let byte = percent_escape(&bytes[i..]).ok_or_else(|| format!("Invalid escape at position {}", i))?;
+                out.push(byte);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).map_err(|_| "Decoded text is not valid UTF-8".to_string())
+}
+
+/// Decode standard or URL-safe base64, ignoring whitespace and padding.
+fn base64_decode(text: &str) -> Result<String, String> {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = STANDARD
+        .decode(&compact)
+        .or_else(|_| URL_SAFE_NO_PAD.decode(compact.trim_end_matches('=')))
+        .map_err(|e| format!("Invalid base64: {}", e))?;
+    String::from_utf8(bytes).map_err(|_| "Decoded data is not valid UTF-8 text".to_string())
+}
+
+fn parse_json(text: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str(text.trim()).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_transforms() {
+        assert_eq!(Transform::Plain.apply(" <b>x</b> ").unwrap(), " <b>x</b> ");
+        assert_eq!(Transform::Trim.apply("\t a b \n").unwrap(), "a b");
+        assert_eq!(Transform::Upper.apply("straße").unwrap(), "STRASSE");
+        assert_eq!(Transform::Lower.apply("ÉTÉ Ok").unwrap(), "été ok");
+        assert_eq!(
+            Transform::Title.apply("hello wORLD, snake_case-kebab o'neil").unwrap(),
+            "Hello World, Snake_Case-Kebab O'neil"
+        );
+    }
+
+    #[test]
+    fn url_transforms() {
+        assert_eq!(Transform::UrlEncode.apply("a b/ü?x=1&y~_.-").unwrap(), "a%20b%2F%C3%BC%3Fx%3D1%26y~_.-");
+        assert_eq!(Transform::UrlDecode.apply("a%20b%2f%C3%BC+c").unwrap(), "a b/ü c");
+        for text in ["", "100% + 1", "ü/ä?q=\"x\""] {
+            assert_eq!(Transform::UrlDecode.apply(&Transform::UrlEncode.apply(text).unwrap()).unwrap(), text);
+        }
+
+        for (text, position) in [("%", 0), ("a%4", 1), ("ab%zz", 2), ("%+F", 0), ("%-1", 0), ("%%41", 0)] {
+            let error = format!("Invalid escape at position {}", position);
+            assert_eq!(Transform::UrlDecode.apply(text), Err(error), "{}", text);
+        }
+        assert_eq!(Transform::UrlDecode.apply("%C3"), Err("Decoded text is not valid UTF-8".to_string()));
+    }
+
+    #[test]
+    fn base64_transforms() {
+        assert_eq!(Transform::Base64Encode.apply("ü?>").unwrap(), "w7w/Pg==");
+        assert_eq!(Transform::Base64Decode.apply("w7w/Pg==").unwrap(), "ü?>");
+        // URL-safe alphabet, without padding, wrapped over lines
+        assert_eq!(Transform::Base64Decode.apply("w7w_Pg").unwrap(), "ü?>");
+        assert_eq!(Transform::Base64Decode.apply(" w7w/\nPg==\n").unwrap(), "ü?>");
+
+        assert!(Transform::Base64Decode.apply("not base64!").unwrap_err().starts_with("Invalid base64"));
+        assert_eq!(Transform::Base64Decode.apply("/w=="), Err("Decoded data is not valid UTF-8 text".to_string()));
+    }
+
+    #[test]
+    fn json_transforms() {
+        let json = " {\"a\": [1, 2], \"b\": \"ü\"}\n";
+        assert_eq!(Transform::JsonMinify.apply(json).unwrap(), r#"{"a":[1,2],"b":"ü"}"#);
+        let pretty = "{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": \"ü\"\n}";
+        assert_eq!(Transform::JsonPretty.apply(json).unwrap(), pretty);
+
+        for transform in [Transform::JsonPretty, Transform::JsonMinify] {
+            assert!(transform.apply("{\"a\": }").unwrap_err().starts_with("Invalid JSON"));
+            assert!(transform.apply("").unwrap_err().starts_with("Invalid JSON"));
+        }
+    }
+
+    #[test]
+    fn transforms_run_in_order_and_stop_at_the_first_error() {
+        assert_eq!(apply_all(" a b ", &[Transform::Trim, Transform::UrlEncode]).unwrap(), "a%20b");
+        assert_eq!(apply_all(" a b ", &[Transform::UrlEncode, Transform::Trim]).unwrap(), "%20a%20b%20");
+        assert_eq!(apply_all("x", &[]).unwrap(), "x");
+        assert!(apply_all("%zz", &[Transform::UrlDecode, Transform::Upper]).is_err());
+    }
+}
@@ -2,20 +2,48 @@ mod clipboard_history;
 mod installed_apps;
 mod text_expansion;
 
-use clipboard_history::entry::ClipboardEntry;
-use clipboard_history::ClipboardHistoryState;
+/// The parts of the private modules the benches use.
+#[doc(hidden)]
+pub mod bench_support {
+    pub use crate::text_expansion::buffer::KeyBuffer;
+    pub use crate::text_expansion::matcher::{ExpandMode, TriggerDefinition, TriggerMatcher};
+}
+
+use base64::Engine;
+use clipboard_history::collections::Collection;
+use clipboard_history::crypto::{EncryptionStatus, KeySource};
+use clipboard_history::actions::{ActionEffect, ClipboardAction};
+use clipboard_history::entry::{ClipboardEntry, ClipboardFormats, ContentType};
+use clipboard_history::export::{ExportFilter, ExportFormat, ImportSummary};
+use clipboard_history::paste_stack::PasteStackState;
+use clipboard_history::search::{SearchQuery, SearchResults};
+use clipboard_history::settings::ClipboardSettings;
+use clipboard_history::source_app::WindowHandle;
+use clipboard_history::transform::{self, Transform};
+use clipboard_history::{ClipboardHistoryState, ClipboardSettingsState, SUPPRESS_CLIPBOARD_MONITOR};
 use installed_apps::InstalledApp;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
 use tauri::{tray::TrayIconBuilder, Emitter, LogicalSize, Manager};
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
-use text_expansion::TriggerMap;
+use text_expansion::matcher::{TriggerConflict, TriggerMatcher, TriggerSet};
+use text_expansion::snippets::{Snippet, SnippetStore};
+use text_expansion::stats::{SnippetStats, SnippetStatsStore};
+use text_expansion::settings::ExpansionSettings;
+use text_expansion::{ExpansionSettingsState, TriggerMap};
 
 struct CurrentShortcut(Arc<RwLock<Shortcut>>);
 
+/// Global hotkey that pastes the next entry of the paste stack.
+struct PasteStackShortcut(Arc<RwLock<Shortcut>>);
+
+/// The window that had focus before the bar was shown, to paste into.
+struct PreviousWindow(Mutex<Option<WindowHandle>>);
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct UserPreferences {
     blur_radius: Option<f64>,
@@ -28,6 +56,7 @@ fn toggle_window(handle: &tauri::AppHandle) {
         if window.is_visible().unwrap_or(false) {
             let _ = window.hide();
         } else {
+            remember_previous_window(handle);
             let _ = window.set_size(LogicalSize::new(760u32, 510u32));
             let _ = window.emit("window-expanded", ());
             let _ = window.show();
@@ -36,6 +65,15 @@ fn toggle_window(handle: &tauri::AppHandle) {
     }
 }
 
+/// Record the focused window before showing the bar over it.
+fn remember_previous_window(handle: &tauri::AppHandle) {
+    if let Some(previous) = handle.try_state::<PreviousWindow>() {
+        if let Ok(mut previous) = previous.0.lock() {
+            *previous = clipboard_history::source_app::foreground_window();
+        }
+    }
+}
+
 fn parse_modifiers(mods: &[String]) -> Option<Modifiers> {
     let mut result: Option<Modifiers> = None;
     for m in mods {
@@ -104,8 +142,14 @@ fn save_preferences(app_handle: &tauri::AppHandle, prefs: &UserPreferences) -> R
     Ok(())
 }
 
+/// Paste a snippet body. `transforms` run after placeholders are resolved; an
+/// empty list uses the defaults for the resolved text's content type.
 #[tauri::command]
-fn paste_snippet(app_handle: tauri::AppHandle, body: String) -> Result<(), String> {
+fn paste_snippet(
+    app_handle: tauri::AppHandle,
+    body: String,
+    transforms: Option<Vec<Transform>>,
+) -> Result<(), String> {
     // Hide the window first
     if let Some(window) = app_handle.get_webview_window("main") {
         let _ = window.hide();
@@ -115,7 +159,20 @@ fn paste_snippet(app_handle: tauri::AppHandle, body: String) -> Result<(), Strin
     std::thread::spawn(move || {
         std::thread::sleep(std::time::Duration::from_millis(150));
         let resolved = text_expansion::placeholder::resolve(&body);
-        text_expansion::injector::inject_snippet(0, &resolved);
+        let resolved = match transforms {
+            Some(transforms) => {
+                let transforms = or_defaults(transforms, || clipboard_history::classifier::classify(&resolved).content_type);
+                match transform::apply_all(&resolved, &transforms) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        log::error!("Failed to transform snippet: {}", e);
+                        return;
+                    }
+                }
+            }
+            None => resolved,
+        };
+        text_expansion::injector::inject_snippet(0, &resolved, None);
     });
 
     Ok(())
@@ -129,18 +186,134 @@ fn open_link(app_handle: tauri::AppHandle, url: String) -> Result<(), String> {
     open::that(&url).map_err(|e| e.to_string())
 }
 
+/// Replace the triggers and report those that conflict with each other, so
+/// the UI can warn about them.
 #[tauri::command]
 fn sync_triggers(
     trigger_map: tauri::State<'_, TriggerMap>,
-    triggers: HashMap<String, String>,
+    triggers: TriggerSet,
+) -> Result<Vec<TriggerConflict>, String> {
+    let triggers = triggers.into_triggers()?;
+    let mut matcher = trigger_map.0.write().map_err(|e| e.to_string())?;
+    *matcher = TriggerMatcher::new(triggers);
+    let conflicts = matcher.conflicts();
+    log::info!(
+        "Trigger map synced – {} entries, {} conflicts",
+        matcher.len(),
+        conflicts.len()
+    );
+    Ok(conflicts)
+}
+
+#[tauri::command]
+fn get_snippets(store: tauri::State<'_, SnippetStore>) -> Result<Vec<Snippet>, String> {
+    let snippets = store.0.read().map_err(|e| e.to_string())?;
+    Ok(snippets.clone())
+}
+
+/// Replace the stored snippets after the frontend changed them.
+#[tauri::command]
+fn save_snippets(
+    store: tauri::State<'_, SnippetStore>,
+    stats: tauri::State<'_, SnippetStatsStore>,
+    app_handle: tauri::AppHandle,
+    snippets: Vec<Snippet>,
+) -> Result<(), String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let mut stored = store.0.write().map_err(|e| e.to_string())?;
+    text_expansion::snippets::save(&dir, &snippets)?;
+    *stored = snippets;
+
+    let mut stats = stats.0.lock().map_err(|e| e.to_string())?;
+    if text_expansion::stats::prune(&mut stats, &stored) {
+        text_expansion::stats::save(&dir, &stats)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_expansion_settings(
+    settings: tauri::State<'_, ExpansionSettingsState>,
+) -> Result<ExpansionSettings, String> {
+    let settings = settings.0.read().map_err(|e| e.to_string())?;
+    Ok(settings.clone())
+}
+
+#[tauri::command]
+fn set_expansion_settings(
+    settings_state: tauri::State<'_, ExpansionSettingsState>,
+    app_handle: tauri::AppHandle,
+    settings: ExpansionSettings,
 ) -> Result<(), String> {
-    let mut map = trigger_map.0.write().map_err(|e| e.to_string())?;
-    map.clear();
-    map.extend(triggers);
-    log::info!("Trigger map synced – {} entries", map.len());
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    text_expansion::settings::save(&dir, &settings)?;
+    *settings_state.0.write().map_err(|e| e.to_string())? = settings;
     Ok(())
 }
 
+/// Whether snippets can be limited to apps, i.e. the foreground app can be
+/// identified (not on Wayland).
+#[tauri::command]
+fn can_scope_snippets_to_apps() -> bool {
+    clipboard_history::source_app::can_identify_foreground_app()
+}
+
+/// How often each snippet was expanded, by snippet id.
+#[tauri::command]
+fn get_snippet_stats(stats: tauri::State<'_, SnippetStatsStore>) -> Result<HashMap<String, SnippetStats>, String> {
+    let stats = stats.0.lock().map_err(|e| e.to_string())?;
+    Ok(stats.clone())
+}
+
+/// Turn a clipboard entry into a snippet expanding `keyword`. The trigger
+/// works right away, without waiting for the frontend to sync.
+#[tauri::command]
+fn promote_clipboard_entry(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    store: tauri::State<'_, SnippetStore>,
+    trigger_map: tauri::State<'_, TriggerMap>,
+    app_handle: tauri::AppHandle,
+    id: String,
+    keyword: String,
+    name: Option<String>,
+) -> Result<Snippet, String> {
+    let entry = {
+        let entries = state.0.read().map_err(|e| e.to_string())?;
+        entries.iter().find(|e| e.id == id).cloned().ok_or("Entry not found")?
+    };
+    if entry.image.is_some() || entry.content.is_empty() {
+        return Err("Only text entries can become snippets".into());
+    }
+
+    let keyword = keyword.trim().to_string();
+    let mut snippets = store.0.write().map_err(|e| e.to_string())?;
+    text_expansion::snippets::validate_keyword(&keyword, &snippets)?;
+    let name = name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| entry.preview.lines().next().unwrap_or_default().chars().take(40).collect());
+    let icon = match entry.content_type {
+        ContentType::Code | ContentType::Json => "Code",
+        _ => "FileText",
+    };
+    let snippet = Snippet::new(name, icon, keyword, entry.content);
+
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    snippets.push(snippet.clone());
+    if let Err(e) = text_expansion::snippets::save(&dir, &snippets) {
+        snippets.pop();
+        return Err(e);
+    }
+    trigger_map
+        .0
+        .write()
+        .map_err(|e| e.to_string())?
+        .insert(snippet.trigger());
+    let _ = app_handle.emit("snippets-changed", &*snippets);
+    log::info!("Promoted clipboard entry to snippet {}", snippet.keyword);
+    Ok(snippet)
+}
+
 #[tauri::command]
 fn change_shortcut(
     app_handle: tauri::AppHandle,
@@ -148,14 +321,37 @@ fn change_shortcut(
     modifiers: Vec<String>,
     key: String,
 ) -> Result<(), String> {
-    let old = *current_shortcut.0.read().map_err(|e| e.to_string())?;
-    app_handle
-        .global_shortcut()
-        .unregister(old)
-        .map_err(|e| e.to_string())?;
+    replace_shortcut(&app_handle, &current_shortcut.0, &modifiers, &key)
+}
+
+#[tauri::command]
+fn change_paste_stack_shortcut(
+    app_handle: tauri::AppHandle,
+    paste_stack_shortcut: tauri::State<'_, PasteStackShortcut>,
+    modifiers: Vec<String>,
+    key: String,
+) -> Result<(), String> {
+    replace_shortcut(&app_handle, &paste_stack_shortcut.0, &modifiers, &key)
+}
 
-    let mods = parse_modifiers(&modifiers);
-    let code = parse_key(&key).ok_or_else(|| format!("Unsupported key: {key}"))?;
+/// Unregister the shortcut in `current` and register the new one in its place.
+fn replace_shortcut(
+    app_handle: &tauri::AppHandle,
+    current: &RwLock<Shortcut>,
+    modifiers: &[String],
+    key: &str,
+) -> Result<(), String> {
+    let old = *current.read().map_err(|e| e.to_string())?;
+    // The old one may never have registered, e.g. if another app owned it
+    if app_handle.global_shortcut().is_registered(old) {
+        app_handle
+            .global_shortcut()
+            .unregister(old)
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mods = parse_modifiers(modifiers);
+    let code = parse_key(key).ok_or_else(|| format!("Unsupported key: {key}"))?;
     let new_shortcut = Shortcut::new(mods, code);
 
     app_handle
@@ -163,7 +359,7 @@ fn change_shortcut(
         .register(new_shortcut)
         .map_err(|e| e.to_string())?;
 
-    *current_shortcut.0.write().map_err(|e| e.to_string())? = new_shortcut;
+    *current.write().map_err(|e| e.to_string())? = new_shortcut;
     Ok(())
 }
 
@@ -184,9 +380,12 @@ fn delete_clipboard_entry(
     id: String,
 ) -> Result<(), String> {
     let mut entries = state.0.write().map_err(|e| e.to_string())?;
+    if let Some(image) = entries.iter().find(|e| e.id == id).and_then(|e| e.image.as_ref()) {
+        clipboard_history::images::delete_files(image);
+    }
     entries.retain(|e| e.id != id);
     if let Some(dir) = app_handle.path().app_data_dir().ok() {
-        clipboard_history::storage::save(&dir, &entries);
+        clipboard_history::storage::delete(&dir, &id);
     }
     Ok(())
 }
@@ -197,6 +396,9 @@ fn clear_clipboard_history(
     app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
     let mut entries = state.0.write().map_err(|e| e.to_string())?;
+    for image in entries.iter().filter_map(|e| e.image.as_ref()) {
+        clipboard_history::images::delete_files(image);
+    }
     entries.clear();
     if let Some(dir) = app_handle.path().app_data_dir().ok() {
         clipboard_history::storage::save(&dir, &entries);
@@ -213,13 +415,491 @@ fn toggle_clipboard_pin(
     let mut entries = state.0.write().map_err(|e| e.to_string())?;
     if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
         entry.pinned = !entry.pinned;
+        // Pinning a secret that was due to expire keeps it (and stores it).
+        if entry.pinned {
+            entry.expires_at = None;
+        }
+        if let Some(dir) = app_handle.path().app_data_dir().ok() {
+            clipboard_history::storage::update(&dir, entry);
+        }
     }
-    if let Some(dir) = app_handle.path().app_data_dir().ok() {
-        clipboard_history::storage::save(&dir, &entries);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_clipboard_collections(app_handle: tauri::AppHandle) -> Result<Vec<Collection>, String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    clipboard_history::storage::load_collections(&dir)
+}
+
+#[tauri::command]
+fn create_clipboard_collection(app_handle: tauri::AppHandle, name: String) -> Result<Collection, String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let collections = clipboard_history::storage::load_collections(&dir)?;
+    let name = clipboard_history::collections::validate_name(&name, &collections, None)?;
+    let collection = Collection::new(name);
+    clipboard_history::storage::save_collection(&dir, &collection)?;
+    emit_collections(&app_handle, &dir);
+    Ok(collection)
+}
+
+#[tauri::command]
+fn rename_clipboard_collection(app_handle: tauri::AppHandle, id: String, name: String) -> Result<(), String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let collections = clipboard_history::storage::load_collections(&dir)?;
+    let name = clipboard_history::collections::validate_name(&name, &collections, Some(&id))?;
+    let mut collection = collections
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or("Collection not found")?;
+    collection.name = name;
+    clipboard_history::storage::save_collection(&dir, &collection)?;
+    emit_collections(&app_handle, &dir);
+    Ok(())
+}
+
+/// Delete a collection. Its entries stay in the history, subject to the
+/// retention policy again.
+#[tauri::command]
+fn delete_clipboard_collection(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    settings_state: tauri::State<'_, ClipboardSettingsState>,
+    app_handle: tauri::AppHandle,
+    id: String,
+) -> Result<(), String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let mut entries = state.0.write().map_err(|e| e.to_string())?;
+    clipboard_history::storage::delete_collection(&dir, &id)?;
+    emit_collections(&app_handle, &dir);
+    for entry in entries.iter_mut().filter(|e| e.collection.as_deref() == Some(id.as_str())) {
+        entry.collection = None;
+    }
+    let retention = settings_state.0.read().map_err(|e| e.to_string())?.retention.clone();
+    clipboard_history::retention::apply(&app_handle, &dir, &retention, &mut entries);
+    let _ = app_handle.emit("clipboard-history-reloaded", &*entries);
+    Ok(())
+}
+
+/// Send the collections to the frontend after they changed.
+fn emit_collections(app_handle: &tauri::AppHandle, app_data_dir: &PathBuf) {
+    match clipboard_history::storage::load_collections(app_data_dir) {
+        Ok(collections) => {
+            let _ = app_handle.emit("clipboard-collections-changed", collections);
+        }
+        Err(e) => log::error!("Failed to load clipboard collections: {}", e),
+    }
+}
+
+/// File entries in a collection, or take them out of theirs with `None`.
+#[tauri::command]
+fn move_clipboard_entries(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    settings_state: tauri::State<'_, ClipboardSettingsState>,
+    app_handle: tauri::AppHandle,
+    ids: Vec<String>,
+    collection: Option<String>,
+) -> Result<(), String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    if let Some(id) = &collection {
+        if !clipboard_history::storage::load_collections(&dir)?.iter().any(|c| &c.id == id) {
+            return Err("Collection not found".into());
+        }
+    }
+    let mut entries = state.0.write().map_err(|e| e.to_string())?;
+    let moved = clipboard_history::collections::move_entries(&mut entries, &ids, collection.as_deref());
+    clipboard_history::storage::insert_many(&dir, &moved);
+    // Entries taken out of a collection may be over the limits now
+    if collection.is_none() {
+        let retention = settings_state.0.read().map_err(|e| e.to_string())?.retention.clone();
+        clipboard_history::retention::apply(&app_handle, &dir, &retention, &mut entries);
+    }
+    Ok(())
+}
+
+/// Replace an entry's tags. Returns them as stored.
+#[tauri::command]
+fn set_clipboard_entry_tags(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    app_handle: tauri::AppHandle,
+    id: String,
+    tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let tags = clipboard_history::collections::normalize_tags(&tags)?;
+    let mut entries = state.0.write().map_err(|e| e.to_string())?;
+    let entry = entries.iter_mut().find(|e| e.id == id).ok_or("Entry not found")?;
+    entry.tags = tags.clone();
+    // Entries only kept in memory aren't stored
+    if entry.expires_at.is_none() {
+        let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+        clipboard_history::storage::update(&dir, entry);
+    }
+    Ok(tags)
+}
+
+#[tauri::command]
+fn search_clipboard_history(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    app_handle: tauri::AppHandle,
+    query: SearchQuery,
+) -> Result<SearchResults, String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let entries = state.0.read().map_err(|e| e.to_string())?;
+    clipboard_history::search::search(&dir, &query, &entries)
+}
+
+#[tauri::command]
+fn get_clipboard_settings(
+    settings: tauri::State<'_, ClipboardSettingsState>,
+) -> Result<ClipboardSettings, String> {
+    let settings = settings.0.read().map_err(|e| e.to_string())?;
+    Ok(settings.clone())
+}
+
+/// Save new settings and apply the retention policy right away.
+#[tauri::command]
+fn set_clipboard_settings(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    settings_state: tauri::State<'_, ClipboardSettingsState>,
+    app_handle: tauri::AppHandle,
+    mut settings: ClipboardSettings,
+) -> Result<(), String> {
+    settings.compile()?;
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    clipboard_history::settings::save(&dir, &settings)?;
+
+    let mut entries = state.0.write().map_err(|e| e.to_string())?;
+    clipboard_history::retention::apply(&app_handle, &dir, &settings.retention, &mut entries);
+    *settings_state.0.write().map_err(|e| e.to_string())? = settings;
+    Ok(())
+}
+
+/// Put an entry back on the system clipboard without recording it again.
+#[tauri::command]
+fn copy_clipboard_entry(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    app_handle: tauri::AppHandle,
+    id: String,
+) -> Result<(), String> {
+    let (entry, image) = clipboard_entry_contents(&state, &app_handle, &id)?;
+
+    std::thread::spawn(move || {
+        SUPPRESS_CLIPBOARD_MONITOR.store(true, Ordering::SeqCst);
+        match image {
+            Some(png) => text_expansion::injector::set_clipboard_image(&png),
+            None => text_expansion::injector::set_clipboard_contents(&entry.content, &entry.formats),
+        }
+        std::thread::sleep(std::time::Duration::from_millis(150));
+        SUPPRESS_CLIPBOARD_MONITOR.store(false, Ordering::SeqCst);
+    });
+    Ok(())
+}
+
+/// Paste an entry into the application that had focus before the bar opened.
+/// With `transforms` the entry is pasted as transformed plain text; an empty
+/// list uses the defaults for the entry's content type.
+#[tauri::command]
+fn paste_clipboard_entry(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    previous: tauri::State<'_, PreviousWindow>,
+    app_handle: tauri::AppHandle,
+    id: String,
+    transforms: Option<Vec<Transform>>,
+) -> Result<(), String> {
+    let (mut entry, image) = clipboard_entry_contents(&state, &app_handle, &id)?;
+    if let Some(transforms) = transforms {
+        if image.is_some() {
+            return Err("Images can't be transformed".into());
+        }
+        let transforms = or_defaults(transforms, || entry.content_type.clone());
+        entry.content = transform::apply_all(&entry.content, &transforms)?;
+        entry.formats = Default::default();
+    }
+    paste_into_previous(&app_handle, &previous, entry.content, entry.formats, image)
+}
+
+/// Hide the bar, give focus back to the window it was opened over and paste.
+fn paste_into_previous(
+    app_handle: &tauri::AppHandle,
+    previous: &PreviousWindow,
+    text: String,
+    formats: ClipboardFormats,
+    image: Option<Vec<u8>>,
+) -> Result<(), String> {
+    let target = *previous.0.lock().map_err(|e| e.to_string())?;
+
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.hide();
+    }
+
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(150));
+        // Hiding usually hands focus back already; activating makes sure.
+        if let Some(target) = target {
+            if clipboard_history::source_app::activate_window(target) {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            } else {
+                log::warn!("Could not restore focus to the previous window");
+            }
+        }
+        text_expansion::injector::paste_entry(&text, &formats, image.as_deref());
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn get_clipboard_actions(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    id: String,
+) -> Result<Vec<ClipboardAction>, String> {
+    let entries = state.0.read().map_err(|e| e.to_string())?;
+    let entry = entries
+        .iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| format!("Clipboard entry not found: {id}"))?;
+    Ok(clipboard_history::actions::actions_for(entry))
+}
+
+/// Run one of the entry's actions (see `get_clipboard_actions`). Returns a
+/// message to show, if the action has one.
+#[tauri::command]
+fn run_clipboard_action(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    previous: tauri::State<'_, PreviousWindow>,
+    app_handle: tauri::AppHandle,
+    id: String,
+    action: String,
+) -> Result<Option<String>, String> {
+    let effect = {
+        let entries = state.0.read().map_err(|e| e.to_string())?;
+        let entry = entries
+            .iter()
+            .find(|e| e.id == id)
+            .ok_or_else(|| format!("Clipboard entry not found: {id}"))?;
+        clipboard_history::actions::resolve(entry, &action)?
+    };
+    let hide = || {
+        if let Some(window) = app_handle.get_webview_window("main") {
+            let _ = window.hide();
+        }
+    };
+
+    match effect {
+        ActionEffect::Open(target) => {
+            hide();
+            open::that(&target).map_err(|e| e.to_string())?;
+        }
+        ActionEffect::Reveal(path) => {
+            hide();
+            clipboard_history::actions::reveal(&path)?;
+        }
+        ActionEffect::Copy(text) => {
+            std::thread::spawn(move || {
+                SUPPRESS_CLIPBOARD_MONITOR.store(true, Ordering::SeqCst);
+                text_expansion::injector::set_clipboard_text(&text);
+                std::thread::sleep(std::time::Duration::from_millis(150));
+                SUPPRESS_CLIPBOARD_MONITOR.store(false, Ordering::SeqCst);
+            });
+            return Ok(Some("Copied".into()));
+        }
+        ActionEffect::Paste(text) => {
+            paste_into_previous(&app_handle, &previous, text, ClipboardFormats::default(), None)?;
+        }
+        ActionEffect::Message(message) => return Ok(Some(message)),
+    }
+    Ok(None)
+}
+
+/// `transforms`, or the defaults for the content type if it is empty.
+fn or_defaults(
+    transforms: Vec<Transform>,
+    content_type: impl FnOnce() -> ContentType,
+) -> Vec<Transform> {
+    if transforms.is_empty() {
+        transform::defaults_for(&content_type())
+    } else {
+        transforms
+    }
+}
+
+#[tauri::command]
+fn get_paste_stack(stack: tauri::State<'_, PasteStackState>) -> Result<Vec<String>, String> {
+    let queue = stack.0.lock().map_err(|e| e.to_string())?;
+    Ok(queue.iter().cloned().collect())
+}
+
+/// Queue entries to be pasted one by one with the paste stack hotkey.
+#[tauri::command]
+fn set_paste_stack(
+    stack: tauri::State<'_, PasteStackState>,
+    app_handle: tauri::AppHandle,
+    ids: Vec<String>,
+) -> Result<(), String> {
+    clipboard_history::paste_stack::set(&app_handle, &stack, ids)
+}
+
+/// An entry from the history plus its image bytes, if it is an image.
+fn clipboard_entry_contents(
+    state: &ClipboardHistoryState,
+    app_handle: &tauri::AppHandle,
+    id: &str,
+) -> Result<(ClipboardEntry, Option<Vec<u8>>), String> {
+    let entry = {
+        let entries = state.0.read().map_err(|e| e.to_string())?;
+        entries
+            .iter()
+            .find(|e| e.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Clipboard entry not found: {id}"))?
+    };
+    let image = match &entry.image {
+        Some(image) => {
+            let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+            Some(clipboard_history::images::read(&dir, &image.path)?)
+        }
+        None => None,
+    };
+    Ok((entry, image))
+}
+
+/// Read an image entry file (full size or thumbnail) as base64 PNG. Used
+/// instead of the asset protocol when the files are encrypted.
+#[tauri::command]
+fn read_clipboard_image(app_handle: tauri::AppHandle, path: String) -> Result<String, String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let png = clipboard_history::images::read(&dir, &path)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(png))
+}
+
+#[tauri::command]
+fn get_clipboard_encryption() -> EncryptionStatus {
+    clipboard_history::crypto::status()
+}
+
+/// Turn history encryption on or off, or switch where the key comes from.
+#[tauri::command]
+fn set_clipboard_encryption(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    app_handle: tauri::AppHandle,
+    source: KeySource,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    // Hold the history lock so the monitor can't write while rows are re-encrypted.
+    let mut entries = state.0.write().map_err(|e| e.to_string())?;
+    clipboard_history::crypto::change_key(&dir, source, passphrase.as_deref())?;
+    reload_clipboard_history(&app_handle, &dir, &mut entries);
+    Ok(())
+}
+
+/// Re-encrypt the history with a fresh key from the same source. A new
+/// passphrase is required for passphrase-protected histories.
+#[tauri::command]
+fn rotate_clipboard_key(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    app_handle: tauri::AppHandle,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    let source = clipboard_history::crypto::status().source;
+    if source == KeySource::None {
+        return Err("Clipboard history is not encrypted".into());
     }
+    set_clipboard_encryption(state, app_handle, source, passphrase)
+}
+
+#[tauri::command]
+fn unlock_clipboard_history(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    app_handle: tauri::AppHandle,
+    passphrase: String,
+) -> Result<(), String> {
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    clipboard_history::crypto::unlock(&dir, &passphrase)?;
+    let mut entries = state.0.write().map_err(|e| e.to_string())?;
+    reload_clipboard_history(&app_handle, &dir, &mut entries);
     Ok(())
 }
 
+/// Write the history entries matching `filter` to a file, by default a
+/// timestamped one in the downloads folder. A given `path` must be absolute
+/// and have the format's extension. Returns the file's path.
+#[tauri::command]
+fn export_clipboard_history(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    app_handle: tauri::AppHandle,
+    format: ExportFormat,
+    filter: ExportFilter,
+    path: Option<String>,
+) -> Result<String, String> {
+    if clipboard_history::crypto::is_locked() {
+        return Err("Clipboard history is locked".into());
+    }
+    let path = path.map(PathBuf::from);
+    if let Some(path) = &path {
+        format.check_path(path)?;
+    }
+    let contents = {
+        let entries = state.0.read().map_err(|e| e.to_string())?;
+        clipboard_history::export::export(&entries, format, &filter)?
+    };
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let dir = app_handle.path().download_dir().map_err(|e| e.to_string())?;
+            let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+            dir.join(format!("clipboard-history-{}.{}", stamp, format.extension()))
+        }
+    };
+    fs::write(&path, contents).map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Merge an export into the history; entries already in it are deduplicated
+/// by content hash. The retention policy applies to the result.
+#[tauri::command]
+fn import_clipboard_history(
+    state: tauri::State<'_, ClipboardHistoryState>,
+    settings_state: tauri::State<'_, ClipboardSettingsState>,
+    app_handle: tauri::AppHandle,
+    format: ExportFormat,
+    contents: String,
+) -> Result<ImportSummary, String> {
+    if clipboard_history::crypto::is_locked() {
+        return Err("Clipboard history is locked".into());
+    }
+    let settings = settings_state.0.read().map_err(|e| e.to_string())?.clone();
+    let dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let collections = clipboard_history::storage::load_collections(&dir)?;
+    let imported =
+        clipboard_history::export::parse(&contents, format, &settings.custom_classifiers, &collections)?;
+
+    let mut entries = state.0.write().map_err(|e| e.to_string())?;
+    let (changed, summary) = clipboard_history::export::merge(&mut entries, imported);
+    clipboard_history::storage::insert_many(&dir, &changed);
+    clipboard_history::retention::apply(&app_handle, &dir, &settings.retention, &mut entries);
+    let _ = app_handle.emit("clipboard-history-reloaded", &*entries);
+    log::info!(
+        "Imported clipboard history: {} added, {} merged",
+        summary.added,
+        summary.merged
+    );
+    Ok(summary)
+}
+
+/// Replace the in-memory history with what is stored, keeping entries that
+/// only live in memory, and send it to the frontend.
+fn reload_clipboard_history(
+    app_handle: &tauri::AppHandle,
+    app_data_dir: &PathBuf,
+    entries: &mut Vec<ClipboardEntry>,
+) {
+    let mut reloaded = clipboard_history::storage::load(app_data_dir);
+    reloaded.extend(entries.drain(..).filter(|e| e.expires_at.is_some()));
+    reloaded.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+    clipboard_history::storage::remove_orphaned_images(app_data_dir, &reloaded);
+    *entries = reloaded;
+    let _ = app_handle.emit("clipboard-history-reloaded", &*entries);
+}
+
 // ── Installed Applications Commands ─────────────────────────────────────
 
 #[tauri::command]
@@ -303,13 +983,43 @@ pub fn run() {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             sync_triggers,
+            get_snippet_stats,
+            get_expansion_settings,
+            set_expansion_settings,
+            can_scope_snippets_to_apps,
+            get_snippets,
+            save_snippets,
+            promote_clipboard_entry,
             change_shortcut,
             paste_snippet,
             open_link,
             get_clipboard_history,
+            search_clipboard_history,
             delete_clipboard_entry,
             clear_clipboard_history,
             toggle_clipboard_pin,
+            get_clipboard_collections,
+            create_clipboard_collection,
+            rename_clipboard_collection,
+            delete_clipboard_collection,
+            move_clipboard_entries,
+            set_clipboard_entry_tags,
+            copy_clipboard_entry,
+            paste_clipboard_entry,
+            get_clipboard_actions,
+            run_clipboard_action,
+            get_paste_stack,
+            set_paste_stack,
+            change_paste_stack_shortcut,
+            get_clipboard_settings,
+            set_clipboard_settings,
+            read_clipboard_image,
+            get_clipboard_encryption,
+            set_clipboard_encryption,
+            rotate_clipboard_key,
+            unlock_clipboard_history,
+            export_clipboard_history,
+            import_clipboard_history,
 
             get_installed_apps,
             launch_installed_app,
@@ -322,6 +1032,7 @@ pub fn run() {
                 .tooltip("OhMyCommandBar")
                 .on_tray_icon_event(|tray, event| {
                     if let tauri::tray::TrayIconEvent::Click { .. } = event {
+                        remember_previous_window(tray.app_handle());
                         let window = tray.app_handle().get_webview_window("main").unwrap();
                         let _ = window.set_size(LogicalSize::new(760u32, 510u32));
                         let _ = window.emit("window-expanded", ());
@@ -335,8 +1046,19 @@ pub fn run() {
             let handle = app.handle().clone();
             app.handle().plugin(
                 tauri_plugin_global_shortcut::Builder::new()
-                    .with_handler(move |_app, _shortcut, event| {
-                        if event.state == ShortcutState::Pressed {
+                    .with_handler(move |app, shortcut, event| {
+                        let paste_stack = app
+                            .try_state::<PasteStackShortcut>()
+                            .and_then(|s| s.0.read().ok().map(|s| *s == *shortcut))
+                            .unwrap_or(false);
+                        if paste_stack {
+                            // Paste on release so the hotkey's own key is up
+                            if event.state == ShortcutState::Released {
+                                let stack = app.state::<PasteStackState>();
+                                let history = app.state::<ClipboardHistoryState>();
+                                clipboard_history::paste_stack::paste_next(app, &stack, &history.0);
+                            }
+                        } else if event.state == ShortcutState::Pressed {
                             toggle_window(&handle);
                         }
                     })
@@ -346,22 +1068,50 @@ pub fn run() {
             let default_shortcut = Shortcut::new(Some(Modifiers::CONTROL), Code::KeyK);
             app.global_shortcut().register(default_shortcut)?;
             app.manage(CurrentShortcut(Arc::new(RwLock::new(default_shortcut))));
+            app.manage(PreviousWindow(Mutex::new(None)));
+
+            let paste_stack_shortcut = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::KeyV);
+            if let Err(e) = app.global_shortcut().register(paste_stack_shortcut) {
+                log::warn!("Failed to register paste stack shortcut: {}", e);
+            }
+            app.manage(PasteStackShortcut(Arc::new(RwLock::new(paste_stack_shortcut))));
+            app.manage(PasteStackState(Mutex::new(Default::default())));
+
+            let app_data_dir = app.path().app_data_dir().expect("Failed to get app data dir");
 
-            // Trigger map
-            let trigger_arc = Arc::new(RwLock::new(HashMap::<String, String>::new()));
+            // Trigger map, filled from the stored snippets until the frontend syncs
+            let snippets = text_expansion::snippets::load(&app_data_dir);
+            let trigger_arc = Arc::new(RwLock::new(TriggerMatcher::new(text_expansion::snippets::triggers(&snippets))));
             app.manage(TriggerMap(Arc::clone(&trigger_arc)));
+            app.manage(SnippetStore(RwLock::new(snippets)));
+            let snippet_stats = text_expansion::stats::load(&app_data_dir);
+            app.manage(SnippetStatsStore(Mutex::new(snippet_stats)));
+
+            let expansion_settings = Arc::new(RwLock::new(text_expansion::settings::load(&app_data_dir)));
+            app.manage(ExpansionSettingsState(Arc::clone(&expansion_settings)));
 
             // Text expansion listener
-            text_expansion::listener::start_listener(Arc::clone(&trigger_arc));
+            text_expansion::listener::start_listener(app.handle().clone(), Arc::clone(&trigger_arc), expansion_settings);
 
             // Clipboard history
-            let app_data_dir = app.path().app_data_dir().expect("Failed to get app data dir");
+            clipboard_history::crypto::init(&app_data_dir);
             let history = clipboard_history::storage::load(&app_data_dir);
+            clipboard_history::storage::remove_orphaned_images(&app_data_dir, &history);
             let history_arc = Arc::new(RwLock::new(history));
             app.manage(ClipboardHistoryState(Arc::clone(&history_arc)));
+            let settings = clipboard_history::settings::load(&app_data_dir);
+            let settings_arc = Arc::new(RwLock::new(settings));
+            app.manage(ClipboardSettingsState(Arc::clone(&settings_arc)));
+            clipboard_history::retention::start_timer(
+                app.handle().clone(),
+                Arc::clone(&history_arc),
+                Arc::clone(&settings_arc),
+                app_data_dir.clone(),
+            );
             clipboard_history::monitor::start_monitor(
                 app.handle().clone(),
                 Arc::clone(&history_arc),
+                Arc::clone(&settings_arc),
                 app_data_dir,
             );
 
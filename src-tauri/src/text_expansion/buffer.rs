@@ -1,38 +1,126 @@
 /// A fixed-capacity ring buffer that accumulates the most recent keystrokes.
+/// Once full, each push overwrites the oldest key.
+#[derive(Clone)]
 pub struct KeyBuffer {
     buf: Vec<char>,
-    capacity: usize,
+    /// Index of the oldest key.
+    start: usize,
+    len: usize,
 }
 
 impl KeyBuffer {
     pub fn new(capacity: usize) -> Self {
         Self {
-            buf: Vec::with_capacity(capacity),
-            capacity,
+            buf: vec!['\0'; capacity.max(1)],
+            start: 0,
+            len: 0,
         }
     }
 
     pub fn push(&mut self, ch: char) {
-        if self.buf.len() >= self.capacity {
-            self.buf.remove(0);
+        let capacity = self.buf.len();
+        if self.len == capacity {
+            self.buf[self.start] = ch;
+            self.start = (self.start + 1) % capacity;
+        } else {
+            self.buf[(self.start + self.len) % capacity] = ch;
+            self.len += 1;
         }
-        self.buf.push(ch);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The keys from newest to oldest.
+    pub fn iter_rev(&self) -> impl Iterator<Item = char> + '_ {
+        let capacity = self.buf.len();
+        (0..self.len).map(move |i| self.buf[(self.start + self.len - 1 - i) % capacity])
+    }
+
+    /// The newest `n` keys, oldest first.
+    pub fn tail(&self, n: usize) -> String {
+        let mut keys: Vec<char> = self.iter_rev().take(n).collect();
+        keys.reverse();
+        keys.into_iter().collect()
     }
 
     pub fn ends_with(&self, trigger: &str) -> bool {
-        let trigger_chars: Vec<char> = trigger.chars().collect();
-        if trigger_chars.len() > self.buf.len() {
-            return false;
-        }
-        let start = self.buf.len() - trigger_chars.len();
-        self.buf[start..] == trigger_chars[..]
+        let mut keys = self.iter_rev();
+        trigger.chars().rev().all(|ch| keys.next() == Some(ch))
     }
 
     pub fn clear(&mut self) {
-        self.buf.clear();
+        self.start = 0;
+        self.len = 0;
     }
 
     pub fn backspace(&mut self) {
-        self.buf.pop();
+        self.len = self.len.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(capacity: usize, text: &str) -> KeyBuffer {
+        let mut keys = KeyBuffer::new(capacity);
+        text.chars().for_each(|ch| keys.push(ch));
+        keys
+    }
+
+    #[test]
+    fn keeps_the_newest_keys_once_full() {
+        let keys = buffer(3, "abcde");
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys.iter_rev().collect::<String>(), "edc");
+        assert_eq!(keys.tail(3), "cde");
+        assert_eq!(keys.tail(10), "cde");
+        assert!(keys.ends_with("de"));
+        assert!(keys.ends_with("cde"));
+        assert!(!keys.ends_with("bcde"));
+    }
+
+    #[test]
+    fn wraps_around_repeatedly() {
+        let mut keys = buffer(4, "");
+        for (i, ch) in ('a'..='z').enumerate() {
+            keys.push(ch);
+            let expected: String = ('a'..=ch).skip((i + 1).saturating_sub(4)).collect();
+            assert_eq!(keys.tail(4), expected);
+        }
+    }
+
+    #[test]
+    fn backspace_and_push_after_wrapping() {
+        let mut keys = buffer(3, "abcd");
+        keys.backspace();
+        assert_eq!(keys.tail(3), "bc");
+        keys.push('x');
+        assert_eq!(keys.tail(3), "bcx");
+        keys.push('y');
+        assert_eq!(keys.tail(3), "cxy");
+        keys.backspace();
+        keys.backspace();
+        keys.backspace();
+        keys.backspace();
+        assert!(keys.is_empty());
+        assert!(!keys.ends_with("y"));
+    }
+
+    #[test]
+    fn clear_after_wrapping() {
+        let mut keys = buffer(2, "abc");
+        keys.clear();
+        assert!(keys.is_empty());
+        keys.push('z');
+        assert_eq!(keys.tail(2), "z");
+        assert!(keys.ends_with("z"));
+        assert!(keys.ends_with(""));
     }
 }
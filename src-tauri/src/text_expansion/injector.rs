@@ -1,11 +1,17 @@
+use crate::clipboard_history::entry::ClipboardFormats;
+#[cfg(target_os = "windows")]
+use crate::clipboard_history::{formats, images};
+#[cfg(target_os = "linux")]
+use crate::clipboard_history::linux;
 use crate::clipboard_history::SUPPRESS_CLIPBOARD_MONITOR;
 use enigo::{Direction, Enigo, Key, Keyboard, Settings};
 use std::sync::atomic::Ordering;
 use std::thread;
 use std::time::Duration;
 
-/// Inject a snippet to replace the trigger text the user just typed.
-pub fn inject_snippet(trigger_len: usize, content: &str) {
+/// Inject a snippet to replace the trigger text the user just typed, then
+/// press `then`, e.g. to put back a delimiter erased with the trigger.
+pub fn inject_snippet(trigger_len: usize, content: &str, then: Option<Key>) {
     thread::sleep(Duration::from_millis(30));
 
     let mut enigo = match Enigo::new(&Settings::default()) {
@@ -37,16 +43,16 @@ pub fn inject_snippet(trigger_len: usize, content: &str) {
     thread::sleep(Duration::from_millis(50));
 
     // Step 4: Paste via Ctrl+V
-    if let Err(e) = enigo.key(Key::Control, Direction::Press) {
-        log::error!("Ctrl press failed: {}", e);
+    if !press_paste(&mut enigo) {
         SUPPRESS_CLIPBOARD_MONITOR.store(false, Ordering::SeqCst);
         return;
     }
-    if let Err(e) = enigo.key(Key::Unicode('v'), Direction::Click) {
-        log::error!("V click failed: {}", e);
-    }
-    if let Err(e) = enigo.key(Key::Control, Direction::Release) {
-        log::error!("Ctrl release failed: {}", e);
+
+    if let Some(key) = then {
+        thread::sleep(Duration::from_millis(30));
+        if let Err(e) = enigo.key(key, Direction::Click) {
+            log::error!("{:?} click failed: {}", key, e);
+        }
     }
 
     // Step 5: Restore original clipboard after a delay
@@ -60,6 +66,63 @@ pub fn inject_snippet(trigger_len: usize, content: &str) {
     SUPPRESS_CLIPBOARD_MONITOR.store(false, Ordering::SeqCst);
 }
 
+/// Put a clipboard history entry (text with its rich formats, or a PNG
+/// image) on the clipboard and paste it into the focused window. Unlike
+/// `inject_snippet`, the entry stays on the clipboard afterwards.
+pub fn paste_entry(text: &str, formats: &ClipboardFormats, png: Option<&[u8]>) {
+    let mut enigo = match Enigo::new(&Settings::default()) {
+        Ok(e) => e,
+        Err(err) => {
+            log::error!("Failed to create Enigo instance: {}", err);
+            return;
+        }
+    };
+
+    SUPPRESS_CLIPBOARD_MONITOR.store(true, Ordering::SeqCst);
+    match png {
+        Some(png) => set_clipboard_image(png),
+        None => set_clipboard_contents(text, formats),
+    }
+    thread::sleep(Duration::from_millis(50));
+    press_paste(&mut enigo);
+
+    // Let the target read the clipboard before the monitor resumes
+    thread::sleep(Duration::from_millis(150));
+    SUPPRESS_CLIPBOARD_MONITOR.store(false, Ordering::SeqCst);
+}
+
+/// Release Shift, Alt and Meta, which may still be held from a global hotkey
+/// and would combine with the keys we send.
+pub fn release_modifiers() {
+    let mut enigo = match Enigo::new(&Settings::default()) {
+        Ok(e) => e,
+        Err(err) => {
+            log::error!("Failed to create Enigo instance: {}", err);
+            return;
+        }
+    };
+    for key in [Key::Shift, Key::Alt, Key::Meta] {
+        if let Err(e) = enigo.key(key, Direction::Release) {
+            log::error!("Modifier release failed: {}", e);
+        }
+    }
+}
+
+/// Send Ctrl+V. Returns `false` if the key press couldn't be sent.
+fn press_paste(enigo: &mut Enigo) -> bool {
+    if let Err(e) = enigo.key(Key::Control, Direction::Press) {
+        log::error!("Ctrl press failed: {}", e);
+        return false;
+    }
+    if let Err(e) = enigo.key(Key::Unicode('v'), Direction::Click) {
+        log::error!("V click failed: {}", e);
+    }
+    if let Err(e) = enigo.key(Key::Control, Direction::Release) {
+        log::error!("Ctrl release failed: {}", e);
+    }
+    true
+}
+
 // ---------------------------------------------------------------------------
 // Clipboard helpers – Windows
 // ---------------------------------------------------------------------------
@@ -96,7 +159,7 @@ pub fn get_clipboard_text() -> Option<String> {
 }
 
 #[cfg(target_os = "windows")]
-fn set_clipboard_text(text: &str) {
+pub fn set_clipboard_text(text: &str) {
     use std::ffi::OsStr;
     use std::os::windows::ffi::OsStrExt;
 
@@ -129,20 +192,281 @@ fn set_clipboard_text(text: &str) {
     }
 }
 
-#[cfg(not(target_os = "windows"))]
+/// Read the clipboard image as PNG, preferring the registered "PNG" format
+/// (keeps transparency) over `CF_DIB`.
+#[cfg(target_os = "windows")]
+pub fn get_clipboard_image() -> Option<Vec<u8>> {
+    unsafe {
+        if OpenClipboard(std::ptr::null_mut()) == 0 {
+            return None;
+        }
+        let png_format = png_clipboard_format();
+        let png = if png_format != 0 && IsClipboardFormatAvailable(png_format) != 0 {
+            read_global(png_format)
+        } else {
+            None
+        };
+        let png = png.or_else(|| {
+            if IsClipboardFormatAvailable(CF_DIB) == 0 {
+                return None;
+            }
+            read_global(CF_DIB).and_then(|dib| images::dib_to_png(&dib))
+        });
+        CloseClipboard();
+        png
+    }
+}
+
+/// Put a PNG image on the clipboard as both "PNG" and `CF_DIB`.
+#[cfg(target_os = "windows")]
+pub fn set_clipboard_image(png: &[u8]) {
+    unsafe {
+        if OpenClipboard(std::ptr::null_mut()) == 0 {
+            return;
+        }
+        EmptyClipboard();
+        let png_format = png_clipboard_format();
+        if png_format != 0 {
+            set_global(png_format, png);
+        }
+        if let Some(dib) = images::png_to_dib(png) {
+            set_global(CF_DIB, &dib);
+        }
+        CloseClipboard();
+    }
+}
+
+/// Read the HTML, RTF and file list representations on the clipboard.
+#[cfg(target_os = "windows")]
+pub fn get_clipboard_formats() -> ClipboardFormats {
+    let mut result = ClipboardFormats::default();
+    unsafe {
+        if OpenClipboard(std::ptr::null_mut()) == 0 {
+            return result;
+        }
+        let html_format = registered_clipboard_format("HTML Format");
+        if html_format != 0 && IsClipboardFormatAvailable(html_format) != 0 {
+            result.html = read_global(html_format).and_then(|data| formats::cf_html_to_fragment(&data));
+        }
+        let rtf_format = registered_clipboard_format("Rich Text Format");
+        if rtf_format != 0 && IsClipboardFormatAvailable(rtf_format) != 0 {
+            result.rtf = read_global(rtf_format).map(|data| {
+                let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                String::from_utf8_lossy(&data[..end]).into_owned()
+            });
+        }
+        if IsClipboardFormatAvailable(CF_HDROP) != 0 {
+            result.files = read_global(CF_HDROP)
+                .map(|data| formats::hdrop_to_paths(&data))
+                .unwrap_or_default();
+        }
+        CloseClipboard();
+    }
+    result
+}
+
+/// Replace the clipboard with `text` plus every representation in `formats`.
+#[cfg(target_os = "windows")]
+pub fn set_clipboard_contents(text: &str, formats: &ClipboardFormats) {
+    unsafe {
+        if OpenClipboard(std::ptr::null_mut()) == 0 {
+            return;
+        }
+        EmptyClipboard();
+        if !text.is_empty() {
+            let wide: Vec<u8> = text
+                .encode_utf16()
+                .chain(std::iter::once(0))
+                .flat_map(u16::to_le_bytes)
+                .collect();
+            set_global(CF_UNICODETEXT, &wide);
+        }
+        if let Some(html) = &formats.html {
+            let html_format = registered_clipboard_format("HTML Format");
+            if html_format != 0 {
+                set_global(html_format, &formats::fragment_to_cf_html(html));
+            }
+        }
+        if let Some(rtf) = &formats.rtf {
+            let rtf_format = registered_clipboard_format("Rich Text Format");
+            if rtf_format != 0 {
+                let mut data = rtf.as_bytes().to_vec();
+                data.push(0);
+                set_global(rtf_format, &data);
+            }
+        }
+        if !formats.files.is_empty() {
+            set_global(CF_HDROP, &formats::paths_to_hdrop(&formats.files));
+        }
+        CloseClipboard();
+    }
+}
+
+/// Whether the application that set the clipboard asked monitors to leave it
+/// out of history (password managers set these formats alongside the text).
+#[cfg(target_os = "windows")]
+pub fn clipboard_excluded_from_history() -> bool {
+    let marked = |name: &str| {
+        let format = registered_clipboard_format(name);
+        format != 0 && unsafe { IsClipboardFormatAvailable(format) } != 0
+    };
+    if marked("ExcludeClipboardContentFromMonitorProcessing") || marked("Clipboard Viewer Ignore") {
+        return true;
+    }
+
+    // "CanIncludeInClipboardHistory" holds a DWORD; 0 opts out.
+    let format = registered_clipboard_format("CanIncludeInClipboardHistory");
+    if format == 0 || unsafe { IsClipboardFormatAvailable(format) } == 0 {
+        return false;
+    }
+    unsafe {
+        if OpenClipboard(std::ptr::null_mut()) == 0 {
+            return false;
+        }
+        let data = read_global(format);
+        CloseClipboard();
+        data.is_some_and(|data| data.len() >= 4 && data[..4] == [0, 0, 0, 0])
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn png_clipboard_format() -> u32 {
+    registered_clipboard_format("PNG")
+}
+
+#[cfg(target_os = "windows")]
+fn registered_clipboard_format(name: &str) -> u32 {
+    let name: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe { RegisterClipboardFormatW(name.as_ptr()) }
+}
+
+/// Copy the global memory behind a clipboard format. The clipboard must be open.
+#[cfg(target_os = "windows")]
+unsafe fn read_global(format: u32) -> Option<Vec<u8>> {
+    let handle = GetClipboardData(format);
+    if handle.is_null() {
+        return None;
+    }
+    let ptr = GlobalLock(handle) as *const u8;
+    if ptr.is_null() {
+        return None;
+    }
+    let data = std::slice::from_raw_parts(ptr, GlobalSize(handle)).to_vec();
+    GlobalUnlock(handle);
+    Some(data)
+}
+
+/// Hand a copy of `data` to the clipboard under `format`. The clipboard must
+/// be open and emptied.
+#[cfg(target_os = "windows")]
+unsafe fn set_global(format: u32, data: &[u8]) {
+    let hmem = GlobalAlloc(GMEM_MOVEABLE, data.len());
+    if hmem.is_null() {
+        return;
+    }
+    let dst = GlobalLock(hmem) as *mut u8;
+    if dst.is_null() {
+        GlobalFree(hmem);
+        return;
+    }
+    std::ptr::copy_nonoverlapping(data.as_ptr(), dst, data.len());
+    GlobalUnlock(hmem);
+    if SetClipboardData(format, hmem).is_null() {
+        GlobalFree(hmem);
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 pub fn get_clipboard_text() -> Option<String> {
     None
 }
 
-#[cfg(not(target_os = "windows"))]
-fn set_clipboard_text(_text: &str) {}
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn set_clipboard_text(_text: &str) {}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn get_clipboard_image() -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn set_clipboard_image(_png: &[u8]) {}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn get_clipboard_formats() -> ClipboardFormats {
+    ClipboardFormats::default()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn set_clipboard_contents(text: &str, _formats: &ClipboardFormats) {
+    set_clipboard_text(text);
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn clipboard_excluded_from_history() -> bool {
+    false
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_primary_selection_text() -> Option<String> {
+    None
+}
+
+// ---------------------------------------------------------------------------
+// Clipboard helpers – Linux (X11 selections / Wayland data-control)
+// ---------------------------------------------------------------------------
+
+#[cfg(target_os = "linux")]
+pub fn get_clipboard_text() -> Option<String> {
+    linux::read_text(linux::Selection::Clipboard)
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_clipboard_text(text: &str) {
+    linux::write_text(linux::Selection::Clipboard, text);
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_clipboard_image() -> Option<Vec<u8>> {
+    linux::read_image(linux::Selection::Clipboard)
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_clipboard_image(png: &[u8]) {
+    linux::write_image(linux::Selection::Clipboard, png);
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_clipboard_formats() -> ClipboardFormats {
+    linux::read_formats(linux::Selection::Clipboard)
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_clipboard_contents(text: &str, formats: &ClipboardFormats) {
+    linux::write_contents(linux::Selection::Clipboard, text, formats);
+}
+
+#[cfg(target_os = "linux")]
+pub fn clipboard_excluded_from_history() -> bool {
+    linux::is_marked_secret(linux::Selection::Clipboard)
+}
+
+/// Text in the PRIMARY selection, i.e. whatever is currently highlighted.
+#[cfg(target_os = "linux")]
+pub fn get_primary_selection_text() -> Option<String> {
+    linux::read_text(linux::Selection::Primary)
+}
 
 // ---------------------------------------------------------------------------
 // Win32 FFI bindings
 // ---------------------------------------------------------------------------
 #[cfg(target_os = "windows")]
+const CF_DIB: u32 = 8;
+#[cfg(target_os = "windows")]
 const CF_UNICODETEXT: u32 = 13;
 #[cfg(target_os = "windows")]
+const CF_HDROP: u32 = 15;
+#[cfg(target_os = "windows")]
 const GMEM_MOVEABLE: u32 = 0x0002;
 
 #[cfg(target_os = "windows")]
@@ -151,9 +475,12 @@ extern "system" {
     fn CloseClipboard() -> i32;
     fn EmptyClipboard() -> i32;
     fn GetClipboardData(format: u32) -> *mut std::ffi::c_void;
+    fn IsClipboardFormatAvailable(format: u32) -> i32;
+    fn RegisterClipboardFormatW(name: *const u16) -> u32;
     fn SetClipboardData(format: u32, hmem: *mut std::ffi::c_void) -> *mut std::ffi::c_void;
     fn GlobalAlloc(flags: u32, bytes: usize) -> *mut std::ffi::c_void;
     fn GlobalLock(hmem: *mut std::ffi::c_void) -> *mut std::ffi::c_void;
     fn GlobalUnlock(hmem: *mut std::ffi::c_void) -> i32;
     fn GlobalFree(hmem: *mut std::ffi::c_void) -> *mut std::ffi::c_void;
+    fn GlobalSize(hmem: *mut std::ffi::c_void) -> usize;
 }
@@ -1,45 +1,46 @@
 use super::buffer::KeyBuffer;
 use super::injector;
+use super::matcher::{ExpandMode, TriggerMatcher};
 use super::placeholder;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use super::settings::ExpansionSettings;
+use super::stats;
+use crate::clipboard_history::source_app;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 
 /// Spawn a background thread that listens for global key events via `rdev`.
-pub fn start_listener(trigger_map: Arc<RwLock<HashMap<String, String>>>) {
+///
+/// Keys typed into password fields are seen like any others, so snippets
+/// expand there too; deny the password manager's app to avoid it.
+pub fn start_listener(
+    app_handle: tauri::AppHandle,
+    trigger_map: Arc<RwLock<TriggerMatcher>>,
+    settings: Arc<RwLock<ExpansionSettings>>,
+) {
+    if !source_app::can_identify_foreground_app() {
+        log::warn!(
+            "The foreground app can't be identified on Wayland: snippets limited to apps won't expand \
+             and the denied apps list doesn't apply"
+        );
+    }
     thread::spawn(move || {
         let trigger_map = trigger_map;
-        let buffer = std::cell::RefCell::new(KeyBuffer::new(64));
+        // Shared with the expansion threads, which clear it once they expand
+        let buffer = Arc::new(Mutex::new(KeyBuffer::new(64)));
 
         let callback = move |event: rdev::Event| {
             match event.event_type {
                 rdev::EventType::KeyPress(key) => {
-                    let mut buf = buffer.borrow_mut();
+                    let Ok(mut buf) = buffer.lock() else {
+                        return;
+                    };
 
                     match key {
                         rdev::Key::Backspace => {
                             buf.backspace();
                         }
                         rdev::Key::Return | rdev::Key::Space | rdev::Key::Tab => {
-                            if key == rdev::Key::Space {
-                                buf.push(' ');
-                            }
-
-                            if let Ok(map) = trigger_map.read() {
-                                for (trigger, content) in map.iter() {
-                                    if buf.ends_with(trigger) {
-                                        let tlen = trigger.chars().count();
-                                        let snippet = content.clone();
-                                        thread::spawn(move || {
-                                            let resolved = placeholder::resolve(&snippet);
-                                            injector::inject_snippet(tlen, &resolved);
-                                        });
-                                        buf.clear();
-                                        return;
-                                    }
-                                }
-                            }
-
+                            expand(&app_handle, &buf, &buffer, &trigger_map, &settings, Some(key));
                             buf.clear();
                         }
                         _ => {
@@ -49,20 +50,7 @@ pub fn start_listener(trigger_map: Arc<RwLock<HashMap<String, String>>>) {
                                 }
                             }
 
-                            if let Ok(map) = trigger_map.read() {
-                                for (trigger, content) in map.iter() {
-                                    if buf.ends_with(trigger) {
-                                        let tlen = trigger.chars().count();
-                                        let snippet = content.clone();
-                                        thread::spawn(move || {
-                                            let resolved = placeholder::resolve(&snippet);
-                                            injector::inject_snippet(tlen, &resolved);
-                                        });
-                                        buf.clear();
-                                        return;
-                                    }
-                                }
-                            }
+                            expand(&app_handle, &buf, &buffer, &trigger_map, &settings, None);
                         }
                     }
                 }
@@ -76,3 +64,81 @@ pub fn start_listener(trigger_map: Arc<RwLock<HashMap<String, String>>>) {
         }
     });
 }
+
+/// Replace the trigger the typed keys end with, if any, by its snippet.
+/// `delimiter` is the key just pressed after them, if it was one, which only
+/// delimiter-mode triggers fire on. Nothing expands in the apps denied by
+/// `settings`, nor triggers in apps outside their own scope.
+///
+/// This runs inside the keyboard hook, so only the trie lookup happens here.
+/// Once a keyword was typed, the foreground app is looked up, the scopes
+/// checked and the snippet injected on another thread. That thread clears
+/// `buffer` (which `buf` was read from) only when it does expand, so a
+/// keyword that doesn't fire here still counts toward a longer one.
+fn expand(
+    app_handle: &tauri::AppHandle,
+    buf: &KeyBuffer,
+    buffer: &Arc<Mutex<KeyBuffer>>,
+    trigger_map: &Arc<RwLock<TriggerMatcher>>,
+    settings: &Arc<RwLock<ExpansionSettings>>,
+    delimiter: Option<rdev::Key>,
+) {
+    let mode = match delimiter {
+        Some(_) => ExpandMode::Delimiter,
+        None => ExpandMode::Immediate,
+    };
+    let typed_keyword = trigger_map
+        .read()
+        .is_ok_and(|matcher| matcher.find(buf, mode, |_| true).is_some());
+    if !typed_keyword {
+        return;
+    }
+    let keys = buf.clone();
+
+    let app_handle = app_handle.clone();
+    let buffer = Arc::clone(buffer);
+    let trigger_map = Arc::clone(trigger_map);
+    let settings = Arc::clone(settings);
+    thread::spawn(move || {
+        let app = source_app::foreground_app(None).name;
+        let denied = settings
+            .read()
+            .map(|settings| source_app::is_listed(&settings.denied_apps, &app))
+            .unwrap_or(false);
+        if denied {
+            log::info!("Not expanding snippets in denied app {}", app);
+            return;
+        }
+        let trigger = match trigger_map.read() {
+            Ok(matcher) => matcher.find(&keys, mode, |trigger| trigger.options.allows_app(&app)).cloned(),
+            Err(_) => None,
+        };
+        let Some(trigger) = trigger else {
+            return;
+        };
+        if let Ok(mut buf) = buffer.lock() {
+            buf.clear();
+        }
+
+        let typed = keys.tail(trigger.len);
+        // The delimiter went through before we saw it, so it's erased too
+        let erase = trigger.len + usize::from(delimiter.is_some());
+        let then = match delimiter {
+            Some(key) if !trigger.options.consume_delimiter => enigo_key(key),
+            _ => None,
+        };
+        log::info!("Expanding snippet {:?} ({})", trigger.name, trigger.id);
+        let resolved = placeholder::resolve(&trigger.body);
+        injector::inject_snippet(erase, &trigger.apply_case(&typed, &resolved), then);
+        stats::record_expansion(&app_handle, &trigger.id);
+    });
+}
+
+fn enigo_key(key: rdev::Key) -> Option<enigo::Key> {
+    match key {
+        rdev::Key::Return => Some(enigo::Key::Return),
+        rdev::Key::Space => Some(enigo::Key::Space),
+        rdev::Key::Tab => Some(enigo::Key::Tab),
+        _ => None,
+    }
+}
@@ -0,0 +1,637 @@
+use super::buffer::KeyBuffer;
+use crate::clipboard_history::source_app::is_listed;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// When a trigger expands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExpandMode {
+    /// As soon as the last key of the keyword is typed.
+    #[default]
+    Immediate,
+    /// Only when Space, Return or Tab follows the keyword.
+    Delimiter,
+}
+
+/// How the case of the typed keys is matched against the keyword.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CaseMode {
+    #[default]
+    Sensitive,
+    Insensitive,
+    /// Insensitive, and the expansion follows the typed case: `Sig`
+    /// capitalizes it, `SIG` upper-cases it.
+    Propagate,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TriggerOptions {
+    pub expand: ExpandMode,
+    /// Erase the delimiter along with the keyword instead of typing it again
+    /// after the expansion. Only applies to `ExpandMode::Delimiter`.
+    pub consume_delimiter: bool,
+    /// Only expand when the keyword starts a word, so `sig` doesn't fire
+    /// inside `xsig`.
+    pub word_boundary: bool,
+    pub case: CaseMode,
+    /// Applications the trigger is limited to; empty means all of them.
+    pub allowed_apps: Vec<String>,
+    /// Applications where the trigger never expands.
+    pub denied_apps: Vec<String>,
+}
+
+impl TriggerOptions {
+    /// Whether the trigger may expand in `app`, the foreground app name.
+    pub fn allows_app(&self, app: &str) -> bool {
+        (self.allowed_apps.is_empty() || is_listed(&self.allowed_apps, app)) && !is_listed(&self.denied_apps, app)
+    }
+
+    pub fn is_app_scoped(&self) -> bool {
+        !self.allowed_apps.is_empty() || !self.denied_apps.is_empty()
+    }
+}
+
+/// Version of the `TriggerSet` schema this build reads.
+pub const TRIGGER_SCHEMA_VERSION: u32 = 1;
+
+/// The triggers sent by `sync_triggers`.
+#[derive(Debug, Deserialize)]
+pub struct TriggerSet {
+    pub version: u32,
+    pub triggers: Vec<TriggerDefinition>,
+}
+
+impl TriggerSet {
+    pub fn into_triggers(self) -> Result<Vec<TriggerDefinition>, String> {
+        if self.version != TRIGGER_SCHEMA_VERSION {
+            return Err(format!(
+                "Unsupported trigger schema version {} (expected {})",
+                self.version, TRIGGER_SCHEMA_VERSION
+            ));
+        }
+        Ok(self.triggers)
+    }
+}
+
+/// A snippet's keyword and the body it expands to.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TriggerDefinition {
+    /// Id of the snippet, to attribute expansions to it.
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub keyword: String,
+    pub body: String,
+    /// Decides between triggers with the same keyword; higher wins.
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub options: TriggerOptions,
+}
+
+/// A compiled trigger.
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    pub id: String,
+    pub name: String,
+    pub keyword: String,
+    pub body: String,
+    pub priority: i32,
+    pub options: TriggerOptions,
+    /// Length of `keyword` in chars, i.e. how many keys to erase.
+    pub len: usize,
+}
+
+impl Trigger {
+    /// Whether the keys, which end with the keyword up to case, fire this
+    /// trigger in `mode`.
+    fn accepts(&self, keys: &KeyBuffer, mode: ExpandMode) -> bool {
+        if self.options.expand != mode {
+            return false;
+        }
+        if self.options.case == CaseMode::Sensitive && !keys.ends_with(&self.keyword) {
+            return false;
+        }
+        !self.options.word_boundary || keys.iter_rev().nth(self.len).map_or(true, |ch| !is_word_char(ch))
+    }
+
+    /// Whether `text`, typed as a whole, matches the keyword.
+    fn matches(&self, text: &str) -> bool {
+        match self.options.case {
+            CaseMode::Sensitive => self.keyword == text,
+            CaseMode::Insensitive | CaseMode::Propagate => fold(&self.keyword) == fold(text),
+        }
+    }
+
+    /// Whether this trigger fires on everything `other` fires on, so that if
+    /// it comes first `other` never does.
+    fn covers(&self, other: &Trigger) -> bool {
+        self.options.expand == other.options.expand && self.covers_keys(other)
+    }
+
+    /// Whether this trigger accepts every key sequence and app `other`
+    /// accepts, whatever their expand modes.
+    fn covers_keys(&self, other: &Trigger) -> bool {
+        let scope_covers = !self.options.is_app_scoped()
+            || (self.options.allowed_apps == other.options.allowed_apps
+                && self.options.denied_apps == other.options.denied_apps);
+        scope_covers
+            && (!self.options.word_boundary || other.options.word_boundary)
+            && (self.options.case != CaseMode::Sensitive
+                || (other.options.case == CaseMode::Sensitive && self.keyword == other.keyword))
+    }
+
+    /// Whether some application lets both this trigger and `other` expand.
+    fn shares_app_with(&self, other: &Trigger) -> bool {
+        let (scoped, rest) = match (&self.options, &other.options) {
+            (a, b) if !a.allowed_apps.is_empty() => (a, b),
+            (a, b) if !b.allowed_apps.is_empty() => (b, a),
+            _ => return true,
+        };
+        scoped
+            .allowed_apps
+            .iter()
+            .map(|app| app.trim())
+            .any(|app| scoped.allows_app(app) && rest.allows_app(app))
+    }
+
+    /// `text` in the case of `typed`, the keys that fired this trigger, if
+    /// it propagates case.
+    pub fn apply_case(&self, typed: &str, text: &str) -> String {
+        if self.options.case != CaseMode::Propagate {
+            return text.to_string();
+        }
+        let mut letters = typed.chars().filter(|ch| ch.is_alphabetic());
+        if !letters.next().is_some_and(char::is_uppercase) {
+            return text.to_string();
+        }
+        let rest: Vec<char> = letters.collect();
+        if !rest.is_empty() && rest.iter().all(|ch| ch.is_uppercase()) {
+            return text.to_uppercase();
+        }
+        match text.char_indices().find(|(_, ch)| ch.is_alphabetic()) {
+            Some((i, ch)) => {
+                let upper: String = ch.to_uppercase().collect();
+                format!("{}{}{}", &text[..i], upper, &text[i + ch.len_utf8()..])
+            }
+            None => text.to_string(),
+        }
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// The char the trie is keyed on, so that case-insensitive triggers share a
+/// path with every casing of their keyword.
+fn fold_char(ch: char) -> char {
+    ch.to_lowercase().next().unwrap_or(ch)
+}
+
+fn fold(text: &str) -> String {
+    text.chars().map(fold_char).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictKind {
+    /// `other` has the same keyword, fires on everything this one fires on
+    /// and wins on priority (or by coming first), so this one never expands.
+    Duplicate,
+    /// `other` expands while `keyword` is being typed, so `keyword` never
+    /// fires.
+    Shadowed,
+    /// `keyword` ends with `other`; typing `keyword` expands it rather than
+    /// `other`, the longest match.
+    Overlap,
+}
+
+/// A pair of triggers that get in each other's way.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerConflict {
+    pub kind: ConflictKind,
+    pub keyword: String,
+    pub other: String,
+}
+
+#[derive(Default)]
+struct Node {
+    /// Child per preceding (case-folded) character, sorted by character.
+    children: Vec<(char, usize)>,
+    /// Indices of the triggers whose keyword ends here, by descending
+    /// priority, then in the order they were added.
+    triggers: Vec<usize>,
+}
+
+/// Keywords compiled into a trie of their reversed characters. Matching
+/// walks back from the newest key, so a lookup costs at most the length of
+/// the longest keyword, however many triggers there are.
+pub struct TriggerMatcher {
+    nodes: Vec<Node>,
+    triggers: Vec<Trigger>,
+}
+
+impl Default for TriggerMatcher {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+            triggers: Vec::new(),
+        }
+    }
+}
+
+impl TriggerMatcher {
+    pub fn new(triggers: impl IntoIterator<Item = TriggerDefinition>) -> Self {
+        let mut matcher = Self::default();
+        for trigger in triggers {
+            matcher.insert(trigger);
+        }
+        matcher
+    }
+
+    /// Add a trigger. Of several that accept the same keys, the one with the
+    /// highest priority fires, or the first added on a tie. Empty keywords
+    /// are ignored.
+    pub fn insert(&mut self, trigger: TriggerDefinition) {
+        let TriggerDefinition {
+            id,
+            name,
+            keyword,
+            body,
+            priority,
+            options,
+        } = trigger;
+        if keyword.is_empty() {
+            return;
+        }
+        let mut node = 0;
+        for ch in keyword.chars().rev().map(fold_char) {
+            node = match self.nodes[node].children.binary_search_by_key(&ch, |&(c, _)| c) {
+                Ok(i) => self.nodes[node].children[i].1,
+                Err(i) => {
+                    let child = self.nodes.len();
+                    self.nodes.push(Node::default());
+                    self.nodes[node].children.insert(i, (ch, child));
+                    child
+                }
+            };
+        }
+        let index = self.triggers.len();
+        let candidates = &mut self.nodes[node].triggers;
+        let position = candidates
+            .iter()
+            .position(|&i| self.triggers[i].priority < priority)
+            .unwrap_or(candidates.len());
+        candidates.insert(position, index);
+        let len = keyword.chars().count();
+        self.triggers.push(Trigger {
+            id,
+            name,
+            keyword,
+            body,
+            priority,
+            options,
+            len,
+        });
+    }
+
+    /// Number of triggers, including duplicates that never fire.
+    pub fn len(&self) -> usize {
+        self.triggers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
+    }
+
+    /// The trigger in `mode` whose keyword the typed keys end with, among
+    /// those `in_scope` keeps. Of overlapping keywords (`date` and `ddate`)
+    /// the longest wins, then the highest priority.
+    pub fn find(&self, keys: &KeyBuffer, mode: ExpandMode, in_scope: impl Fn(&Trigger) -> bool) -> Option<&Trigger> {
+        let mut node = 0;
+        let mut found = None;
+        for ch in keys.iter_rev().map(fold_char) {
+            let children = &self.nodes[node].children;
+            match children.binary_search_by_key(&ch, |&(c, _)| c) {
+                Ok(i) => node = children[i].1,
+                Err(_) => break,
+            }
+            let accepted = self.nodes[node]
+                .triggers
+                .iter()
+                .map(|&index| &self.triggers[index])
+                .find(|trigger| trigger.accepts(keys, mode) && in_scope(trigger));
+            if accepted.is_some() {
+                found = accepted;
+            }
+        }
+        found
+    }
+
+    /// Triggers that can't fire, or not always, because of another one.
+    /// Sorted by keyword, then kind.
+    pub fn conflicts(&self) -> Vec<TriggerConflict> {
+        let mut conflicts = Vec::new();
+
+        let mut duplicates = HashSet::new();
+        for node in &self.nodes {
+            for (n, &index) in node.triggers.iter().enumerate() {
+                let trigger = &self.triggers[index];
+                let winner = node.triggers[..n]
+                    .iter()
+                    .map(|&i| &self.triggers[i])
+                    .find(|other| other.covers(trigger));
+                if let Some(winner) = winner {
+                    duplicates.insert(index);
+                    conflicts.push(TriggerConflict {
+                        kind: ConflictKind::Duplicate,
+                        keyword: trigger.keyword.clone(),
+                        other: winner.keyword.clone(),
+                    });
+                    continue;
+                }
+                // An immediate trigger fires on the keyword's last key, before
+                // a delimiter can follow it, whatever the priorities.
+                if trigger.options.expand != ExpandMode::Delimiter {
+                    continue;
+                }
+                let immediate = node
+                    .triggers
+                    .iter()
+                    .map(|&i| &self.triggers[i])
+                    .find(|other| other.options.expand == ExpandMode::Immediate && other.covers_keys(trigger));
+                if let Some(immediate) = immediate {
+                    duplicates.insert(index);
+                    conflicts.push(TriggerConflict {
+                        kind: ConflictKind::Shadowed,
+                        keyword: trigger.keyword.clone(),
+                        other: immediate.keyword.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut by_keyword: HashMap<String, Vec<&Trigger>> = HashMap::new();
+        for (index, trigger) in self.triggers.iter().enumerate() {
+            if !duplicates.contains(&index) {
+                by_keyword.entry(fold(&trigger.keyword)).or_default().push(trigger);
+            }
+        }
+        let others = |text: &str| {
+            by_keyword
+                .get(&fold(text))
+                .into_iter()
+                .flatten()
+                .filter(|other| other.matches(text))
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        for (index, trigger) in self.triggers.iter().enumerate() {
+            if duplicates.contains(&index) {
+                continue;
+            }
+            let mut conflict = |kind, other: &Trigger| {
+                conflicts.push(TriggerConflict {
+                    kind,
+                    keyword: trigger.keyword.clone(),
+                    other: other.keyword.clone(),
+                })
+            };
+            for (i, _) in trigger.keyword.char_indices().skip(1) {
+                let (prefix, suffix) = trigger.keyword.split_at(i);
+                for other in others(prefix) {
+                    if other.options.expand == ExpandMode::Immediate && trigger.shares_app_with(other) {
+                        conflict(ConflictKind::Shadowed, other);
+                    }
+                }
+                // A suffix needing a word boundary only fires after a non-word char
+                let bounded = !prefix.chars().next_back().is_some_and(is_word_char);
+                for other in others(suffix) {
+                    if (other.options.word_boundary && !bounded) || !trigger.shares_app_with(other) {
+                        continue;
+                    }
+                    // Fires on the keyword's last key, before a delimiter can follow it
+                    if other.options.expand == ExpandMode::Immediate && trigger.options.expand == ExpandMode::Delimiter {
+                        conflict(ConflictKind::Shadowed, other);
+                    } else {
+                        conflict(ConflictKind::Overlap, other);
+                    }
+                }
+            }
+        }
+
+        conflicts.sort_by(|a, b| (&a.keyword, a.kind, &a.other).cmp(&(&b.keyword, b.kind, &b.other)));
+        conflicts.dedup();
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(keyword: &str, body: &str, options: TriggerOptions) -> TriggerDefinition {
+        TriggerDefinition {
+            id: body.to_string(),
+            name: String::new(),
+            keyword: keyword.to_string(),
+            body: body.to_string(),
+            priority: 0,
+            options,
+        }
+    }
+
+    fn typed(text: &str) -> KeyBuffer {
+        let mut keys = KeyBuffer::new(64);
+        text.chars().for_each(|ch| keys.push(ch));
+        keys
+    }
+
+    /// Body of the trigger the typed text fires immediately, if any.
+    fn expand<'a>(matcher: &'a TriggerMatcher, text: &str) -> Option<&'a str> {
+        matcher
+            .find(&typed(text), ExpandMode::Immediate, |_| true)
+            .map(|trigger| trigger.body.as_str())
+    }
+
+    fn options(case: CaseMode) -> TriggerOptions {
+        TriggerOptions {
+            case,
+            ..Default::default()
+        }
+    }
+
+    fn conflict(kind: ConflictKind, keyword: &str, other: &str) -> TriggerConflict {
+        TriggerConflict {
+            kind,
+            keyword: keyword.to_string(),
+            other: other.to_string(),
+        }
+    }
+
+    #[test]
+    fn longest_keyword_wins() {
+        let matcher = TriggerMatcher::new([
+            trigger("date", "short", Default::default()),
+            trigger("ddate", "long", Default::default()),
+        ]);
+        assert_eq!(expand(&matcher, "the date"), Some("short"));
+        assert_eq!(expand(&matcher, "the ddate"), Some("long"));
+        assert_eq!(expand(&matcher, "dat"), None);
+    }
+
+    #[test]
+    fn higher_priority_wins_then_first_added() {
+        let mut low = trigger("sig", "low", Default::default());
+        low.priority = -1;
+        let mut high = trigger("sig", "high", Default::default());
+        high.priority = 5;
+        let matcher = TriggerMatcher::new([low.clone(), trigger("sig", "first", Default::default()), high, low]);
+        assert_eq!(expand(&matcher, "sig"), Some("high"));
+
+        let matcher = TriggerMatcher::new([
+            trigger("sig", "first", Default::default()),
+            trigger("sig", "second", Default::default()),
+        ]);
+        assert_eq!(expand(&matcher, "sig"), Some("first"));
+    }
+
+    #[test]
+    fn expand_mode_and_scope_filter_candidates() {
+        let delimited = TriggerOptions {
+            expand: ExpandMode::Delimiter,
+            ..Default::default()
+        };
+        let matcher = TriggerMatcher::new([trigger("sig", "delimited", delimited)]);
+        assert!(matcher.find(&typed("sig"), ExpandMode::Immediate, |_| true).is_none());
+        assert!(matcher.find(&typed("sig"), ExpandMode::Delimiter, |_| true).is_some());
+
+        let matcher = TriggerMatcher::new([
+            trigger("sig", "scoped", Default::default()),
+            trigger("sig", "fallback", Default::default()),
+        ]);
+        let found = matcher.find(&typed("sig"), ExpandMode::Immediate, |t| t.body != "scoped");
+        assert_eq!(found.map(|t| t.body.as_str()), Some("fallback"));
+    }
+
+    #[test]
+    fn case_modes() {
+        let matcher = TriggerMatcher::new([trigger("sig", "exact", options(CaseMode::Sensitive))]);
+        assert_eq!(expand(&matcher, "sig"), Some("exact"));
+        assert_eq!(expand(&matcher, "Sig"), None);
+
+        let matcher = TriggerMatcher::new([trigger("sig", "any", options(CaseMode::Insensitive))]);
+        assert_eq!(expand(&matcher, "SiG"), Some("any"));
+
+        let matcher = TriggerMatcher::new([trigger("Sig", "exact", options(CaseMode::Sensitive))]);
+        assert_eq!(expand(&matcher, "Sig"), Some("exact"));
+        assert_eq!(expand(&matcher, "sig"), None);
+    }
+
+    #[test]
+    fn propagate_follows_typed_case() {
+        let matcher = TriggerMatcher::new([trigger("sig", "best regards", options(CaseMode::Propagate))]);
+        let apply = |text: &str| {
+            let trigger = matcher.find(&typed(text), ExpandMode::Immediate, |_| true).unwrap();
+            trigger.apply_case(&typed(text).tail(trigger.len), &trigger.body)
+        };
+        assert_eq!(apply("sig"), "best regards");
+        assert_eq!(apply("Sig"), "Best regards");
+        assert_eq!(apply("SIG"), "BEST REGARDS");
+        assert_eq!(apply("sIG"), "best regards");
+    }
+
+    #[test]
+    fn word_boundary() {
+        let bounded = TriggerOptions {
+            word_boundary: true,
+            ..Default::default()
+        };
+        let matcher = TriggerMatcher::new([trigger("sig", "bounded", bounded)]);
+        assert_eq!(expand(&matcher, "sig"), Some("bounded"));
+        assert_eq!(expand(&matcher, "a sig"), Some("bounded"));
+        assert_eq!(expand(&matcher, "(sig"), Some("bounded"));
+        assert_eq!(expand(&matcher, "xsig"), None);
+        assert_eq!(expand(&matcher, "_sig"), None);
+    }
+
+    #[test]
+    fn duplicate_conflicts() {
+        let matcher = TriggerMatcher::new([
+            trigger("sig", "a", options(CaseMode::Insensitive)),
+            trigger("SIG", "b", options(CaseMode::Sensitive)),
+        ]);
+        assert_eq!(matcher.conflicts(), vec![conflict(ConflictKind::Duplicate, "SIG", "sig")]);
+
+        // A sensitive trigger doesn't cover an insensitive one
+        let matcher = TriggerMatcher::new([
+            trigger("sig", "a", options(CaseMode::Sensitive)),
+            trigger("sig", "b", options(CaseMode::Insensitive)),
+        ]);
+        assert_eq!(matcher.conflicts(), vec![]);
+
+        // Nor does a trigger limited to an app cover one that isn't
+        let scoped = TriggerOptions {
+            allowed_apps: vec!["Code".to_string()],
+            ..Default::default()
+        };
+        let matcher = TriggerMatcher::new([trigger("sig", "a", scoped), trigger("sig", "b", Default::default())]);
+        assert_eq!(matcher.conflicts(), vec![]);
+    }
+
+    #[test]
+    fn immediate_shadows_delimiter_with_same_keyword() {
+        let delimited = TriggerOptions {
+            expand: ExpandMode::Delimiter,
+            ..Default::default()
+        };
+        let mut delimiter = trigger("sig", "delimited", delimited.clone());
+        delimiter.priority = 10;
+        let matcher = TriggerMatcher::new([delimiter, trigger("sig", "immediate", Default::default())]);
+        assert_eq!(matcher.conflicts(), vec![conflict(ConflictKind::Shadowed, "sig", "sig")]);
+
+        // Unless the immediate one only fires at a word boundary
+        let bounded = TriggerOptions {
+            word_boundary: true,
+            ..Default::default()
+        };
+        let matcher = TriggerMatcher::new([trigger("sig", "delimited", delimited), trigger("sig", "immediate", bounded)]);
+        assert_eq!(matcher.conflicts(), vec![]);
+    }
+
+    #[test]
+    fn prefix_and_suffix_conflicts() {
+        let delimited = TriggerOptions {
+            expand: ExpandMode::Delimiter,
+            ..Default::default()
+        };
+        let bounded = TriggerOptions {
+            word_boundary: true,
+            ..Default::default()
+        };
+        let matcher = TriggerMatcher::new([
+            trigger(";s", "prefix", Default::default()),
+            trigger(";sig", "shadowed", Default::default()),
+            trigger("date", "suffix", Default::default()),
+            trigger("ddate", "overlap", Default::default()),
+            trigger("todo", "bounded", bounded),
+            trigger("xtodo", "no boundary", Default::default()),
+            trigger("addr", "immediate", Default::default()),
+            trigger("/addr", "delimited", delimited),
+        ]);
+        assert_eq!(
+            matcher.conflicts(),
+            vec![
+                conflict(ConflictKind::Shadowed, "/addr", "addr"),
+                conflict(ConflictKind::Shadowed, ";sig", ";s"),
+                conflict(ConflictKind::Overlap, "ddate", "date"),
+            ]
+        );
+    }
+}
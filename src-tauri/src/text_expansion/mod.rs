@@ -1,10 +1,18 @@
 pub mod buffer;
 pub mod injector;
 pub mod listener;
+pub mod matcher;
 pub mod placeholder;
+pub mod settings;
+pub mod snippets;
+pub mod stats;
 
-use std::collections::HashMap;
+use matcher::TriggerMatcher;
+use settings::ExpansionSettings;
 use std::sync::{Arc, RwLock};
 
-/// Shared mapping from trigger string -> snippet content.
-pub struct TriggerMap(pub Arc<RwLock<HashMap<String, String>>>);
+/// Shared matcher over the snippet triggers, rebuilt by `sync_triggers`.
+pub struct TriggerMap(pub Arc<RwLock<TriggerMatcher>>);
+
+/// Expansion settings shared by the commands and the listener.
+pub struct ExpansionSettingsState(pub Arc<RwLock<ExpansionSettings>>);
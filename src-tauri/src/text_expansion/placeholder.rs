@@ -1,7 +1,7 @@
 use chrono::Local;
 use uuid::Uuid;
 
-use super::injector::get_clipboard_text;
+use super::injector::{get_clipboard_text, get_primary_selection_text};
 
 /// Replace dynamic placeholders in a snippet body.
 ///
@@ -11,6 +11,7 @@ use super::injector::get_clipboard_text;
 /// - `{datetime}`  → e.g. "12 Feb 2026 3:05 PM"
 /// - `{day}`       → e.g. "Thursday"
 /// - `{clipboard}` → current clipboard content
+/// - `{selection}` → currently highlighted text (PRIMARY selection, Linux only)
 /// - `{uuid}`      → unique UUID v4 per occurrence
 pub fn resolve(body: &str) -> String {
     let now = Local::now();
@@ -32,6 +33,12 @@ pub fn resolve(body: &str) -> String {
         result = result.replace("{clipboard}", &clip);
     }
 
+    // {selection} → PRIMARY selection (empty where the platform has none)
+    if result.contains("{selection}") {
+        let selection = get_primary_selection_text().unwrap_or_default();
+        result = result.replace("{selection}", &selection);
+    }
+
     // {uuid} → unique UUID v4 per occurrence (each gets its own UUID)
     while result.contains("{uuid}") {
         let id = Uuid::new_v4().to_string();
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "expansion_settings.json";
+
+/// Text expansion behaviour shared by every snippet, persisted as JSON in the
+/// app data directory. Missing fields take their defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExpansionSettings {
+    /// Applications where no snippet expands (terminals, games...), matched
+    /// case-insensitively against the foreground app name.
+    pub denied_apps: Vec<String>,
+}
+
+fn settings_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(FILE_NAME)
+}
+
+/// Load settings from disk, falling back to defaults.
+pub fn load(app_data_dir: &Path) -> ExpansionSettings {
+    match std::fs::read_to_string(settings_path(app_data_dir)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            log::error!("Invalid expansion settings, using defaults: {}", e);
+            ExpansionSettings::default()
+        }),
+        Err(_) => ExpansionSettings::default(),
+    }
+}
+
+/// Save settings to disk.
+pub fn save(app_data_dir: &Path, settings: &ExpansionSettings) -> Result<(), String> {
+    std::fs::create_dir_all(app_data_dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(settings_path(app_data_dir), json).map_err(|e| e.to_string())
+}
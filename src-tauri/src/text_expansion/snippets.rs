@@ -0,0 +1,103 @@
+use super::matcher::{TriggerDefinition, TriggerOptions};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+const FILE_NAME: &str = "snippets.json";
+
+/// A text expansion snippet: typing `keyword` expands to `body`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+    pub id: String,
+    pub name: String,
+    /// Name of a frontend icon.
+    pub icon: String,
+    pub keyword: String,
+    pub body: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Decides between snippets with the same keyword; higher wins.
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub options: TriggerOptions,
+}
+
+impl Snippet {
+    pub fn new(name: String, icon: &str, keyword: String, body: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            icon: icon.to_string(),
+            keyword,
+            body,
+            tags: Vec::new(),
+            pinned: false,
+            priority: 0,
+            options: TriggerOptions::default(),
+        }
+    }
+
+    pub fn trigger(&self) -> TriggerDefinition {
+        TriggerDefinition {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            keyword: self.keyword.clone(),
+            body: self.body.clone(),
+            priority: self.priority,
+            options: self.options.clone(),
+        }
+    }
+}
+
+/// The snippets, owned by the backend so commands can add to them.
+pub struct SnippetStore(pub RwLock<Vec<Snippet>>);
+
+fn snippets_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(FILE_NAME)
+}
+
+/// Load the snippets from disk. Returns nothing before the frontend first
+/// saved them.
+pub fn load(app_data_dir: &Path) -> Vec<Snippet> {
+    match std::fs::read_to_string(snippets_path(app_data_dir)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            log::error!("Invalid snippets file: {}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Save the snippets to disk.
+pub fn save(app_data_dir: &Path, snippets: &[Snippet]) -> Result<(), String> {
+    std::fs::create_dir_all(app_data_dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(snippets).map_err(|e| e.to_string())?;
+    std::fs::write(snippets_path(app_data_dir), json).map_err(|e| e.to_string())
+}
+
+/// The triggers of `snippets`, as `sync_triggers` receives them.
+pub fn triggers(snippets: &[Snippet]) -> Vec<TriggerDefinition> {
+    snippets
+        .iter()
+        .filter(|s| !s.keyword.is_empty() && !s.body.is_empty())
+        .map(Snippet::trigger)
+        .collect()
+}
+
+/// Check that `keyword` can be used for a new snippet.
+pub fn validate_keyword(keyword: &str, snippets: &[Snippet]) -> Result<(), String> {
+    if keyword.is_empty() {
+        return Err("Keyword is empty".into());
+    }
+    if keyword.chars().any(char::is_whitespace) {
+        return Err("Keywords can't contain whitespace".into());
+    }
+    if snippets.iter().any(|s| s.keyword == keyword) {
+        return Err(format!("Another snippet already uses {}", keyword));
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use super::snippets::Snippet;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+const FILE_NAME: &str = "snippet_stats.json";
+
+/// How often a snippet was expanded by typing its keyword.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SnippetStats {
+    pub expansions: u64,
+    pub last_expanded_at: Option<i64>,
+}
+
+/// Expansion stats by snippet id.
+pub struct SnippetStatsStore(pub Mutex<HashMap<String, SnippetStats>>);
+
+/// Payload of the "snippet-expanded" event.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SnippetExpanded {
+    id: String,
+    stats: SnippetStats,
+}
+
+fn stats_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(FILE_NAME)
+}
+
+pub fn load(app_data_dir: &Path) -> HashMap<String, SnippetStats> {
+    match std::fs::read_to_string(stats_path(app_data_dir)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            log::error!("Invalid snippet stats file: {}", e);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    }
+}
+
+pub fn save(app_data_dir: &Path, stats: &HashMap<String, SnippetStats>) -> Result<(), String> {
+    std::fs::create_dir_all(app_data_dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(stats).map_err(|e| e.to_string())?;
+    std::fs::write(stats_path(app_data_dir), json).map_err(|e| e.to_string())
+}
+
+/// Count an expansion of the snippet `id`, save the stats and emit
+/// "snippet-expanded".
+pub fn record_expansion(app_handle: &tauri::AppHandle, id: &str) {
+    let Some(store) = app_handle.try_state::<SnippetStatsStore>() else {
+        return;
+    };
+    let Ok(mut stats) = store.0.lock() else {
+        return;
+    };
+    let entry = stats.entry(id.to_string()).or_default();
+    entry.expansions += 1;
+    entry.last_expanded_at = Some(chrono::Local::now().timestamp_millis());
+    let event = SnippetExpanded {
+        id: id.to_string(),
+        stats: entry.clone(),
+    };
+
+    if let Ok(dir) = app_handle.path().app_data_dir() {
+        if let Err(e) = save(&dir, &stats) {
+            log::error!("Failed to save snippet stats: {}", e);
+        }
+    }
+    let _ = app_handle.emit("snippet-expanded", event);
+}
+
+/// Drop the stats of snippets that no longer exist. Returns whether any were
+/// dropped.
+pub fn prune(stats: &mut HashMap<String, SnippetStats>, snippets: &[Snippet]) -> bool {
+    let before = stats.len();
+    stats.retain(|id, _| snippets.iter().any(|s| &s.id == id));
+    stats.len() != before
+}